@@ -0,0 +1,8 @@
+# The largest machine the 16-bit addresses allow, for stress tests.
+num_registers = 16
+ram_len = 65536
+stack_len = 256
+max_procs = 64
+time_slice_ms = 5
+alloc = buddy
+replacer = clock
@@ -0,0 +1,10 @@
+# A machine small enough to follow by hand: every structure fits in a few
+# screens of `dump` output, and two programs are enough to force page
+# replacement.
+num_registers = 4
+ram_len = 1024
+stack_len = 32
+max_procs = 4
+time_slice_ms = 1
+alloc = first-fit
+replacer = fifo
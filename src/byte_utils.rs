@@ -64,3 +64,35 @@ pub fn get_u16_at(bytes: &[u8], addr: usize) -> AccessResult<u16> {
 pub fn is_aligned(addr: usize, alignment: usize) -> bool {
     addr % alignment == 0
 }
+
+/// Returns a hex dump of `bytes`, 16 per line, each line prefixed by the address of its first
+/// byte counting from `start`.
+pub fn dump_hex(start: usize, bytes: &[u8]) -> String {
+    bytes.chunks(16)
+        .enumerate()
+        .map(|(line_idx, line)| {
+            let hex = line.iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!("0x{:04x}  {}", start + line_idx * 16, hex)
+        })
+        .fold(String::new(), |acc, line| acc + &line + "\n")
+}
+
+/// Writes `bytes` as two lowercase hexadecimal digits each.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parses bytes written as two hexadecimal digits each.
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digits = s.bytes()
+        .map(|byte| (byte as char).to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(format!("{}: not hexadecimal", s))?;
+    if digits.len() % 2 != 0 {
+        return Err(format!("{}: odd number of hexadecimal digits", s));
+    }
+    Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
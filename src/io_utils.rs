@@ -1,38 +1,35 @@
-use std::io::{self, Read, Write};
-
-pub fn read_byte_from_stdin() -> u8 {
-    read_byte(&mut io::stdin())
-}
+use std::io::{Read, Write};
 
+/// Returns the next byte of the input, or 0 once the input ended.
 pub fn read_byte<R: Read>(reader: &mut R) -> u8 {
     let mut buf = [0];
-    reader.read_exact(&mut buf);
-    buf[0]
-}
-
-pub fn read_char<R: Read>(reader: &mut R) -> char {
-    read_byte(reader) as char
+    match reader.read_exact(&mut buf) {
+        Ok(()) => buf[0],
+        Err(_) => 0,
+    }
 }
 
-/// Returns a trimmed line read from the input.
-/// Note: Inefficient as it reads only one byte at a time.
-pub fn read_line<R: Read>(reader: &mut R) -> String {
+/// Returns a trimmed line read from the input, or `None` if the input ended before a line did.
+/// A last line without a line feed is still returned.
+pub fn try_read_line<R: Read>(reader: &mut R) -> Option<String> {
     let mut input_text = String::new();
+    let mut buf = [0];
     loop {
-        let ch = read_char(reader);
-        if ch == '\n' {
-            break;
-        } else {
-            input_text.push(ch);
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) if input_text.is_empty() => return None,
+            Ok(0) | Err(_) => break,
+            Ok(_) if buf[0] == b'\n' => break,
+            Ok(_) => input_text.push(buf[0] as char),
         }
     }
-    let trimmed = input_text.trim();
-    trimmed.to_string()
+    Some(input_text.trim().to_string())
 }
 
+/// Writes `msg` and flushes, logging any error.
 pub fn write<W: Write>(writer: &mut W, msg: &str) {
-    writer.write_all(msg.as_bytes());
-    writer.flush();
+    if let Err(err) = writer.write_all(msg.as_bytes()).and_then(|_| writer.flush()) {
+        warn!("Cannot write to the console: {}", err);
+    }
 }
 
 pub fn write_ln<W: Write>(writer: &mut W, msg: &str) {
@@ -0,0 +1,26 @@
+//! A simulated operating system.
+//!
+//! Boot a `System` from a `Config`, load programs with `System::load` or `System::load_file`,
+//! then `step`, `run` or `start` them. A `Console` holds the streams that programs read from
+//! and print to: `set_console` replaces the standard ones and `attach_console` gives a process
+//! its own. `get_proc_info` inspects a process.
+
+#[macro_use]
+extern crate enum_primitive;
+#[macro_use]
+extern crate log;
+extern crate time;
+
+mod byte_utils;
+mod io_utils;
+mod time_utils;
+pub mod sh;
+pub mod os;
+
+pub use os::{CachePolicy, CacheStats, Config, Console, CycleCosts, DebugInfo, Debugger, Event,
+             EventKind, Executable, ExecResult, ExitedProc, GdbStub, InstructionType,
+             OutputBuffer, ProcInfo, ProcSnapshot, ProcessStatus, RecordedProgram, RecordedSlice,
+             RunLog, SectionKind, Snapshot, StopReason, Symbol, System, Timeline, TraceFilter,
+             Tracer, CONFIG_ALIASES, CONFIG_KEYS, DATA_ADDR_BASE, EXE_VERSION, MAX_EXITED_PROCS,
+             MAX_TIMELINE_EVENTS, RUN_LOG_VERSION, SNAPSHOT_VERSION};
+pub use sh::Shell;
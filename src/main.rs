@@ -1,38 +1,185 @@
-#![allow(dead_code)]
-#![allow(unused_must_use)]
-
-#[macro_use]
-extern crate enum_primitive;
 #[macro_use]
 extern crate log;
 extern crate log4rs;
-extern crate time;
-
-mod byte_utils;
-mod io_utils;
-mod time_utils;
-mod sh;
-mod os;
+extern crate snoss;
 
-use os::System;
-use sh::Shell;
+use std::env;
+use std::fs;
+use std::process;
+use snoss::{Config, Shell, System, Tracer, CONFIG_ALIASES, CONFIG_KEYS};
 
 const PROMPT: &'static str = "> ";
 
+const USAGE: &str = "Usage: snoss [OPTIONS]
+
+Options:
+    --run FILE        Execute FILE of the file system and exit with its status
+    --script FILE     Run the shell commands of the host file FILE
+    --stdin FILE      Feed the host file FILE to the programs' input
+    --log CONFIG      Configure logging with the host file CONFIG (default: log.yaml)
+    --trace FILE      Record every executed instruction to the host file FILE
+    --trace-filter F  Only trace what F allows, e.g. pid=1,addrs=0x10..0x40
+    --profile FILE    Boot with the machine parameters of the host file FILE
+    --KEY VALUE       Override a machine parameter, where KEY is one of
+                      num-registers, ram-len, stack-len, max-procs, time-slice-ms,
+                      slice-cycles, cycle-costs, alloc, replacer, cache-len,
+                      cache-line-len, cache-ways, cache-policy, fs
+                      (--replace is still accepted for --replacer)
+    --help            Print this message";
+
+/// The command line options.
+struct Options {
+    run: Option<String>,
+    script: Option<String>,
+    stdin: Option<String>,
+    log: String,
+    trace: Option<String>,
+    trace_filter: String,
+    profile: Option<String>,
+    /// The machine parameters to override, as `(key, value)`.
+    overrides: Vec<(String, String)>,
+}
+
 fn main() {
+    let opts = match parse_args(env::args().skip(1).collect()) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            println!("Err: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
     // Init logging
-    log4rs::init_file("log.yaml", Default::default()).unwrap();
+    if let Err(err) = log4rs::init_file(&opts.log, Default::default()) {
+        println!("Err: {}: {:?}", opts.log, err);
+        process::exit(2);
+    }
 
     info!("Booting...");
 
-    let system = System::init();
+    let mut system = match boot(&opts) {
+        Ok(system) => system,
+        Err(err) => {
+            println!("Err: {}", err);
+            process::exit(2);
+        }
+    };
 
-    let prompt = PROMPT.to_string();
+    if let Some(ref path) = opts.stdin {
+        match fs::File::open(path) {
+            Ok(file) => {
+                let console = system.get_console().with_input(Box::new(file));
+                system.set_console(console);
+            }
+            Err(err) => {
+                println!("Err: {}: {}", path, err);
+                process::exit(2);
+            }
+        }
+    }
 
-    let mut shell = Shell::new(system, prompt);
+    if let Some(ref path) = opts.trace {
+        match opts.trace_filter.parse().and_then(|filter| Tracer::create(path, filter)) {
+            Ok(tracer) => system.set_tracer(Some(tracer)),
+            Err(err) => {
+                println!("Err: {}", err);
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(ref file_name) = opts.run {
+        let code = match system.exec(file_name, true) {
+            Ok(handle) => handle.join().map(|result| result.get_exit_code()).unwrap_or(1),
+            Err(err) => {
+                println!("Err: {}", err);
+                1
+            }
+        };
+        info!("Shutting down...");
+        process::exit(code);
+    }
+
+    let prompt = PROMPT.to_string();
+    let mut shell = match opts.script {
+        Some(ref path) => {
+            match fs::File::open(path) {
+                Ok(file) => Shell::with_script(system, prompt, Box::new(file)),
+                Err(err) => {
+                    println!("Err: {}: {}", path, err);
+                    process::exit(2);
+                }
+            }
+        }
+        None => Shell::new(system, prompt),
+    };
     let result = shell.start();
     if result.is_err() {
         println!("Err: {:?}", result.err().unwrap());
     }
     info!("Shutting down...");
 }
+
+/// Returns the options of `args`, or `None` if the usage was asked for.
+fn parse_args(args: Vec<String>) -> Result<Option<Options>, String> {
+    let mut opts = Options {
+        run: None,
+        script: None,
+        stdin: None,
+        log: "log.yaml".to_string(),
+        trace: None,
+        trace_filter: String::new(),
+        profile: None,
+        overrides: vec![],
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" {
+            return Ok(None);
+        }
+        if !arg.starts_with("--") {
+            return Err(format!("{}: unexpected argument", arg));
+        }
+        let val = args.next().ok_or(format!("{} requires a value", arg))?;
+        match arg.as_str() {
+            "--run" => opts.run = Some(val),
+            "--script" => opts.script = Some(val),
+            "--stdin" => opts.stdin = Some(val),
+            "--log" => opts.log = val,
+            "--trace" => opts.trace = Some(val),
+            "--trace-filter" => opts.trace_filter = val,
+            "--profile" => opts.profile = Some(val),
+            _ => {
+                let key = arg[2..].replace('-', "_");
+                if !CONFIG_KEYS.contains(&key.as_str()) &&
+                   !CONFIG_ALIASES.iter().any(|&(alias, _)| alias == key) {
+                    return Err(format!("{}: unknown option", arg));
+                }
+                opts.overrides.push((key, val));
+            }
+        }
+    }
+    if opts.run.is_some() && opts.script.is_some() {
+        return Err("--run and --script cannot be used together".to_string());
+    }
+    Ok(Some(opts))
+}
+
+/// Boots a machine from the profile given with `--profile FILE` (the defaults otherwise),
+/// overriding any parameter with `--KEY VALUE`, where `KEY` is a parameter name with dashes for
+/// underscores (e.g. `--ram-len 4096 --alloc buddy`).
+fn boot(opts: &Options) -> Result<System, String> {
+    let mut config = match opts.profile {
+        Some(ref path) => Config::from_profile(path)?,
+        None => Config::default(),
+    };
+    for (key, val) in &opts.overrides {
+        config.set(key, val)?;
+    }
+    info!("Booting with:\n{}", config);
+    System::init(config)
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::str::FromStr;
+use super::{BestFit, Buddy, FirstFit, NextFit};
+
+/// A contiguous range of RAM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Block {
+    pub addr: usize,
+    pub len: usize,
+}
+
+impl Block {
+    pub fn new(addr: usize, len: usize) -> Self {
+        Block {
+            addr,
+            len,
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.addr + self.len
+    }
+}
+
+/// Places variable-sized blocks within a region of RAM.
+pub trait Allocator: fmt::Debug + Send {
+    /// Returns the address of a block of at least `len` bytes.
+    /// Returns `None` if no free block is large enough.
+    fn alloc(&mut self, len: usize) -> Option<usize>;
+
+    /// Returns the block starting at `addr` to the free pool.
+    fn dealloc(&mut self, addr: usize);
+
+    /// Returns the region managed by the allocator.
+    fn get_region(&self) -> Block;
+
+    /// Returns every free block, sorted by address.
+    fn get_free_blks(&self) -> Vec<Block>;
+
+    /// Returns every allocated block, sorted by address, along with the length originally
+    /// requested for it.
+    fn get_used_blks(&self) -> Vec<(Block, usize)>;
+
+    fn get_kind(&self) -> AllocatorKind;
+
+    fn get_stats(&self) -> AllocStats {
+        let free_blks = self.get_free_blks();
+        let used_blks = self.get_used_blks();
+        AllocStats {
+            kind: self.get_kind(),
+            total: self.get_region().len,
+            used: used_blks.iter().map(|&(blk, _)| blk.len).sum(),
+            requested: used_blks.iter().map(|&(_, requested)| requested).sum(),
+            free: free_blks.iter().map(|blk| blk.len).sum(),
+            num_used_blks: used_blks.len(),
+            num_free_blks: free_blks.len(),
+            largest_free_blk: free_blks.iter().map(|blk| blk.len).max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AllocatorKind {
+    FirstFit,
+    BestFit,
+    NextFit,
+    Buddy,
+}
+
+impl AllocatorKind {
+    /// Returns an allocator of this kind managing `region`.
+    pub fn new_allocator(&self, region: Block) -> Box<dyn Allocator> {
+        match *self {
+            AllocatorKind::FirstFit => Box::new(FirstFit::new(region)),
+            AllocatorKind::BestFit => Box::new(BestFit::new(region)),
+            AllocatorKind::NextFit => Box::new(NextFit::new(region)),
+            AllocatorKind::Buddy => Box::new(Buddy::new(region)),
+        }
+    }
+}
+
+impl FromStr for AllocatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-fit" => Ok(AllocatorKind::FirstFit),
+            "best-fit" => Ok(AllocatorKind::BestFit),
+            "next-fit" => Ok(AllocatorKind::NextFit),
+            "buddy" => Ok(AllocatorKind::Buddy),
+            _ => {
+                Err(format!("{}: unknown allocator (expected first-fit, best-fit, next-fit or \
+                             buddy)",
+                            s))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AllocatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            AllocatorKind::FirstFit => "first-fit",
+            AllocatorKind::BestFit => "best-fit",
+            AllocatorKind::NextFit => "next-fit",
+            AllocatorKind::Buddy => "buddy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A snapshot of how an allocator's region is used.
+#[derive(Clone, Copy, Debug)]
+pub struct AllocStats {
+    pub kind: AllocatorKind,
+    pub total: usize,
+    /// Bytes reserved by allocated blocks.
+    pub used: usize,
+    /// Bytes actually asked for; `used - requested` is lost to internal fragmentation.
+    pub requested: usize,
+    pub free: usize,
+    pub num_used_blks: usize,
+    pub num_free_blks: usize,
+    pub largest_free_blk: usize,
+}
+
+impl AllocStats {
+    /// Returns the fraction of free memory that cannot be used for a request of the largest
+    /// free block's size: `1 - largest_free_blk / free`.
+    pub fn get_external_fragmentation(&self) -> f64 {
+        if self.free == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_blk as f64 / self.free as f64
+        }
+    }
+
+    pub fn get_internal_fragmentation(&self) -> usize {
+        self.used - self.requested
+    }
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "allocator: {}, total: {}, used: {} ({} blocks, {} requested), free: {} ({} \
+                blocks), largest free block: {}, external fragmentation: {:.3}",
+               self.kind,
+               self.total,
+               self.used,
+               self.num_used_blks,
+               self.requested,
+               self.free,
+               self.num_free_blks,
+               self.largest_free_blk,
+               self.get_external_fragmentation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Allocator, AllocatorKind, Block};
+
+    /// Allocates 10, 30, 10, 20 and 10 bytes of a 100-byte region, then frees the 30- and
+    /// 20-byte blocks, leaving 10..40, 50..70 and 80..100 free.
+    fn fragment(kind: AllocatorKind) -> Box<dyn Allocator> {
+        let mut allocator = kind.new_allocator(Block::new(0, 100));
+        let addrs = [10, 30, 10, 20, 10]
+            .iter()
+            .map(|len| allocator.alloc(*len).unwrap())
+            .collect::<Vec<usize>>();
+        assert_eq!(addrs, vec![0, 10, 40, 50, 70]);
+        allocator.dealloc(10);
+        allocator.dealloc(50);
+        allocator
+    }
+
+    #[test]
+    fn fit_policies_choose_different_blocks() {
+        assert_eq!(fragment(AllocatorKind::FirstFit).alloc(15), Some(10));
+        assert_eq!(fragment(AllocatorKind::BestFit).alloc(15), Some(50));
+        // The last block ended at 80.
+        assert_eq!(fragment(AllocatorKind::NextFit).alloc(15), Some(80));
+    }
+
+    #[test]
+    fn free_memory_fragments_and_coalesces() {
+        let mut allocator = fragment(AllocatorKind::FirstFit);
+        let stats = allocator.get_stats();
+        assert_eq!((stats.free, stats.num_free_blks, stats.largest_free_blk), (70, 3, 30));
+        assert!((stats.get_external_fragmentation() - 4.0 / 7.0).abs() < 1e-9);
+        assert_eq!(allocator.alloc(31), None);
+        allocator.dealloc(40);
+        assert_eq!(allocator.get_free_blks(),
+                   vec![Block::new(10, 60), Block::new(80, 20)]);
+        assert_eq!(allocator.alloc(31), Some(10));
+    }
+
+    #[test]
+    fn buddies_split_and_merge() {
+        let mut allocator = AllocatorKind::Buddy.new_allocator(Block::new(0, 128));
+        assert_eq!(allocator.alloc(20), Some(0));
+        assert_eq!(allocator.alloc(16), Some(32));
+        assert_eq!(allocator.get_free_blks(),
+                   vec![Block::new(48, 16), Block::new(64, 64)]);
+        assert_eq!(allocator.get_stats().get_internal_fragmentation(), 12);
+        allocator.dealloc(0);
+        allocator.dealloc(32);
+        assert_eq!(allocator.get_free_blks(), vec![Block::new(0, 128)]);
+        // The last 4 bytes are too small for a block of their own.
+        let allocator = AllocatorKind::Buddy.new_allocator(Block::new(0, 100));
+        assert_eq!(allocator.get_free_blks(),
+                   vec![Block::new(0, 64), Block::new(64, 32)]);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet};
+use super::{Allocator, AllocatorKind, Block};
+
+/// The smallest block the buddy allocator hands out.
+pub const MIN_BUDDY_LEN: usize = 16;
+
+/// Splits the region into power-of-two blocks; a freed block is merged with its buddy whenever
+/// the buddy is free as well.
+#[derive(Debug)]
+pub struct Buddy {
+    region: Block,
+    /// Free block addresses keyed by block length.
+    free: BTreeMap<usize, BTreeSet<usize>>,
+    /// Allocated blocks by address, along with the length that was requested.
+    used: BTreeMap<usize, (Block, usize)>,
+}
+
+impl Buddy {
+    /// Covers the region with the largest power-of-two blocks that fit, in decreasing order so
+    /// that every block is aligned to its length relative to the start of the region.
+    /// Any remainder smaller than `MIN_BUDDY_LEN` is never handed out.
+    pub fn new(region: Block) -> Self {
+        let mut buddy = Buddy {
+            region,
+            free: BTreeMap::new(),
+            used: BTreeMap::new(),
+        };
+        let mut offset = 0;
+        while region.len - offset >= MIN_BUDDY_LEN {
+            let len = prev_power_of_two(region.len - offset);
+            buddy.insert_free(Block::new(region.addr + offset, len));
+            offset += len;
+        }
+        buddy
+    }
+
+    fn insert_free(&mut self, blk: Block) {
+        self.free.entry(blk.len).or_default().insert(blk.addr);
+    }
+
+    fn remove_free(&mut self, blk: Block) -> bool {
+        match self.free.get_mut(&blk.len) {
+            Some(addrs) => addrs.remove(&blk.addr),
+            None => false,
+        }
+    }
+
+    fn get_buddy(&self, blk: Block) -> Block {
+        let offset = blk.addr - self.region.addr;
+        Block::new(self.region.addr + (offset ^ blk.len), blk.len)
+    }
+}
+
+impl Allocator for Buddy {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let wanted_len = len.next_power_of_two().max(MIN_BUDDY_LEN);
+        let (&found_len, addrs) = self.free
+            .range_mut(wanted_len..)
+            .find(|(_, addrs)| !addrs.is_empty())?;
+        let addr = *addrs.iter().next().unwrap();
+        addrs.remove(&addr);
+        let mut blk = Block::new(addr, found_len);
+        while blk.len > wanted_len {
+            blk.len /= 2;
+            self.insert_free(Block::new(blk.addr + blk.len, blk.len));
+        }
+        self.used.insert(addr, (blk, len));
+        Some(addr)
+    }
+
+    fn dealloc(&mut self, addr: usize) {
+        let mut blk = match self.used.remove(&addr) {
+            Some((blk, _)) => blk,
+            None => return,
+        };
+        loop {
+            let buddy = self.get_buddy(blk);
+            if !self.remove_free(buddy) {
+                break;
+            }
+            blk = Block::new(blk.addr.min(buddy.addr), blk.len * 2);
+        }
+        self.insert_free(blk);
+    }
+
+    fn get_region(&self) -> Block {
+        self.region
+    }
+
+    fn get_free_blks(&self) -> Vec<Block> {
+        let mut blks = self.free
+            .iter()
+            .flat_map(|(len, addrs)| addrs.iter().map(move |addr| Block::new(*addr, *len)))
+            .collect::<Vec<Block>>();
+        blks.sort_by_key(|blk| blk.addr);
+        blks
+    }
+
+    fn get_used_blks(&self) -> Vec<(Block, usize)> {
+        self.used.values().cloned().collect()
+    }
+
+    fn get_kind(&self) -> AllocatorKind {
+        AllocatorKind::Buddy
+    }
+}
+
+fn prev_power_of_two(n: usize) -> usize {
+    let next = n.next_power_of_two();
+    if next == n { n } else { next / 2 }
+}
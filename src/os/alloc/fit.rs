@@ -0,0 +1,137 @@
+use super::{Allocator, AllocatorKind, Block};
+use super::free_list::FreeList;
+
+/// Places each block in the lowest-addressed free block that is large enough.
+#[derive(Debug)]
+pub struct FirstFit {
+    blks: FreeList,
+}
+
+/// Places each block in the smallest free block that is large enough.
+#[derive(Debug)]
+pub struct BestFit {
+    blks: FreeList,
+}
+
+/// Like first-fit, but resumes searching where the previous allocation ended.
+#[derive(Debug)]
+pub struct NextFit {
+    blks: FreeList,
+    rover: usize,
+}
+
+impl FirstFit {
+    pub fn new(region: Block) -> Self {
+        FirstFit { blks: FreeList::new(region) }
+    }
+}
+
+impl BestFit {
+    pub fn new(region: Block) -> Self {
+        BestFit { blks: FreeList::new(region) }
+    }
+}
+
+impl NextFit {
+    pub fn new(region: Block) -> Self {
+        NextFit {
+            blks: FreeList::new(region),
+            rover: region.addr,
+        }
+    }
+}
+
+impl Allocator for FirstFit {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let idx = self.blks.get_free().iter().position(|blk| blk.len >= len)?;
+        Some(self.blks.take(idx, len))
+    }
+
+    fn dealloc(&mut self, addr: usize) {
+        self.blks.release(addr);
+    }
+
+    fn get_region(&self) -> Block {
+        self.blks.get_region()
+    }
+
+    fn get_free_blks(&self) -> Vec<Block> {
+        self.blks.get_free().to_vec()
+    }
+
+    fn get_used_blks(&self) -> Vec<(Block, usize)> {
+        self.blks.get_used()
+    }
+
+    fn get_kind(&self) -> AllocatorKind {
+        AllocatorKind::FirstFit
+    }
+}
+
+impl Allocator for BestFit {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let (idx, _) = self.blks
+            .get_free()
+            .iter()
+            .enumerate()
+            .filter(|&(_, blk)| blk.len >= len)
+            .min_by_key(|&(_, blk)| blk.len)?;
+        Some(self.blks.take(idx, len))
+    }
+
+    fn dealloc(&mut self, addr: usize) {
+        self.blks.release(addr);
+    }
+
+    fn get_region(&self) -> Block {
+        self.blks.get_region()
+    }
+
+    fn get_free_blks(&self) -> Vec<Block> {
+        self.blks.get_free().to_vec()
+    }
+
+    fn get_used_blks(&self) -> Vec<(Block, usize)> {
+        self.blks.get_used()
+    }
+
+    fn get_kind(&self) -> AllocatorKind {
+        AllocatorKind::BestFit
+    }
+}
+
+impl Allocator for NextFit {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let idx = {
+            let free = self.blks.get_free();
+            // Search from the first block at or after the rover, wrapping around once.
+            let start = free.iter().position(|blk| blk.end() > self.rover).unwrap_or(0);
+            (start..free.len())
+                .chain(0..start)
+                .find(|idx| free[*idx].len >= len)?
+        };
+        let addr = self.blks.take(idx, len);
+        self.rover = addr + len;
+        Some(addr)
+    }
+
+    fn dealloc(&mut self, addr: usize) {
+        self.blks.release(addr);
+    }
+
+    fn get_region(&self) -> Block {
+        self.blks.get_region()
+    }
+
+    fn get_free_blks(&self) -> Vec<Block> {
+        self.blks.get_free().to_vec()
+    }
+
+    fn get_used_blks(&self) -> Vec<(Block, usize)> {
+        self.blks.get_used()
+    }
+
+    fn get_kind(&self) -> AllocatorKind {
+        AllocatorKind::NextFit
+    }
+}
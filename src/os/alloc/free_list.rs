@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+use super::Block;
+
+/// Bookkeeping shared by the "fit" allocators: an address-ordered list of free blocks that is
+/// coalesced on every release.
+#[derive(Debug)]
+pub struct FreeList {
+    region: Block,
+    free: Vec<Block>,
+    /// Allocated blocks by address, along with the length that was requested.
+    used: BTreeMap<usize, (Block, usize)>,
+}
+
+impl FreeList {
+    pub fn new(region: Block) -> Self {
+        let free = if region.len > 0 { vec![region] } else { Vec::new() };
+        FreeList {
+            region,
+            free,
+            used: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_region(&self) -> Block {
+        self.region
+    }
+
+    pub fn get_free(&self) -> &[Block] {
+        &self.free
+    }
+
+    pub fn get_used(&self) -> Vec<(Block, usize)> {
+        self.used.values().cloned().collect()
+    }
+
+    /// Carves `len` bytes from the front of the free block at `idx` and returns their address.
+    pub fn take(&mut self, idx: usize, len: usize) -> usize {
+        let blk = self.free[idx];
+        if blk.len == len {
+            self.free.remove(idx);
+        } else {
+            self.free[idx] = Block::new(blk.addr + len, blk.len - len);
+        }
+        self.used.insert(blk.addr, (Block::new(blk.addr, len), len));
+        blk.addr
+    }
+
+    /// Returns the block at `addr` to the list, merging it with its free neighbours.
+    pub fn release(&mut self, addr: usize) {
+        let blk = match self.used.remove(&addr) {
+            Some((blk, _)) => blk,
+            None => return,
+        };
+        let idx = self.free
+            .iter()
+            .position(|free_blk| free_blk.addr > blk.addr)
+            .unwrap_or(self.free.len());
+        self.free.insert(idx, blk);
+        if idx + 1 < self.free.len() && self.free[idx].end() == self.free[idx + 1].addr {
+            let next = self.free.remove(idx + 1);
+            self.free[idx].len += next.len;
+        }
+        if idx > 0 && self.free[idx - 1].end() == self.free[idx].addr {
+            let curr = self.free.remove(idx);
+            self.free[idx - 1].len += curr.len;
+        }
+    }
+}
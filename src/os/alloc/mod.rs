@@ -0,0 +1,8 @@
+pub use self::allocator::*;
+pub use self::buddy::*;
+pub use self::fit::*;
+
+mod allocator;
+mod buddy;
+mod fit;
+mod free_list;
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Chooses which line of a full set a miss replaces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CachePolicy {
+    /// The line that was used the longest time ago.
+    Lru,
+    /// The line that was filled first.
+    Fifo,
+    /// Any line, from a generator with a fixed seed, so that runs repeat.
+    Random,
+}
+
+/// The seed of the generator of `CachePolicy::Random`.
+const RANDOM_SEED: u32 = 0x2545_f491;
+
+/// Cache lookups: the whole machine's since boot, or those of a process.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// A set-associative cache in front of RAM, looked up with RAM addresses, so that the processes
+/// share it. Only which lines it holds is simulated: reads and writes still go to RAM.
+#[derive(Debug)]
+pub struct Cache {
+    line_len: usize,
+    num_ways: usize,
+    num_sets: usize,
+    policy: CachePolicy,
+    /// `num_ways` lines per set, `None` for lines not filled yet.
+    lines: Vec<Option<Line>>,
+    /// Counts the lookups, to stamp the lines.
+    clock: u64,
+    rand_state: u32,
+    stats: CacheStats,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    tag: usize,
+    /// When the line was filled (`Fifo`) or last used (`Lru`).
+    stamp: u64,
+}
+
+impl CacheStats {
+    pub fn get_num_accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    /// Returns the percentage of lookups that hit, or 0 if there were none.
+    pub fn get_hit_rate(&self) -> f64 {
+        match self.get_num_accesses() {
+            0 => 0.0,
+            num_accesses => self.hits as f64 * 100.0 / num_accesses as f64,
+        }
+    }
+
+    pub fn add(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} hits, {} misses ({:.1}% hits)",
+               self.hits,
+               self.misses,
+               self.get_hit_rate())
+    }
+}
+
+impl Cache {
+    /// Creates an empty cache of `len` bytes, in lines of `line_len` bytes grouped in sets of
+    /// `num_ways`. The sizes must be powers of two, with `line_len * num_ways` at most `len`
+    /// (see `Config::validate`).
+    pub fn new(len: usize, line_len: usize, num_ways: usize, policy: CachePolicy) -> Self {
+        let num_sets = len / (line_len * num_ways);
+        Cache {
+            line_len,
+            num_ways,
+            num_sets,
+            policy,
+            lines: vec![None; num_sets * num_ways],
+            clock: 0,
+            rand_state: RANDOM_SEED,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Looks up the lines holding the bytes at the RAM addresses `addrs`, each line once, and
+    /// fills those that miss. Returns the hits and misses.
+    pub fn access(&mut self, addrs: &[usize]) -> CacheStats {
+        let mut stats = CacheStats::default();
+        let mut line_addrs = addrs.iter()
+            .map(|addr| addr / self.line_len)
+            .collect::<Vec<usize>>();
+        line_addrs.dedup();
+        for line_addr in line_addrs {
+            if self.access_line(line_addr) {
+                stats.hits += 1;
+            } else {
+                stats.misses += 1;
+            }
+        }
+        self.stats.add(&stats);
+        stats
+    }
+
+    /// Empties every line; the counters are kept.
+    pub fn flush(&mut self) {
+        for line in &mut self.lines {
+            *line = None;
+        }
+    }
+
+    pub fn get_len(&self) -> usize {
+        self.line_len * self.num_ways * self.num_sets
+    }
+
+    pub fn get_line_len(&self) -> usize {
+        self.line_len
+    }
+
+    pub fn get_num_ways(&self) -> usize {
+        self.num_ways
+    }
+
+    pub fn get_num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    pub fn get_policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    /// Returns the lookups since boot.
+    pub fn get_stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Returns whether the line numbered `line_addr` (its RAM address over `line_len`) was
+    /// held, filling it if not.
+    fn access_line(&mut self, line_addr: usize) -> bool {
+        self.clock += 1;
+        let set_start = (line_addr % self.num_sets) * self.num_ways;
+        let tag = line_addr / self.num_sets;
+        let clock = self.clock;
+        let policy = self.policy;
+        let set = &mut self.lines[set_start..set_start + self.num_ways];
+        if let Some(line) = set.iter_mut().filter_map(|line| line.as_mut()).find(|line| {
+            line.tag == tag
+        }) {
+            if policy == CachePolicy::Lru {
+                line.stamp = clock;
+            }
+            return true;
+        }
+        let way = match set.iter().position(|line| line.is_none()) {
+            Some(way) => way,
+            None if policy == CachePolicy::Random => {
+                next_rand(&mut self.rand_state) as usize % self.num_ways
+            }
+            None => {
+                set.iter()
+                    .enumerate()
+                    .min_by_key(|&(_, line)| line.unwrap().stamp)
+                    .map(|(way, _)| way)
+                    .unwrap()
+            }
+        };
+        set[way] = Some(Line {
+            tag,
+            stamp: clock,
+        });
+        false
+    }
+}
+
+impl FromStr for CachePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lru" => Ok(CachePolicy::Lru),
+            "fifo" => Ok(CachePolicy::Fifo),
+            "random" => Ok(CachePolicy::Random),
+            _ => Err(format!("{}: unknown cache policy (expected lru, fifo or random)", s)),
+        }
+    }
+}
+
+impl fmt::Display for CachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            CachePolicy::Lru => "lru",
+            CachePolicy::Fifo => "fifo",
+            CachePolicy::Random => "random",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Advances the xorshift generator `state` and returns its next value.
+fn next_rand(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use super::alloc::AllocatorKind;
+use super::cache::CachePolicy;
+use super::cycles::CycleCosts;
+use super::mmu::{get_num_frames, ReplacerKind, MIN_FRAMES};
+use super::ps;
+use super::sys::consts::{FS_PATH, MAX_PROCS, NUM_REGISTERS, RAM_LEN, STACK_LEN,
+                         TIME_SLICE_MS};
+
+/// The names of the parameters that a profile or the command line can set.
+pub const CONFIG_KEYS: [&str; 14] = ["num_registers",
+                                      "ram_len",
+                                      "stack_len",
+                                      "max_procs",
+                                      "time_slice_ms",
+                                      "slice_cycles",
+                                      "cycle_costs",
+                                      "alloc",
+                                      "replacer",
+                                      "cache_len",
+                                      "cache_line_len",
+                                      "cache_ways",
+                                      "cache_policy",
+                                      "fs"];
+
+/// Former names of parameters, still accepted: (former name, name).
+pub const CONFIG_ALIASES: [(&str, &str); 1] = [("replace", "replacer")];
+
+/// The parameters of the machine, chosen at boot.
+/// Defaults to the values of `consts`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub num_registers: usize,
+    pub ram_len: usize,
+    pub stack_len: usize,
+    pub max_procs: usize,
+    pub time_slice_ms: i64,
+    /// If not 0, time slices last this many cycles instead of `time_slice_ms`.
+    pub slice_cycles: u64,
+    pub cycle_costs: CycleCosts,
+    pub alloc_kind: AllocatorKind,
+    pub replacer_kind: ReplacerKind,
+    /// Bytes of cache in front of RAM, or 0 for none.
+    pub cache_len: usize,
+    pub cache_line_len: usize,
+    /// Lines per set.
+    pub cache_ways: usize,
+    pub cache_policy: CachePolicy,
+    /// The host directory mounted as the file system.
+    pub fs_path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            num_registers: NUM_REGISTERS,
+            ram_len: RAM_LEN,
+            stack_len: STACK_LEN,
+            max_procs: MAX_PROCS,
+            time_slice_ms: TIME_SLICE_MS,
+            slice_cycles: 0,
+            cycle_costs: CycleCosts::default(),
+            alloc_kind: AllocatorKind::FirstFit,
+            replacer_kind: ReplacerKind::Clock,
+            cache_len: 0,
+            cache_line_len: 16,
+            cache_ways: 2,
+            cache_policy: CachePolicy::Lru,
+            fs_path: PathBuf::from(FS_PATH),
+        }
+    }
+}
+
+impl Config {
+    /// Reads a machine profile: one `key = value` per line, where `key` is one of
+    /// `CONFIG_KEYS`. Blank lines and lines starting with `#` are ignored, and parameters that
+    /// the profile leaves out keep their default.
+    pub fn from_profile<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut contents = String::new();
+        fs::File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut config = Config::default();
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.splitn(2, '=');
+            let key = tokens.next().unwrap().trim();
+            let val = tokens.next()
+                .ok_or(format!("{}:{}: expected `key = value`", path.display(), idx + 1))?
+                .trim();
+            config.set(key, val)
+                .map_err(|err| format!("{}:{}: {}", path.display(), idx + 1, err))?;
+        }
+        Ok(config)
+    }
+
+    /// Sets the parameter named `key` (see `CONFIG_KEYS` and `CONFIG_ALIASES`) from its textual
+    /// value.
+    pub fn set(&mut self, key: &str, val: &str) -> Result<(), String> {
+        let key = CONFIG_ALIASES.iter()
+            .find(|&&(alias, _)| alias == key)
+            .map_or(key, |&(_, name)| name);
+        match key {
+            "num_registers" => self.num_registers = parse_num(key, val)?,
+            "ram_len" => self.ram_len = parse_num(key, val)?,
+            "stack_len" => self.stack_len = parse_num(key, val)?,
+            "max_procs" => self.max_procs = parse_num(key, val)?,
+            "time_slice_ms" => self.time_slice_ms = parse_num(key, val)? as i64,
+            "slice_cycles" => self.slice_cycles = parse_num(key, val)? as u64,
+            "cycle_costs" => self.cycle_costs = val.parse()?,
+            "alloc" => self.alloc_kind = val.parse()?,
+            "replacer" => self.replacer_kind = val.parse()?,
+            "cache_len" => self.cache_len = parse_num(key, val)?,
+            "cache_line_len" => self.cache_line_len = parse_num(key, val)?,
+            "cache_ways" => self.cache_ways = parse_num(key, val)?,
+            "cache_policy" => self.cache_policy = val.parse()?,
+            "fs" => self.fs_path = PathBuf::from(val),
+            _ => return Err(format!("{}: unknown parameter", key)),
+        }
+        Ok(())
+    }
+
+    /// Checks that the machine can be addressed with 16-bit words.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.num_registers == 0 || self.num_registers > 256 {
+            return Err(format!("num_registers must be between 1 and 256, not {}",
+                               self.num_registers));
+        }
+        if self.ram_len > 0x10000 {
+            return Err(format!("ram_len must be at most 65536, not {}", self.ram_len));
+        }
+        let blk_region_addr = ps::get_blk_region_addr(self.max_procs);
+        if get_num_frames(self.ram_len, blk_region_addr) < MIN_FRAMES {
+            return Err(format!("ram_len must be large enough for {} page frames, not {}",
+                               MIN_FRAMES,
+                               self.ram_len));
+        }
+        if self.stack_len == 0 || self.stack_len > 0x8000 {
+            return Err(format!("stack_len must be between 1 and 32768, not {}",
+                               self.stack_len));
+        }
+        if self.max_procs == 0 || self.max_procs > 0x10000 {
+            return Err(format!("max_procs must be between 1 and 65536, not {}",
+                               self.max_procs));
+        }
+        if self.time_slice_ms <= 0 {
+            return Err(format!("time_slice_ms must be positive, not {}", self.time_slice_ms));
+        }
+        if let Some(instr_type) = self.cycle_costs.find_free() {
+            return Err(format!("cycle_costs must be at least 1 for every instruction, not 0 \
+                                for {:?}",
+                               instr_type));
+        }
+        if self.cache_len > 0 {
+            for &(key, val) in &[("cache_len", self.cache_len),
+                                 ("cache_line_len", self.cache_line_len),
+                                 ("cache_ways", self.cache_ways)] {
+                if !val.is_power_of_two() {
+                    return Err(format!("{} must be a power of two, not {}", key, val));
+                }
+            }
+            if self.cache_line_len * self.cache_ways > self.cache_len {
+                return Err(format!("cache_len must hold a set of {} lines of {} bytes, not {}",
+                                   self.cache_ways,
+                                   self.cache_line_len,
+                                   self.cache_len));
+            }
+        }
+        if !self.fs_path.is_dir() {
+            return Err(format!("fs: {} is not a directory", self.fs_path.display()));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "num_registers = {}\nram_len = {}\nstack_len = {}\nmax_procs = {}\n\
+                time_slice_ms = {}\nslice_cycles = {}\ncycle_costs = {}\nalloc = {}\n\
+                replacer = {}\ncache_len = {}\ncache_line_len = {}\ncache_ways = {}\n\
+                cache_policy = {}\nfs = {}",
+               self.num_registers,
+               self.ram_len,
+               self.stack_len,
+               self.max_procs,
+               self.time_slice_ms,
+               self.slice_cycles,
+               self.cycle_costs,
+               self.alloc_kind,
+               self.replacer_kind,
+               self.cache_len,
+               self.cache_line_len,
+               self.cache_ways,
+               self.cache_policy,
+               self.fs_path.display())
+    }
+}
+
+fn parse_num(key: &str, val: &str) -> Result<usize, String> {
+    val.replace('_', "").parse().map_err(|_| format!("{}: {} is not a number", key, val))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+    use super::Config;
+    use super::super::mmu::ReplacerKind;
+
+    fn config() -> Config {
+        Config { fs_path: env::temp_dir(), ..Config::default() }
+    }
+
+    #[test]
+    fn out_of_range_parameters_are_rejected() {
+        assert_eq!(config().validate(), Ok(()));
+        let err = |config: Config| config.validate().unwrap_err();
+        assert_eq!(err(Config { num_registers: 0, ..config() }),
+                   "num_registers must be between 1 and 256, not 0");
+        assert_eq!(err(Config { ram_len: 0x10001, ..config() }),
+                   "ram_len must be at most 65536, not 65537");
+        assert_eq!(err(Config { ram_len: 128, ..config() }),
+                   "ram_len must be large enough for 2 page frames, not 128");
+        assert_eq!(err(Config { ram_len: 500, max_procs: 100, ..config() }),
+                   "ram_len must be large enough for 2 page frames, not 500");
+        assert_eq!(err(Config { stack_len: 0, ..config() }),
+                   "stack_len must be between 1 and 32768, not 0");
+        assert_eq!(err(Config { max_procs: 0, ..config() }),
+                   "max_procs must be between 1 and 65536, not 0");
+        assert_eq!(err(Config { time_slice_ms: 0, ..config() }),
+                   "time_slice_ms must be positive, not 0");
+        assert!(err(Config { fs_path: PathBuf::from("/no/such/dir"), ..config() })
+            .starts_with("fs: "));
+    }
+
+    #[test]
+    fn parameters_are_parsed() {
+        let mut config = config();
+        config.set("ram_len", "4_096").unwrap();
+        config.set("replacer", "lru").unwrap();
+        assert_eq!(config.ram_len, 4096);
+        config.set("replace", "fifo").unwrap();
+        assert_eq!(config.replacer_kind, ReplacerKind::Fifo);
+        assert_eq!(config.set("ram_len", "lots"),
+                   Err("ram_len: lots is not a number".to_string()));
+        assert!(config.set("replacer", "random").is_err());
+        assert_eq!(config.set("colour", "blue"),
+                   Err("colour: unknown parameter".to_string()));
+    }
+}
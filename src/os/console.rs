@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use io_utils;
+
+/// A terminal: an input that programs and the shell read from, and an output they write to.
+/// Bytes buffered in memory are read before the input stream. Clones share the same buffer and
+/// streams.
+#[derive(Clone)]
+pub struct Console {
+    buffer: Arc<Mutex<VecDeque<u8>>>,
+    input: Arc<Mutex<Box<dyn Read + Send>>>,
+    output: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+/// An in-memory output whose bytes can be read back, e.g. by tests.
+/// Clones share the same bytes.
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Console {
+    pub fn new(input: Box<dyn Read + Send>, output: Box<dyn Write + Send>) -> Self {
+        Console {
+            buffer: Arc::new(Mutex::new(VecDeque::new())),
+            input: Arc::new(Mutex::new(input)),
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+
+    /// The standard input and output of snoss.
+    pub fn std() -> Self {
+        Console::new(Box::new(io::stdin()), Box::new(io::stdout()))
+    }
+
+    /// A console reading the buffered bytes `input`, whose output is kept in the returned
+    /// buffer.
+    pub fn in_memory(input: Vec<u8>) -> (Self, OutputBuffer) {
+        let output = OutputBuffer::default();
+        let console = Console::new(Box::new(io::empty()), Box::new(output.clone()));
+        console.buffer.lock().unwrap().extend(input);
+        (console, output)
+    }
+
+    /// A console reading the host file `input_path` and writing to the host file
+    /// `output_path`, which is created or truncated.
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(input_path: P,
+                                                output_path: Q)
+                                                -> io::Result<Self> {
+        let input = fs::File::open(input_path)?;
+        let output = fs::File::create(output_path)?;
+        Ok(Console::new(Box::new(input), Box::new(output)))
+    }
+
+    /// Returns a console with the same output, reading from `input` instead.
+    pub fn with_input(&self, input: Box<dyn Read + Send>) -> Self {
+        Console {
+            buffer: Arc::new(Mutex::new(VecDeque::new())),
+            input: Arc::new(Mutex::new(input)),
+            output: self.output.clone(),
+        }
+    }
+
+    /// Returns a console with the same streams and a buffer of its own holding `buffered`.
+    pub fn with_buffered(&self, buffered: Vec<u8>) -> Self {
+        Console {
+            buffer: Arc::new(Mutex::new(buffered.into_iter().collect())),
+            input: self.input.clone(),
+            output: self.output.clone(),
+        }
+    }
+
+    /// Returns the buffered bytes that have not been read yet; the input stream is left out.
+    pub fn get_buffered(&self) -> Vec<u8> {
+        self.buffer.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Returns the next byte of the input, or 0 at its end.
+    pub fn read_byte(&self) -> u8 {
+        if let Some(byte) = self.buffer.lock().unwrap().pop_front() {
+            return byte;
+        }
+        io_utils::read_byte(&mut *self.input.lock().unwrap())
+    }
+
+    /// Returns the next trimmed line of the input, or `None` at its end.
+    pub fn read_line(&self) -> Option<String> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut input = self.input.lock().unwrap();
+        if buffer.is_empty() {
+            // Lets `get_buffered` answer while this waits for the stream.
+            drop(buffer);
+            return io_utils::try_read_line(&mut *input);
+        }
+        io_utils::try_read_line(&mut (&mut *buffer).chain(&mut *input))
+    }
+
+    pub fn write(&self, msg: &str) {
+        io_utils::write(&mut *self.output.lock().unwrap(), msg)
+    }
+
+    pub fn write_ln(&self, msg: &str) {
+        io_utils::write_ln(&mut *self.output.lock().unwrap(), msg)
+    }
+}
+
+impl OutputBuffer {
+    /// Returns a copy of everything written so far.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        self.bytes.lock().unwrap().clone()
+    }
+
+    /// Returns everything written so far, with invalid UTF-8 replaced.
+    pub fn get_string(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
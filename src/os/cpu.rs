@@ -1,17 +1,46 @@
 use std::fmt;
 use byte_utils::AccessResult;
-use os::consts::NUM_REGISTERS;
 
 pub struct Cpu {
     pub instr_ptr: u16,
-    pub registers: [u16; NUM_REGISTERS],
+    pub registers: Vec<u16>,
+    /// Base/limit registers of the running process, loaded on every context switch.
+    /// The data segment spans the stack and the heap.
+    pub code_seg: Segment,
+    pub data_seg: Segment,
+    /// The cycles executed since boot (see `CycleCosts`).
+    pub cycles: u64,
+}
+
+/// A base/limit register pair: the segment spans `limit` bytes of virtual memory from `base`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Segment {
+    pub base: u16,
+    pub limit: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SegmentKind {
+    Code,
+    Data,
+}
+
+/// An access to `addr` (relative to the segment) that does not fit under the segment's limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegFault {
+    pub kind: SegmentKind,
+    pub addr: u16,
+    pub limit: u16,
 }
 
 impl Cpu {
-    pub fn init() -> Self {
+    pub fn init(num_registers: usize) -> Self {
         Cpu {
             instr_ptr: 0,
-            registers: [0; NUM_REGISTERS],
+            registers: vec![0; num_registers],
+            code_seg: Segment::default(),
+            data_seg: Segment::default(),
+            cycles: 0,
         }
     }
 
@@ -24,13 +53,69 @@ impl Cpu {
         let addr = addr as usize;
         self.registers.get_mut(addr).map(|curr_val| *curr_val = val).ok_or(())
     }
+
+    /// Returns the virtual address of `len` bytes at `addr` of the `kind` segment.
+    pub fn translate(&self, kind: SegmentKind, addr: u16, len: usize) -> Result<usize, SegFault> {
+        let seg = match kind {
+            SegmentKind::Code => self.code_seg,
+            SegmentKind::Data => self.data_seg,
+        };
+        if addr as usize + len > seg.limit as usize {
+            Err(SegFault {
+                kind,
+                addr,
+                limit: seg.limit,
+            })
+        } else {
+            Ok(seg.base as usize + addr as usize)
+        }
+    }
 }
 
 impl fmt::Debug for Cpu {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
-               "Cpu: {{ instr_ptr: 0x{:x}, registers: {:?} }}",
+               "Cpu: {{ instr_ptr: 0x{:x}, registers: {:?}, code_seg: {:?}, data_seg: {:?} }}",
                self.instr_ptr,
-               self.registers)
+               self.registers,
+               self.code_seg,
+               self.data_seg)
+    }
+}
+
+impl fmt::Display for SegFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seg_name = match self.kind {
+            SegmentKind::Code => "code",
+            SegmentKind::Data => "data",
+        };
+        write!(f,
+               "segmentation fault at 0x{:04x}, past the end of the {} segment (limit 0x{:04x})",
+               self.addr,
+               seg_name,
+               self.limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cpu, SegFault, Segment, SegmentKind};
+
+    #[test]
+    fn accesses_past_the_limit_fault() {
+        let mut cpu = Cpu::init(1);
+        cpu.data_seg = Segment {
+            base: 0x40,
+            limit: 0x10,
+        };
+        assert_eq!(cpu.translate(SegmentKind::Data, 0x0e, 2), Ok(0x4e));
+        assert_eq!(cpu.translate(SegmentKind::Data, 0x0f, 2),
+                   Err(SegFault {
+                       kind: SegmentKind::Data,
+                       addr: 0x0f,
+                       limit: 0x10,
+                   }));
+        // The code segment is still empty.
+        assert!(cpu.translate(SegmentKind::Code, 0, 1).is_err());
     }
 }
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::str::FromStr;
+use super::instr::{InstructionType, Op, INSTRUCTION_TYPES};
+
+/// The cycles an instruction with an unknown opcode takes before it fails.
+const INVALID_OP_CYCLES: u32 = 1;
+
+/// How many cycles each type of instruction takes.
+/// Written as `TYPE:CYCLES` pairs separated by commas, e.g. `Multiply:4,Divide:20`.
+#[derive(Clone, PartialEq)]
+pub struct CycleCosts {
+    /// Indexed by opcode.
+    costs: Vec<u32>,
+}
+
+impl Default for CycleCosts {
+    /// Arithmetic and jumps take a cycle or two and memory accesses a few; multiplication and
+    /// division take longer, and system calls and I/O, which go through the kernel and a
+    /// device, much longer.
+    fn default() -> Self {
+        use super::instr::InstructionType::*;
+
+        let mut costs = CycleCosts { costs: vec![INVALID_OP_CYCLES; 0x100] };
+        let defaults = [(Load, 3),
+                        (LoadConstant, 1),
+                        (Store, 3),
+                        (Add, 1),
+                        (Subtract, 1),
+                        (Multiply, 4),
+                        (Divide, 20),
+                        (Equal, 1),
+                        (Goto, 2),
+                        (GotoIf, 2),
+                        (CharPrint, 100),
+                        (CharRead, 100),
+                        (Sbrk, 50),
+                        (Exit, 1)];
+        for &(instr_type, cycles) in &defaults {
+            costs.set(instr_type, cycles);
+        }
+        costs
+    }
+}
+
+impl CycleCosts {
+    pub fn get(&self, instr_type: InstructionType) -> u32 {
+        self.costs[instr_type as usize]
+    }
+
+    pub fn set(&mut self, instr_type: InstructionType, cycles: u32) {
+        self.costs[instr_type as usize] = cycles;
+    }
+
+    /// Returns an instruction type that takes no cycles, if any. A slice of `slice_cycles`
+    /// would never end while the process ran only instructions of that type.
+    pub fn find_free(&self) -> Option<InstructionType> {
+        INSTRUCTION_TYPES.iter().cloned().find(|instr_type| self.get(*instr_type) == 0)
+    }
+
+    /// Returns how many cycles executing `op` takes.
+    pub fn get_op_cost(&self, op: &Op) -> u32 {
+        op.get_type().map_or(INVALID_OP_CYCLES, |instr_type| self.get(instr_type))
+    }
+}
+
+impl FromStr for CycleCosts {
+    type Err = String;
+
+    /// Parses `TYPE:CYCLES` pairs; the types left out keep their default.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut costs = CycleCosts::default();
+        for pair in s.split(',').map(|pair| pair.trim()).filter(|pair| !pair.is_empty()) {
+            let mut tokens = pair.splitn(2, ':');
+            let name = tokens.next().unwrap().trim();
+            let instr_type = *INSTRUCTION_TYPES.iter()
+                .find(|instr_type| format!("{:?}", instr_type) == name)
+                .ok_or(format!("{}: unknown instruction type", name))?;
+            let cycles = tokens.next()
+                .and_then(|cycles| cycles.trim().parse::<u32>().ok())
+                .ok_or(format!("{}: expected `TYPE:CYCLES`", pair))?;
+            if cycles == 0 {
+                return Err(format!("{}: an instruction takes at least 1 cycle", pair));
+            }
+            costs.set(instr_type, cycles);
+        }
+        Ok(costs)
+    }
+}
+
+impl fmt::Display for CycleCosts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs = INSTRUCTION_TYPES.iter()
+            .map(|instr_type| format!("{:?}:{}", instr_type, self.get(*instr_type)))
+            .collect::<Vec<String>>();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl fmt::Debug for CycleCosts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CycleCosts({})", self)
+    }
+}
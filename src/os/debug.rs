@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use byte_utils;
+use os::consts::WORD_LEN;
+use super::exec::ExecResult;
+use super::instr::{Executable, Instruction, InstructionType, SectionKind, INSTRUCTION_LEN};
+use super::sys::System;
+
+/// How many instructions `next` and `cont` execute at most by default, so that a process that
+/// never gets where it is going cannot hang the debugger.
+pub const MAX_DEBUG_STEPS: usize = 1_000_000;
+
+/// Why a process under a debugger stopped.
+#[derive(Debug, PartialEq)]
+pub enum StopReason {
+    /// It executed the instructions it was asked to.
+    Stepped,
+    Breakpoint(u16),
+    Watchpoint {
+        addr: u16,
+        old_val: u16,
+        new_val: u16,
+    },
+    /// It is gone, having ended with this result.
+    Exited(ExecResult),
+    /// It executed this many instructions without getting where it was going.
+    StepLimit(usize),
+}
+
+/// Controls a paused process one instruction at a time, stopping at breakpoints (code
+/// addresses) and watchpoints (data words that change).
+pub struct Debugger {
+    proc_id: u16,
+    exe: Executable,
+    breakpoints: BTreeSet<u16>,
+    /// The last value seen of every watched word.
+    watchpoints: BTreeMap<u16, u16>,
+    /// How many instructions `next` and `cont` execute at most.
+    max_steps: usize,
+    exited: bool,
+}
+
+impl Debugger {
+    /// Takes control of `proc_id`, a loaded process of `exe` that was not started.
+    pub fn new(proc_id: u16, exe: Executable) -> Self {
+        Debugger {
+            proc_id,
+            exe,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            max_steps: MAX_DEBUG_STEPS,
+            exited: false,
+        }
+    }
+
+    pub fn get_proc_id(&self) -> u16 {
+        self.proc_id
+    }
+
+    pub fn get_exe(&self) -> &Executable {
+        &self.exe
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    pub fn get_max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    /// Returns the address of `location`: a number (decimal or `0x`-prefixed) or a symbol of
+    /// the `kind` section.
+    pub fn resolve(&self, location: &str, kind: SectionKind) -> Result<u16, String> {
+        let num = match location.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => location.parse::<u16>().ok(),
+        };
+        num.or_else(|| {
+                self.exe
+                    .symbols
+                    .iter()
+                    .find(|symbol| symbol.kind == kind && symbol.name == location)
+                    .map(|symbol| symbol.addr)
+            })
+            .ok_or(format!("{}: not an address or a symbol", location))
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) -> Result<(), String> {
+        if !(addr as usize).is_multiple_of(INSTRUCTION_LEN) ||
+           addr as usize >= self.exe.code.len() {
+            return Err(format!("0x{:04x} is not an instruction", addr));
+        }
+        self.breakpoints.insert(addr);
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn get_breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Watches the word at data address `addr`.
+    pub fn add_watchpoint(&mut self, sys: &System, addr: u16) -> Result<(), String> {
+        let val = read_word(sys, self.proc_id, addr)?;
+        self.watchpoints.insert(addr, val);
+        Ok(())
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn get_watchpoints(&self) -> &BTreeMap<u16, u16> {
+        &self.watchpoints
+    }
+
+    /// Executes `num_instrs` instructions, or fewer if a watched word changes or the process
+    /// exits. Breakpoints are ignored.
+    pub fn step(&mut self, sys: &mut System, num_instrs: usize) -> Result<StopReason, String> {
+        for _ in 0..num_instrs {
+            if let Some(reason) = self.exec_once(sys)? {
+                return Ok(reason);
+            }
+        }
+        Ok(StopReason::Stepped)
+    }
+
+    /// Executes instructions until the one following the current one (by address) is reached,
+    /// which runs a loop that jumps back to its start to completion, or `max_steps` were.
+    pub fn next(&mut self, sys: &mut System) -> Result<StopReason, String> {
+        let next_addr = self.get_instr_ptr(sys)?.wrapping_add(INSTRUCTION_LEN as u16);
+        for _ in 0..self.max_steps {
+            if let Some(reason) = self.exec_once(sys)? {
+                return Ok(reason);
+            }
+            let instr_ptr = self.get_instr_ptr(sys)?;
+            if instr_ptr == next_addr {
+                return Ok(StopReason::Stepped);
+            }
+            if self.breakpoints.contains(&instr_ptr) {
+                return Ok(StopReason::Breakpoint(instr_ptr));
+            }
+        }
+        Ok(StopReason::StepLimit(self.max_steps))
+    }
+
+    /// Executes instructions until a breakpoint is reached, a watched word changes, the
+    /// process exits or `max_steps` instructions were executed.
+    pub fn cont(&mut self, sys: &mut System) -> Result<StopReason, String> {
+        for _ in 0..self.max_steps {
+            if let Some(reason) = self.exec_once(sys)? {
+                return Ok(reason);
+            }
+            let instr_ptr = self.get_instr_ptr(sys)?;
+            if self.breakpoints.contains(&instr_ptr) {
+                return Ok(StopReason::Breakpoint(instr_ptr));
+            }
+        }
+        Ok(StopReason::StepLimit(self.max_steps))
+    }
+
+    pub fn get_instr_ptr(&self, sys: &System) -> Result<u16, String> {
+        self.check_alive()?;
+        Ok(sys.get_proc_info(self.proc_id)?.instr_ptr)
+    }
+
+    /// Describes the instruction at `addr`, with the nearest preceding code symbol and the
+    /// source line when the executable has them.
+    pub fn describe(&self, addr: u16) -> String {
+        let mut desc = format!("0x{:04x}", addr);
+        let symbol = self.exe
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SectionKind::Code && symbol.addr <= addr)
+            .max_by_key(|symbol| symbol.addr);
+        if let Some(symbol) = symbol {
+            desc += &format!(" <{}+{}>", symbol.name, addr - symbol.addr);
+        }
+        let start = addr as usize;
+        if let Ok(bytes) = byte_utils::get_slice(&self.exe.code, start..start + INSTRUCTION_LEN) {
+            let instr = Instruction::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            desc += &format!("  {}", disassemble(&instr));
+        }
+        if let Some(ref debug) = self.exe.debug {
+            let line = debug.lines.iter().rev().find(|&&(line_addr, _)| line_addr <= addr);
+            if let Some(&(_, line)) = line {
+                desc += &format!("  ({}:{})", debug.source_file_name, line);
+            }
+        }
+        desc
+    }
+
+    /// Executes one instruction. Returns why the process must stop, if it must.
+    fn exec_once(&mut self, sys: &mut System) -> Result<Option<StopReason>, String> {
+        self.check_alive()?;
+        let result = sys.step(self.proc_id)?;
+        if result != ExecResult::Success {
+            self.exited = true;
+            return Ok(Some(StopReason::Exited(result)));
+        }
+        for (addr, old_val) in self.watchpoints.iter_mut() {
+            let new_val = read_word(sys, self.proc_id, *addr)?;
+            if new_val != *old_val {
+                let reason = StopReason::Watchpoint {
+                    addr: *addr,
+                    old_val: *old_val,
+                    new_val,
+                };
+                *old_val = new_val;
+                return Ok(Some(reason));
+            }
+        }
+        Ok(None)
+    }
+
+    fn check_alive(&self) -> Result<(), String> {
+        if self.exited {
+            Err(format!("Process {} has exited.", self.proc_id))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StopReason::Stepped => write!(f, "stepped"),
+            StopReason::Breakpoint(addr) => write!(f, "breakpoint at 0x{:04x}", addr),
+            StopReason::Watchpoint { addr, old_val, new_val } => {
+                write!(f,
+                       "watchpoint at 0x{:04x}: {} (0x{:04x}) -> {} (0x{:04x})",
+                       addr,
+                       old_val,
+                       old_val,
+                       new_val,
+                       new_val)
+            }
+            StopReason::Exited(ref result) => {
+                write!(f, "exited: {:?} (exit code {})", result, result.get_exit_code())
+            }
+            StopReason::StepLimit(max_steps) => {
+                write!(f, "step limit of {} instructions reached", max_steps)
+            }
+        }
+    }
+}
+
+/// Returns the mnemonic of `instr` and its operands.
+pub fn disassemble(instr: &Instruction) -> String {
+    use enum_primitive::FromPrimitive;
+    use self::InstructionType::*;
+
+    let bytes = instr.to_bytes();
+    let instr_type = match InstructionType::from_u8(bytes[0]) {
+        Some(instr_type) => instr_type,
+        None => return format!("(invalid {:02x} {:02x} {:02x} {:02x})",
+                               bytes[0], bytes[1], bytes[2], bytes[3]),
+    };
+    let (reg_1, reg_2, reg_3) = (instr.get_reg_1(), instr.get_reg_2(), instr.get_reg_3());
+    let (lit_1, lit_2) = (instr.get_literal_1(), instr.get_literal_2());
+    let operands = match instr_type {
+        Load => format!("r{}, [0x{:04x}]", reg_1, lit_2),
+        LoadConstant => format!("r{}, {}", reg_1, lit_2),
+        Store => format!("[0x{:04x}], r{}", lit_1, reg_3),
+        Add | Subtract | Multiply | Divide | Equal => {
+            format!("r{}, r{}, r{}", reg_1, reg_2, reg_3)
+        }
+        Goto => format!("0x{:04x}", lit_1),
+        GotoIf => format!("r{}, 0x{:04x}", reg_3, lit_1),
+        CharPrint | CharRead => format!("[0x{:04x}]", lit_1),
+        Sbrk => format!("r{}, r{}", reg_1, reg_3),
+        Exit => String::new(),
+    };
+    format!("{:?} {}", instr_type, operands).trim().to_string()
+}
+
+fn read_word(sys: &System, proc_id: u16, addr: u16) -> Result<u16, String> {
+    let bytes = sys.read_mem(proc_id, addr, WORD_LEN)?;
+    Ok(byte_utils::u16_from_bytes([bytes[0], bytes[1]]))
+}
@@ -1,13 +1,23 @@
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::Sender;
 use std::thread;
-use byte_utils::{self, AccessResult};
-use io_utils;
+use std::time::Duration;
+use byte_utils::AccessResult;
 use time_utils;
-use os::consts::TIME_SLICE_MS;
-use super::cpu::Cpu;
-use super::instr::Instruction;
+use os::consts::WORD_LEN;
+use super::cache::Cache;
+use super::console::Console;
+use super::cpu::{Cpu, SegFault, SegmentKind};
+use super::cycles::CycleCosts;
+use super::instr::{Instruction, Op, INSTRUCTION_LEN};
 use super::ps::{Pcb, Status as ProcessStatus};
+use super::timeline::{EventKind, Timeline};
+use super::trace::Tracer;
+
+/// How many instructions a time slice executes between two looks at the clock.
+const TIME_CHECK_INTERVAL: usize = 64;
+/// How long a slice of a frozen process waits before giving the CPU back.
+const FROZEN_POLL_MS: u64 = 1;
 
 /// Responsible for taking control of the CPU.
 /// Each PCB should be "wrapped" with an executor to make it execute.
@@ -17,6 +27,15 @@ pub struct Executor {
     cpu: Arc<Mutex<Cpu>>,
     pcb: Arc<Mutex<Pcb>>,
     use_term: bool,
+    console: Console,
+    time_slice_ms: i64,
+    /// If not 0, time slices last this many cycles instead of `time_slice_ms`.
+    slice_cycles: u64,
+    costs: CycleCosts,
+    /// The cache that fetches, loads and stores are looked up in, if any.
+    cache: Option<Arc<Mutex<Cache>>>,
+    tracer: Option<Tracer>,
+    timeline: Option<Timeline>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,78 +43,233 @@ pub enum ExecResult {
     Success,
     Exit,
     AccessErr,
+    SegFault(SegFault),
 }
 
 impl Executor {
-    pub fn new(cpu: Arc<Mutex<Cpu>>, pcb: Arc<Mutex<Pcb>>, use_term: bool) -> Executor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(cpu: Arc<Mutex<Cpu>>,
+               pcb: Arc<Mutex<Pcb>>,
+               use_term: bool,
+               console: Console,
+               time_slice_ms: i64,
+               slice_cycles: u64,
+               costs: CycleCosts,
+               cache: Option<Arc<Mutex<Cache>>>,
+               tracer: Option<Tracer>,
+               timeline: Option<Timeline>)
+               -> Executor {
         Executor {
             cpu: cpu.clone(),
             pcb: pcb.clone(),
             use_term: use_term,
+            console,
+            time_slice_ms,
+            slice_cycles,
+            costs,
+            cache,
+            tracer,
+            timeline,
         }
     }
 
+    /// Runs the process on a thread of its own, which frees the process through `kill_tx` when
+    /// it stops.
     pub fn start(self, kill_tx: Sender<u16>) -> thread::JoinHandle<ExecResult> {
         thread::spawn(move || {
             let proc_id = self.get_proc_id();
-            let mut result = ExecResult::Success;
-            while result == ExecResult::Success {
-                {
-                    use std::time::Duration;
-                    // BEGIN TIME SLICE
-                    let last_time_slice = time_utils::now();
-                    // Rust does not seem to use FIFO queues for Mutex locks.
-                    // This enables the other threads to grab hold of the resources.
-                    thread::sleep(Duration::new(0, 1));
-                    let mut pcb = self.pcb.lock().unwrap();
-                    if *pcb.get_status() == ProcessStatus::Killed {
-                        break;
-                    }
-                    let mut cpu = self.cpu.lock().unwrap();
-                    debug!("Proc {}: begin time slice", proc_id);
-                    load_cpu_ctx(&mut cpu, &pcb);
-                    // Execute
-                    pcb.set_status(ProcessStatus::Executing);
-                    while time_utils::since(&last_time_slice).num_milliseconds() <
-                          TIME_SLICE_MS && result == ExecResult::Success {
-                        result = exec_once(&mut cpu, &mut pcb, self.use_term);
-                    }
-                    // END TIME SLICE
-                    save_cpu_ctx(&cpu, &mut pcb);
-                    pcb.set_status(ProcessStatus::Blocked);
-                    debug!("Proc {}: end time slice", proc_id);
-                }
-                thread::yield_now();
-            }
+            let result = self.run();
             // if exiting on its own
-            if result == ExecResult::Exit {
+            if result != ExecResult::Success {
                 kill_tx.send(proc_id).unwrap();
             }
             result
         })
     }
 
+    /// Runs the process on the calling thread, one time slice at a time, until it stops.
+    /// Returns `Success` if it was killed instead.
+    pub fn run(&self) -> ExecResult {
+        let mut result = ExecResult::Success;
+        while result == ExecResult::Success {
+            // Rust does not seem to use FIFO queues for Mutex locks.
+            // This enables the other threads to grab hold of the resources.
+            thread::sleep(Duration::new(0, 1));
+            match self.run_slice(None) {
+                Some((slice_result, _)) => result = slice_result,
+                None => break,
+            }
+            thread::yield_now();
+        }
+        if let Some(ref tracer) = self.tracer {
+            tracer.flush();
+        }
+        self.report(&result);
+        result
+    }
+
+    /// Executes the next instruction of the process.
+    /// Returns `Success` without executing anything if the process was killed.
+    pub fn step(&self) -> ExecResult {
+        let result = match self.run_slice(Some(1)) {
+            Some((result, _)) => result,
+            None => return ExecResult::Success,
+        };
+        if let Some(ref tracer) = self.tracer {
+            tracer.flush();
+        }
+        self.report(&result);
+        result
+    }
+
+    /// Runs one time slice of the process on the calling thread: `max_instrs` instructions, or
+    /// as many as fit in `time_slice_ms` (or `slice_cycles`) if `None`, fewer if the process
+    /// stops. The cycles it took are added to the process's.
+    /// Returns how the slice ended and how many instructions it executed, or `None` if the
+    /// process was killed. A frozen process executes nothing.
+    pub fn run_slice(&self, max_instrs: Option<usize>) -> Option<(ExecResult, usize)> {
+        // BEGIN TIME SLICE
+        let last_time_slice = time_utils::now();
+        let mut pcb = self.pcb.lock().unwrap();
+        if pcb.get_status() == ProcessStatus::Killed {
+            return None;
+        }
+        if pcb.is_frozen() {
+            drop(pcb);
+            thread::sleep(Duration::from_millis(FROZEN_POLL_MS));
+            return Some((ExecResult::Success, 0));
+        }
+        let mut cpu = self.cpu.lock().unwrap();
+        let mut cache = self.cache.as_ref().map(|cache| cache.lock().unwrap());
+        debug!("Proc {}: begin time slice", pcb.get_id());
+        self.record(&pcb, EventKind::SliceStart);
+        load_cpu_ctx(&mut cpu, &pcb);
+        // Execute
+        pcb.set_status(ProcessStatus::Executing);
+        let start_cycles = cpu.cycles;
+        let is_slice_over = |num_instrs: usize, num_cycles: u64| match max_instrs {
+            Some(max_instrs) => num_instrs >= max_instrs,
+            None if self.slice_cycles > 0 => num_cycles >= self.slice_cycles,
+            None => {
+                num_instrs.is_multiple_of(TIME_CHECK_INTERVAL) &&
+                time_utils::since(&last_time_slice).num_milliseconds() >= self.time_slice_ms
+            }
+        };
+        let mut result = ExecResult::Success;
+        let mut num_instrs = 0;
+        while result == ExecResult::Success &&
+              !is_slice_over(num_instrs, cpu.cycles - start_cycles) {
+            result = self.exec_once(&mut cpu, &mut pcb, cache.as_deref_mut());
+            num_instrs += 1;
+        }
+        // END TIME SLICE
+        save_cpu_ctx(&cpu, &mut pcb);
+        let cycles = pcb.get_cycles() + cpu.cycles - start_cycles;
+        pcb.set_cycles(cycles);
+        pcb.set_status(ProcessStatus::Blocked);
+        self.record(&pcb, EventKind::SliceEnd);
+        if result != ExecResult::Success {
+            self.record(&pcb, EventKind::Exit);
+        }
+        debug!("Proc {}: end time slice", pcb.get_id());
+        Some((result, num_instrs))
+    }
+
+    /// Records an event of the process on the timeline, if there is one.
+    fn record(&self, pcb: &Pcb, kind: EventKind) {
+        if let Some(ref timeline) = self.timeline {
+            timeline.record(pcb, kind);
+        }
+    }
+
+    /// Executes the next instruction, recording it if it is traced.
+    fn exec_once(&self, cpu: &mut Cpu, pcb: &mut Pcb, cache: Option<&mut Cache>) -> ExecResult {
+        let tracer = match self.tracer {
+            Some(ref tracer) if tracer.is_traced(pcb.get_id(), cpu.instr_ptr) => tracer,
+            _ => {
+                return exec_once(cpu,
+                                 pcb,
+                                 self.use_term,
+                                 &self.console,
+                                 &self.costs,
+                                 cache,
+                                 self.timeline.as_ref())
+            }
+        };
+        let instr_ptr = cpu.instr_ptr;
+        let instr = match get_next_instr(cpu, pcb) {
+            Ok(instr) => instr,
+            Err(result) => return result,
+        };
+        let old_registers = cpu.registers.clone();
+        let result = exec_once(cpu,
+                               pcb,
+                               self.use_term,
+                               &self.console,
+                               &self.costs,
+                               cache,
+                               self.timeline.as_ref());
+        tracer.record(cpu, pcb, instr_ptr, &instr, &old_registers, &result);
+        result
+    }
+
+    /// Reports a segmentation fault on the console, and the cache lookups of a process that
+    /// stopped if there is a cache.
+    pub fn report(&self, result: &ExecResult) {
+        if let ExecResult::SegFault(fault) = *result {
+            let pcb = self.pcb.lock().unwrap();
+            error!("Proc {}: {}", pcb.get_id(), fault);
+            self.console.write_ln(&format!("\nProc {} ({}): {}",
+                                           pcb.get_id(),
+                                           pcb.get_exe_file_name(),
+                                           fault));
+        }
+        if self.cache.is_some() && *result != ExecResult::Success {
+            let pcb = self.pcb.lock().unwrap();
+            self.console.write_ln(&format!("\nProc {} ({}): cache: {}",
+                                           pcb.get_id(),
+                                           pcb.get_exe_file_name(),
+                                           pcb.get_cache_stats()));
+        }
+    }
+
     fn get_proc_id(&self) -> u16 {
         let pcb = self.pcb.lock().unwrap();
         pcb.get_id()
     }
 }
 
+impl ExecResult {
+    /// Returns the status that a process ending with this result exits with.
+    pub fn get_exit_code(&self) -> i32 {
+        match *self {
+            ExecResult::Success | ExecResult::Exit => 0,
+            ExecResult::AccessErr => 1,
+            // As if killed by SIGSEGV.
+            ExecResult::SegFault(_) => 139,
+        }
+    }
+}
+
 impl<T> From<AccessResult<T>> for ExecResult {
     fn from(access_result: AccessResult<T>) -> ExecResult {
         match access_result {
             Ok(_) => ExecResult::Success,
-            Err(_) => ExecResult::Exit,
+            Err(_) => ExecResult::AccessErr,
         }
     }
 }
 
-fn get_next_instr(cpu: &Cpu, pcb: &Pcb) -> AccessResult<Instruction> {
-    let addr = cpu.instr_ptr as usize;
-    let instr_blk = pcb.get_instr_blk();
-    // println!("loading instr at {}", addr);
-    let instr = *instr_blk.get_instruction_at(addr)?;
-    Ok(instr)
+fn get_next_op(cpu: &Cpu, pcb: &Pcb) -> Result<Op, ExecResult> {
+    let vaddr = cpu.translate(SegmentKind::Code, cpu.instr_ptr, INSTRUCTION_LEN)
+        .map_err(ExecResult::SegFault)?;
+    pcb.get_op_at(vaddr).map_err(|_| ExecResult::AccessErr)
+}
+
+fn get_next_instr(cpu: &Cpu, pcb: &Pcb) -> Result<Instruction, ExecResult> {
+    let vaddr = cpu.translate(SegmentKind::Code, cpu.instr_ptr, INSTRUCTION_LEN)
+        .map_err(ExecResult::SegFault)?;
+    pcb.get_instruction_at(vaddr).map_err(|_| ExecResult::AccessErr)
 }
 
 fn get_cpu_instr_ptr(cpu: &Cpu) -> u16 {
@@ -103,8 +277,7 @@ fn get_cpu_instr_ptr(cpu: &Cpu) -> u16 {
 }
 
 fn advance_cpu_instr_ptr(cpu: &mut Cpu) {
-    use super::instr::INSTRUCTION_LEN;
-    cpu.instr_ptr += INSTRUCTION_LEN as u16;
+    cpu.instr_ptr = cpu.instr_ptr.wrapping_add(INSTRUCTION_LEN as u16);
 }
 
 fn load_cpu_ctx(cpu: &mut Cpu, pcb: &Pcb) {
@@ -115,44 +288,57 @@ fn save_cpu_ctx(cpu: &Cpu, pcb: &mut Pcb) {
     pcb.save_cpu_ctx(&cpu);
 }
 
-fn load(cpu: &mut Cpu, pcb: &mut Pcb, dest_reg: u8, addr: u16) -> AccessResult<()> {
-    let stack = pcb.get_stack_mut();
-    byte_utils::get_u16_at(stack, addr as usize)
-        .and_then(|loaded_val| cpu.set_reg(dest_reg, loaded_val))
+fn load(cpu: &mut Cpu, pcb: &mut Pcb, dest_reg: u8, addr: u16) -> ExecResult {
+    match cpu.translate(SegmentKind::Data, addr, WORD_LEN) {
+        Ok(vaddr) => {
+            pcb.get_u16(vaddr)
+                .and_then(|loaded_val| cpu.set_reg(dest_reg, loaded_val))
+                .into()
+        }
+        Err(fault) => ExecResult::SegFault(fault),
+    }
 }
 
 fn load_const(cpu: &mut Cpu, dest_reg: u8, constant: u16) -> AccessResult<()> {
     cpu.set_reg(dest_reg, constant)
 }
 
-fn store(cpu: &Cpu, pcb: &mut Pcb, src_reg: u8, addr: u16) -> AccessResult<()> {
-    let stack = pcb.get_stack_mut();
-    let addr = addr as usize;
-    cpu.get_reg(src_reg)
-        .and_then(|reg_val| byte_utils::set_u16_at(stack, addr, reg_val))
+fn store(cpu: &Cpu, pcb: &mut Pcb, src_reg: u8, addr: u16) -> ExecResult {
+    match cpu.translate(SegmentKind::Data, addr, WORD_LEN) {
+        Ok(vaddr) => {
+            cpu.get_reg(src_reg)
+                .and_then(|reg_val| pcb.set_u16(vaddr, reg_val))
+                .into()
+        }
+        Err(fault) => ExecResult::SegFault(fault),
+    }
 }
 
 fn add(cpu: &mut Cpu, src_reg_a: u8, src_reg_b: u8, dest_reg: u8) -> AccessResult<()> {
     let a = cpu.get_reg(src_reg_a)?;
     let b = cpu.get_reg(src_reg_b)?;
-    cpu.set_reg(dest_reg, a + b)
+    cpu.set_reg(dest_reg, a.wrapping_add(b))
 }
 
 fn sub(cpu: &mut Cpu, src_reg_a: u8, src_reg_b: u8, dest_reg: u8) -> AccessResult<()> {
     let a = cpu.get_reg(src_reg_a)?;
     let b = cpu.get_reg(src_reg_b)?;
-    cpu.set_reg(dest_reg, a - b)
+    cpu.set_reg(dest_reg, a.wrapping_sub(b))
 }
 
 fn mul(cpu: &mut Cpu, src_reg_a: u8, src_reg_b: u8, dest_reg: u8) -> AccessResult<()> {
     let a = cpu.get_reg(src_reg_a)?;
     let b = cpu.get_reg(src_reg_b)?;
-    cpu.set_reg(dest_reg, a * b)
+    cpu.set_reg(dest_reg, a.wrapping_mul(b))
 }
 
 fn div(cpu: &mut Cpu, src_reg_a: u8, src_reg_b: u8, dest_reg: u8) -> AccessResult<()> {
     let a = cpu.get_reg(src_reg_a)?;
     let b = cpu.get_reg(src_reg_b)?;
+    if b == 0 {
+        warn!("Division by zero at 0x{:04x}", cpu.instr_ptr.wrapping_sub(INSTRUCTION_LEN as u16));
+        return Err(());
+    }
     cpu.set_reg(dest_reg, a / b)
 }
 
@@ -175,70 +361,145 @@ fn goto_if(cpu: &mut Cpu, if_reg: u8, addr: u16) -> AccessResult<()> {
     Ok(())
 }
 
-fn char_print(use_term: bool, pcb: &Pcb, addr: u16) -> AccessResult<()> {
+fn char_print(use_term: bool, console: &Console, cpu: &Cpu, pcb: &Pcb, addr: u16) -> ExecResult {
+    let vaddr = match cpu.translate(SegmentKind::Data, addr, 1) {
+        Ok(vaddr) => vaddr,
+        Err(fault) => return ExecResult::SegFault(fault),
+    };
     if use_term {
-        let stack = pcb.get_stack();
-
-        let addr = addr as usize;
-        let ch = stack.get(addr)
-            .map(|ascii_byte| *ascii_byte as char)
-            .ok_or(())?;
+        let ch = match pcb.get_byte(vaddr) {
+            Ok(ascii_byte) => ascii_byte as char,
+            Err(_) => return ExecResult::AccessErr,
+        };
         let to_write = ch.to_string();
-        print!("{}", to_write);
+        console.write(&to_write);
     }
-    Ok(())
+    ExecResult::Success
 }
 
-fn char_read(use_term: bool, pcb: &mut Pcb, addr: u16) -> AccessResult<()> {
+fn char_read(use_term: bool,
+             console: &Console,
+             timeline: Option<&Timeline>,
+             cpu: &Cpu,
+             pcb: &mut Pcb,
+             addr: u16)
+             -> ExecResult {
+    let vaddr = match cpu.translate(SegmentKind::Data, addr, 1) {
+        Ok(vaddr) => vaddr,
+        Err(fault) => return ExecResult::SegFault(fault),
+    };
     if use_term {
-        let mut stack = pcb.get_stack_mut();
-
-        let addr = addr as usize;
-        let read_byte = io_utils::read_byte_from_stdin();
-        stack.get_mut(addr)
-            .map(|slot| *slot = read_byte)
-            .ok_or(())
+        if let Some(timeline) = timeline {
+            timeline.record(pcb, EventKind::Block);
+        }
+        let read_byte = console.read_byte();
+        if let Some(timeline) = timeline {
+            timeline.record(pcb, EventKind::Wakeup);
+        }
+        pcb.set_byte(vaddr, read_byte).into()
     } else {
-        panic!("Cannot run program requiring stdin async")
-    }
-}
-
-fn dispatch(cpu: &mut Cpu, pcb: &mut Pcb, use_term: bool, instr: &Instruction) -> ExecResult {
-    use super::instr::InstructionType::*;
-
-    let reg_1 = instr.get_reg_1();
-    let reg_2 = instr.get_reg_2();
-    let reg_3 = instr.get_reg_3();
-    let lit_1 = instr.get_literal_1();
-    let lit_2 = instr.get_literal_2();
-
-    match instr.get_type() {
-        Load => load(cpu, pcb, reg_1, lit_2).into(),
-        LoadConstant => load_const(cpu, reg_1, lit_2).into(),
-        Store => store(cpu, pcb, reg_3, lit_1).into(),
-        Add => add(cpu, reg_1, reg_2, reg_3).into(),
-        Subtract => sub(cpu, reg_1, reg_2, reg_3).into(),
-        Multiply => mul(cpu, reg_1, reg_2, reg_3).into(),
-        Divide => div(cpu, reg_1, reg_2, reg_3).into(),
-        Equal => eq(cpu, reg_1, reg_2, reg_3).into(),
-        Goto => {
-            goto(cpu, lit_1);
+        // Processes run without the terminal have no input.
+        warn!("Proc {}: CharRead without a terminal", pcb.get_id());
+        ExecResult::AccessErr
+    }
+}
+
+/// Grows (or shrinks) the heap by the signed value of `delta_reg`, storing the previous end of
+/// the heap in `dest_reg`, or 0xFFFF if the heap could not be resized.
+fn sbrk(cpu: &mut Cpu, pcb: &mut Pcb, delta_reg: u8, dest_reg: u8) -> AccessResult<()> {
+    let delta = cpu.get_reg(delta_reg)? as i16;
+    let old_brk = match pcb.sbrk(delta) {
+        Ok(old_brk) => old_brk,
+        Err(err) => {
+            warn!("Proc {}: sbrk({}) failed: {}", pcb.get_id(), delta, err);
+            0xFFFF
+        }
+    };
+    cpu.data_seg = pcb.get_data_seg();
+    cpu.set_reg(dest_reg, old_brk)
+}
+
+fn dispatch(cpu: &mut Cpu,
+            pcb: &mut Pcb,
+            use_term: bool,
+            console: &Console,
+            timeline: Option<&Timeline>,
+            op: Op)
+            -> ExecResult {
+    match op {
+        Op::Load { dest, addr } => load(cpu, pcb, dest, addr),
+        Op::LoadConstant { dest, val } => load_const(cpu, dest, val).into(),
+        Op::Store { src, addr } => store(cpu, pcb, src, addr),
+        Op::Add { a, b, dest } => add(cpu, a, b, dest).into(),
+        Op::Subtract { a, b, dest } => sub(cpu, a, b, dest).into(),
+        Op::Multiply { a, b, dest } => mul(cpu, a, b, dest).into(),
+        Op::Divide { a, b, dest } => div(cpu, a, b, dest).into(),
+        Op::Equal { a, b, dest } => eq(cpu, a, b, dest).into(),
+        Op::Goto { addr } => {
+            goto(cpu, addr);
             ExecResult::Success
         }
-        GotoIf => goto_if(cpu, reg_3, lit_1).into(),
-        CharPrint => char_print(use_term, pcb, lit_1).into(),
-        CharRead => char_read(use_term, pcb, lit_1).into(),
-        Exit => ExecResult::Exit,
+        Op::GotoIf { cond, addr } => goto_if(cpu, cond, addr).into(),
+        Op::CharPrint { addr } => char_print(use_term, console, cpu, pcb, addr),
+        Op::CharRead { addr } => char_read(use_term, console, timeline, cpu, pcb, addr),
+        Op::Sbrk { delta, dest } => sbrk(cpu, pcb, delta, dest).into(),
+        Op::Exit => ExecResult::Exit,
+        Op::Invalid(_) => ExecResult::AccessErr,
     }
 }
 
-fn exec_once(cpu: &mut Cpu, pcb: &mut Pcb, use_term: bool) -> ExecResult {
-    let result = get_next_instr(&cpu, &pcb);
-    let instr = match result {
-        Ok(instr) => instr,
-        Err(_) => return result.into(),
+fn exec_once(cpu: &mut Cpu,
+             pcb: &mut Pcb,
+             use_term: bool,
+             console: &Console,
+             costs: &CycleCosts,
+             cache: Option<&mut Cache>,
+             timeline: Option<&Timeline>)
+             -> ExecResult {
+    let op = match get_next_op(cpu, pcb) {
+        Ok(op) => op,
+        Err(result) => return result,
     };
+    let instr_ptr = get_cpu_instr_ptr(cpu);
     // println!("{} adv", pcb.get_id());
     advance_cpu_instr_ptr(cpu);
-    dispatch(cpu, pcb, use_term, &instr)
+    cpu.cycles += costs.get_op_cost(&op) as u64;
+    let result = dispatch(cpu, pcb, use_term, console, timeline, op);
+    if let Some(cache) = cache {
+        look_up_cache(cache, cpu, pcb, instr_ptr, op, &result);
+    }
+    result
+}
+
+/// Looks up the instruction fetched from `instr_ptr` in the cache, then the word that `op`
+/// loaded or stored, if it did, and charges the process with the hits and misses.
+fn look_up_cache(cache: &mut Cache,
+                 cpu: &Cpu,
+                 pcb: &mut Pcb,
+                 instr_ptr: u16,
+                 op: Op,
+                 result: &ExecResult) {
+    let code_addrs = get_ram_addrs(cpu, pcb, SegmentKind::Code, instr_ptr, INSTRUCTION_LEN);
+    let mut stats = cache.access(&code_addrs);
+    let data_addr = match op {
+        Op::Load { addr, .. } | Op::Store { addr, .. } => Some(addr),
+        _ => None,
+    };
+    if let (Some(addr), &ExecResult::Success) = (data_addr, result) {
+        let data_addrs = get_ram_addrs(cpu, pcb, SegmentKind::Data, addr, WORD_LEN);
+        stats.add(&cache.access(&data_addrs));
+    }
+    let mut pcb_stats = pcb.get_cache_stats();
+    pcb_stats.add(&stats);
+    pcb.set_cache_stats(pcb_stats);
+}
+
+/// Returns the RAM addresses of the `len` bytes at `addr` of a segment, which were just
+/// accessed, so that their pages are in frames. The page table is only read, so that a cache
+/// leaves paging as it would be without one.
+fn get_ram_addrs(cpu: &Cpu, pcb: &Pcb, kind: SegmentKind, addr: u16, len: usize) -> Vec<usize> {
+    match cpu.translate(kind, addr, len) {
+        Ok(vaddr) => (vaddr..vaddr + len).filter_map(|vaddr| pcb.find_ram_addr(vaddr)).collect(),
+        Err(_) => vec![],
+    }
 }
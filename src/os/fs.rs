@@ -18,16 +18,14 @@ impl FileSystem {
         self.root.join(rel_path)
     }
 
+    /// Lists the files whose names do not start with a dot, such as the swap file.
     pub fn list_files(&self) -> String {
         let paths = fs::read_dir(&self.root).unwrap();
         paths.map(|path| format!("{}", path.unwrap().file_name().to_str().unwrap()))
+            .filter(|file_name| !file_name.starts_with('.'))
             .fold(String::new(), |acc, file_name| acc + &file_name + "\t")
     }
 
-    pub fn exists<P: AsRef<Path>>(&self, file_name: P) -> bool {
-        self.get_full_path(file_name).exists()
-    }
-
     pub fn create<P: AsRef<Path>>(&self, file_name: P) -> io::Result<fs::File> {
         let full_path = self.get_full_path(file_name);
         fs::File::create(full_path)
@@ -49,14 +47,4 @@ impl FileSystem {
         Ok(bytes.map(|result| result.unwrap())
             .collect())
     }
-
-    pub fn write_str_to_file<P: AsRef<Path>>(&self,
-                                             file_name: P,
-                                             contents: &str)
-                                             -> io::Result<()> {
-        use std::io::Write;
-
-        let mut file = self.create(&file_name)?;
-        file.write_all(contents.as_bytes())
-    }
 }
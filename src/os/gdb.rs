@@ -0,0 +1,411 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use byte_utils::{decode_hex, encode_hex};
+use super::exec::ExecResult;
+use super::debug::{Debugger, StopReason};
+use super::sys::System;
+
+/// Where the data segment starts in the addresses seen by GDB, which has a single address
+/// space: code addresses are below it, and data address `addr` is `DATA_ADDR_BASE + addr`.
+pub const DATA_ADDR_BASE: u32 = 0x10000;
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+/// The byte GDB sends to interrupt a running process.
+const INTERRUPT: u8 = 0x03;
+/// How many instructions a continued process executes between two checks for an interrupt.
+const INTERRUPT_CHECK_STEPS: usize = 10_000;
+
+/// Serves a process under a `Debugger` to a GDB front end over the remote serial protocol.
+/// Registers are `r0`... then `ip`, 16 bits each, in the big-endian byte order of the machine.
+pub struct GdbStub {
+    dbg: Debugger,
+    num_registers: usize,
+}
+
+impl GdbStub {
+    pub fn new(sys: &System, dbg: Debugger) -> Result<Self, String> {
+        let num_registers = sys.get_proc_info(dbg.get_proc_id())?.registers.len();
+        Ok(GdbStub {
+            dbg,
+            num_registers,
+        })
+    }
+
+    pub fn get_debugger(&self) -> &Debugger {
+        &self.dbg
+    }
+
+    /// Answers the packets read from `stream` until the front end kills the process, detaches
+    /// or hangs up. A detached process runs to completion in the background.
+    pub fn serve(&mut self, sys: &mut System, stream: TcpStream) -> Result<(), String> {
+        let mut conn = Connection {
+            stream,
+            ack: true,
+        };
+        while let Some(packet) = conn.read_packet()? {
+            if let Some(args) = packet.strip_prefix('c') {
+                let reply = self.cont(sys, &mut conn, args)
+                    .unwrap_or_else(|err| {
+                        debug!("gdb: {}: {}", packet, err);
+                        "E01".to_string()
+                    });
+                conn.write_packet(&reply)?;
+                continue;
+            }
+            match packet.as_str() {
+                "k" => {
+                    if !self.dbg.has_exited() {
+                        let _ = sys.kill(self.dbg.get_proc_id());
+                    }
+                    return Ok(());
+                }
+                "D" => {
+                    conn.write_packet("OK")?;
+                    if !self.dbg.has_exited() {
+                        let handle = sys.start(self.dbg.get_proc_id(), true)?;
+                        thread::spawn(move || handle.join());
+                    }
+                    return Ok(());
+                }
+                "QStartNoAckMode" => {
+                    conn.write_packet("OK")?;
+                    conn.ack = false;
+                }
+                _ => {
+                    let reply = self.answer(sys, &packet)
+                        .unwrap_or_else(|err| {
+                            debug!("gdb: {}: {}", packet, err);
+                            "E01".to_string()
+                        });
+                    conn.write_packet(&reply)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the reply to `packet`, which is empty for packets that are not supported.
+    fn answer(&mut self, sys: &mut System, packet: &str) -> Result<String, String> {
+        let mut chars = packet.chars();
+        let cmd = match chars.next() {
+            Some(cmd) => cmd,
+            None => return Ok(String::new()),
+        };
+        let args = chars.as_str();
+        match cmd {
+            '?' => Ok(format!("S{:02x}", SIGTRAP)),
+            'g' => {
+                let regs = self.get_regs(sys)?;
+                Ok(regs.iter().map(|reg| format!("{:04x}", reg)).collect())
+            }
+            'G' => {
+                let regs = decode_hex(args)?;
+                if regs.len() != (self.num_registers + 1) * 2 {
+                    return Err("wrong number of registers".to_string());
+                }
+                for (idx, reg) in regs.chunks(2).enumerate() {
+                    self.set_reg(sys, idx, (reg[0] as u16) << 8 | reg[1] as u16)?;
+                }
+                Ok("OK".to_string())
+            }
+            'p' => {
+                let idx = parse_hex(args)? as usize;
+                let regs = self.get_regs(sys)?;
+                let reg = regs.get(idx).ok_or(format!("no register {}", idx))?;
+                Ok(format!("{:04x}", reg))
+            }
+            'P' => {
+                let mut parts = args.splitn(2, '=');
+                let idx = parse_hex(parts.next().unwrap())? as usize;
+                let val = parse_hex(parts.next().ok_or("no value")?)?;
+                let val = u16::try_from(val).map_err(|_| format!("{:x}: out of range", val))?;
+                self.set_reg(sys, idx, val)?;
+                Ok("OK".to_string())
+            }
+            'm' => {
+                let (addr, len) = parse_addr_len(args)?;
+                let bytes = self.read_mem(sys, addr, len)?;
+                Ok(encode_hex(&bytes))
+            }
+            'M' => {
+                let mut parts = args.splitn(2, ':');
+                let (addr, len) = parse_addr_len(parts.next().unwrap())?;
+                let bytes = decode_hex(parts.next().ok_or("no data")?)?;
+                if bytes.len() != len {
+                    return Err("wrong data length".to_string());
+                }
+                let addr = get_data_addr(addr, len)?;
+                sys.write_mem(self.dbg.get_proc_id(), addr, &bytes)?;
+                Ok("OK".to_string())
+            }
+            'Z' | 'z' => self.set_point(sys, cmd == 'Z', args),
+            's' => {
+                self.resume_at(sys, args)?;
+                let reason = self.dbg.step(sys, 1)?;
+                Ok(stop_reply(&reason))
+            }
+            'H' => Ok("OK".to_string()),
+            'q' => Ok(self.query(args)),
+            _ => Ok(String::new()),
+        }
+    }
+
+    /// Continues the process, from the address `args` if any, until it stops or the front end
+    /// interrupts it. Returns the stop reply.
+    fn cont(&mut self,
+            sys: &mut System,
+            conn: &mut Connection<TcpStream>,
+            args: &str)
+            -> Result<String, String> {
+        self.resume_at(sys, args)?;
+        let max_steps = self.dbg.get_max_steps();
+        self.dbg.set_max_steps(INTERRUPT_CHECK_STEPS);
+        let reply = loop {
+            match self.dbg.cont(sys) {
+                Ok(StopReason::StepLimit(_)) => {
+                    match conn.poll_interrupt() {
+                        Ok(false) => continue,
+                        Ok(true) => break Ok(format!("S{:02x}", SIGINT)),
+                        Err(err) => break Err(err),
+                    }
+                }
+                Ok(reason) => break Ok(stop_reply(&reason)),
+                Err(err) => break Err(err),
+            }
+        };
+        self.dbg.set_max_steps(max_steps);
+        reply
+    }
+
+    fn query(&self, args: &str) -> String {
+        if args.starts_with("Supported") {
+            "PacketSize=1000;qXfer:features:read+;QStartNoAckMode+".to_string()
+        } else if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+            match parse_addr_len(range) {
+                Ok((offset, len)) => {
+                    let xml = self.target_xml();
+                    let start = (offset as usize).min(xml.len());
+                    let end = (start + len).min(xml.len());
+                    let more = if end < xml.len() { "m" } else { "l" };
+                    format!("{}{}", more, &xml[start..end])
+                }
+                Err(_) => "E01".to_string(),
+            }
+        } else if args == "Attached" {
+            "1".to_string()
+        } else if args == "C" {
+            format!("QC{:x}", self.dbg.get_proc_id() + 1)
+        } else if args == "fThreadInfo" {
+            format!("m{:x}", self.dbg.get_proc_id() + 1)
+        } else if args == "sThreadInfo" {
+            "l".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Describes the registers of the process to GDB.
+    fn target_xml(&self) -> String {
+        let num_registers = self.num_registers;
+        let mut xml = "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+                       <target version=\"1.0\">\n<feature name=\"org.snoss.core\">\n"
+            .to_string();
+        for idx in 0..num_registers {
+            xml += &format!("<reg name=\"r{}\" bitsize=\"16\" type=\"uint16\" regnum=\"{}\"/>\n",
+                            idx,
+                            idx);
+        }
+        xml += &format!("<reg name=\"ip\" bitsize=\"16\" type=\"code_ptr\" regnum=\"{}\"/>\n",
+                        num_registers);
+        xml + "</feature>\n</target>\n"
+    }
+
+    /// Returns the registers of the process followed by its instruction pointer.
+    fn get_regs(&self, sys: &System) -> Result<Vec<u16>, String> {
+        let info = sys.get_proc_info(self.dbg.get_proc_id())?;
+        let mut regs = info.registers;
+        regs.push(info.instr_ptr);
+        Ok(regs)
+    }
+
+    fn set_reg(&self, sys: &mut System, idx: usize, val: u16) -> Result<(), String> {
+        let proc_id = self.dbg.get_proc_id();
+        match idx.cmp(&self.num_registers) {
+            // There are at most 256 registers.
+            Ordering::Less => sys.set_reg(proc_id, idx as u8, val),
+            Ordering::Equal => sys.set_instr_ptr(proc_id, val),
+            Ordering::Greater => Err(format!("no register {}", idx)),
+        }
+    }
+
+    fn read_mem(&self, sys: &System, addr: u32, len: usize) -> Result<Vec<u8>, String> {
+        if addr >= DATA_ADDR_BASE {
+            let addr = get_data_addr(addr, len)?;
+            sys.read_mem(self.dbg.get_proc_id(), addr, len)
+        } else {
+            let code = &self.dbg.get_exe().code;
+            let start = addr as usize;
+            if start + len > code.len() {
+                return Err(format!("0x{:x} is past the code", start + len));
+            }
+            Ok(code[start..start + len].to_vec())
+        }
+    }
+
+    /// Sets or clears a software breakpoint (`0`) or a write watchpoint (`2`).
+    fn set_point(&mut self, sys: &System, is_set: bool, args: &str) -> Result<String, String> {
+        let parts = args.split(',').collect::<Vec<&str>>();
+        if parts.len() < 3 {
+            return Err("malformed".to_string());
+        }
+        let addr = parse_hex(parts[1])?;
+        match (parts[0], is_set) {
+            ("0", true) => self.dbg.add_breakpoint(get_code_addr(addr)?)?,
+            ("0", false) => {
+                self.dbg.remove_breakpoint(get_code_addr(addr)?);
+            }
+            ("2", true) => self.dbg.add_watchpoint(sys, get_data_addr(addr, 2)?)?,
+            ("2", false) => {
+                self.dbg.remove_watchpoint(get_data_addr(addr, 2)?);
+            }
+            _ => return Ok(String::new()),
+        }
+        Ok("OK".to_string())
+    }
+
+    /// Moves the instruction pointer to the address of a `c` or `s` packet, if any.
+    fn resume_at(&self, sys: &mut System, args: &str) -> Result<(), String> {
+        if !args.is_empty() {
+            let addr = get_code_addr(parse_hex(args)?)?;
+            sys.set_instr_ptr(self.dbg.get_proc_id(), addr)?;
+        }
+        Ok(())
+    }
+}
+
+/// The framing of packets: `$DATA#CHECKSUM`, each acknowledged with `+` until no-ack mode.
+struct Connection<S> {
+    stream: S,
+    ack: bool,
+}
+
+impl<S: Read + Write> Connection<S> {
+    /// Returns the data of the next packet, or `None` when the stream ends.
+    fn read_packet(&mut self) -> Result<Option<String>, String> {
+        loop {
+            match self.read_byte()? {
+                Some(b'$') => (),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+            let mut data = vec![];
+            loop {
+                match self.read_byte()? {
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                    None => return Ok(None),
+                }
+            }
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum).map_err(|err| err.to_string())?;
+            let is_valid = String::from_utf8_lossy(&checksum) == format!("{:02x}", sum(&data));
+            if self.ack {
+                let ack: &[u8] = if is_valid { b"+" } else { b"-" };
+                self.stream.write_all(ack).map_err(|err| err.to_string())?;
+            }
+            if is_valid {
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> Result<(), String> {
+        let mut escaped = vec![];
+        for &byte in data.as_bytes() {
+            if byte == b'#' || byte == b'$' || byte == b'}' || byte == b'*' {
+                escaped.push(b'}');
+                escaped.push(byte ^ 0x20);
+            } else {
+                escaped.push(byte);
+            }
+        }
+        let mut packet = vec![b'$'];
+        packet.extend_from_slice(&escaped);
+        packet.extend_from_slice(format!("#{:02x}", sum(&escaped)).as_bytes());
+        self.stream.write_all(&packet).map_err(|err| err.to_string())?;
+        self.stream.flush().map_err(|err| err.to_string())
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>, String> {
+        let mut byte = [0];
+        match self.stream.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(byte[0])),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+impl Connection<TcpStream> {
+    /// Returns whether the front end interrupted the running process, or hung up, without
+    /// waiting for it to. Nothing else is sent while a process runs.
+    fn poll_interrupt(&mut self) -> Result<bool, String> {
+        self.stream.set_nonblocking(true).map_err(|err| err.to_string())?;
+        let mut byte = [0];
+        let result = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false).map_err(|err| err.to_string())?;
+        match result {
+            Ok(0) => Ok(true),
+            Ok(_) => Ok(byte[0] == INTERRUPT),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+fn stop_reply(reason: &StopReason) -> String {
+    match *reason {
+        StopReason::Exited(ExecResult::SegFault(_)) => format!("S{:02x}", SIGSEGV),
+        StopReason::Exited(ref result) => format!("W{:02x}", result.get_exit_code() as u8),
+        StopReason::Watchpoint { addr, .. } => {
+            format!("T{:02x}watch:{:x};", SIGTRAP, DATA_ADDR_BASE + addr as u32)
+        }
+        _ => format!("S{:02x}", SIGTRAP),
+    }
+}
+
+fn get_code_addr(addr: u32) -> Result<u16, String> {
+    if addr >= DATA_ADDR_BASE {
+        return Err(format!("0x{:x} is not a code address", addr));
+    }
+    Ok(addr as u16)
+}
+
+fn get_data_addr(addr: u32, len: usize) -> Result<u16, String> {
+    if addr < DATA_ADDR_BASE || (addr - DATA_ADDR_BASE) as usize + len > 0x10000 {
+        return Err(format!("0x{:x} is not a data address", addr));
+    }
+    Ok((addr - DATA_ADDR_BASE) as u16)
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s, 16).map_err(|_| format!("{}: not a hexadecimal number", s))
+}
+
+fn parse_addr_len(s: &str) -> Result<(u32, usize), String> {
+    let mut parts = s.splitn(2, ',');
+    let addr = parse_hex(parts.next().unwrap())?;
+    let len = parse_hex(parts.next().ok_or("no length")?)?;
+    Ok((addr, len as usize))
+}
+
@@ -0,0 +1,257 @@
+use byte_utils;
+use super::INSTRUCTION_LEN;
+
+/// The first bytes of an executable file; legacy files, which are bare instructions, never
+/// start with them as no opcode is 0x7f.
+pub const EXE_MAGIC: [u8; 4] = [0x7f, b'S', b'N', b'O'];
+/// The format version that this build reads and writes.
+pub const EXE_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 0x0c;
+const SECTION_ENTRY_LEN: usize = 0x08;
+
+// Offsets of the header fields (see design.md).
+const VERSION_OFFSET: usize = 0x04;
+const ENTRY_OFFSET: usize = 0x06;
+const NUM_SECTIONS_OFFSET: usize = 0x08;
+
+enum_from_primitive! {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SectionKind {
+    Code = 0x01,
+    Data = 0x02,
+    Symbols = 0x03,
+    Debug = 0x04,
+}
+}
+
+/// A name for an address of the code or data segment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SectionKind,
+    pub addr: u16,
+}
+
+/// Maps code addresses back to the lines of the source file the program was built from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DebugInfo {
+    pub source_file_name: String,
+    /// `(code address, line number)`, by ascending address.
+    pub lines: Vec<(u16, u16)>,
+}
+
+/// A program as stored in a `.sno` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Executable {
+    /// The format version the file was written with; 0 for legacy files.
+    pub version: u16,
+    /// The code address of the first instruction to execute.
+    pub entry: u16,
+    pub code: Vec<u8>,
+    /// Initialized data, preloaded at the start of the data segment.
+    pub data: Vec<u8>,
+    pub symbols: Vec<Symbol>,
+    pub debug: Option<DebugInfo>,
+}
+
+impl Executable {
+    /// Creates an executable of the current version that starts at the first instruction of
+    /// `code` and has no data.
+    pub fn new(code: Vec<u8>) -> Self {
+        Executable {
+            version: EXE_VERSION,
+            entry: 0,
+            code,
+            data: vec![],
+            symbols: vec![],
+            debug: None,
+        }
+    }
+
+    /// Parses an executable file, or a legacy file made of instructions only.
+    /// Sections of unknown kinds are skipped.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(&EXE_MAGIC) {
+            let mut exe = Executable::new(bytes.to_vec());
+            exe.version = 0;
+            exe.validate()?;
+            return Ok(exe);
+        }
+        let get_u16 = |addr: usize| {
+            byte_utils::get_u16_at(bytes, addr).map_err(|_| "Truncated executable.".to_string())
+        };
+        let version = get_u16(VERSION_OFFSET)?;
+        if version == 0 || version > EXE_VERSION {
+            return Err(format!("Unsupported executable version {} (at most {}).",
+                               version,
+                               EXE_VERSION));
+        }
+        let mut exe = Executable::new(vec![]);
+        exe.version = version;
+        exe.entry = get_u16(ENTRY_OFFSET)?;
+        let num_sections = get_u16(NUM_SECTIONS_OFFSET)? as usize;
+        let mut has_code = false;
+        for idx in 0..num_sections {
+            use enum_primitive::FromPrimitive;
+
+            let entry_addr = HEADER_LEN + idx * SECTION_ENTRY_LEN;
+            let kind = get_u16(entry_addr)?;
+            let offset = get_u16(entry_addr + 2)? as usize;
+            let len = get_u16(entry_addr + 4)? as usize;
+            let section = byte_utils::get_slice(bytes, offset..offset + len)
+                .map_err(|_| format!("Section {} is past the end of the file.", idx))?;
+            match SectionKind::from_u16(kind) {
+                Some(SectionKind::Code) => {
+                    exe.code = section.to_vec();
+                    has_code = true;
+                }
+                Some(SectionKind::Data) => exe.data = section.to_vec(),
+                Some(SectionKind::Symbols) => exe.symbols = parse_symbols(section)?,
+                Some(SectionKind::Debug) => exe.debug = Some(parse_debug_info(section)?),
+                None => (),
+            }
+        }
+        if !has_code {
+            return Err("The executable has no code section.".to_string());
+        }
+        exe.validate()?;
+        Ok(exe)
+    }
+
+    /// Writes the executable in the current format; empty sections are left out.
+    /// Returns an error if a section or a name is too long for the format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut sections = vec![(SectionKind::Code, self.code.clone())];
+        if !self.data.is_empty() {
+            sections.push((SectionKind::Data, self.data.clone()));
+        }
+        if !self.symbols.is_empty() {
+            sections.push((SectionKind::Symbols, symbols_to_bytes(&self.symbols)?));
+        }
+        if let Some(ref debug) = self.debug {
+            sections.push((SectionKind::Debug, debug_info_to_bytes(debug)?));
+        }
+        let mut bytes = EXE_MAGIC.to_vec();
+        push_u16(&mut bytes, EXE_VERSION);
+        push_u16(&mut bytes, self.entry);
+        push_u16(&mut bytes, sections.len() as u16);
+        push_u16(&mut bytes, 0);
+        let mut offset = HEADER_LEN + sections.len() * SECTION_ENTRY_LEN;
+        for &(kind, ref section) in &sections {
+            if offset > 0xFFFF || section.len() > 0xFFFF {
+                return Err(format!("The {:?} section ({} bytes at offset {}) does not fit in a \
+                                    64 KiB executable.",
+                                   kind,
+                                   section.len(),
+                                   offset));
+            }
+            push_u16(&mut bytes, kind as u16);
+            push_u16(&mut bytes, offset as u16);
+            push_u16(&mut bytes, section.len() as u16);
+            push_u16(&mut bytes, 0);
+            offset += section.len();
+        }
+        for (_, section) in &sections {
+            bytes.extend_from_slice(section);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the symbol named `name`.
+    pub fn get_symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.code.len().is_multiple_of(INSTRUCTION_LEN) {
+            return Err(format!("The code's size must be a multiple of the instruction size ({} \
+                                bytes).",
+                               INSTRUCTION_LEN));
+        }
+        let entry = self.entry as usize;
+        if !entry.is_multiple_of(INSTRUCTION_LEN) || (entry >= self.code.len() && entry != 0) {
+            return Err(format!("The entry point 0x{:04x} is not an instruction.", entry));
+        }
+        Ok(())
+    }
+}
+
+/// Symbols are `KIND NAME_LEN ADDR NAME`, with one byte for the kind and for the length.
+fn parse_symbols(bytes: &[u8]) -> Result<Vec<Symbol>, String> {
+    use enum_primitive::FromPrimitive;
+
+    let mut symbols = vec![];
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let truncated = || "Truncated symbol section.".to_string();
+        let kind = SectionKind::from_u8(bytes[idx]).ok_or(format!("Bad symbol kind {}.",
+                                                                  bytes[idx]))?;
+        let name_len = *bytes.get(idx + 1).ok_or_else(&truncated)? as usize;
+        let addr = byte_utils::get_u16_at(bytes, idx + 2).map_err(|_| truncated())?;
+        let name = byte_utils::get_slice(bytes, idx + 4..idx + 4 + name_len)
+            .map_err(|_| truncated())?;
+        symbols.push(Symbol {
+            name: String::from_utf8_lossy(name).into_owned(),
+            kind,
+            addr,
+        });
+        idx += 4 + name_len;
+    }
+    Ok(symbols)
+}
+
+fn symbols_to_bytes(symbols: &[Symbol]) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    for symbol in symbols {
+        bytes.push(symbol.kind as u8);
+        bytes.push(get_name_len(&symbol.name)?);
+        push_u16(&mut bytes, symbol.addr);
+        bytes.extend_from_slice(symbol.name.as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Debug info is `NAME_LEN NAME`, with one byte for the length, followed by `ADDR LINE` pairs.
+fn parse_debug_info(bytes: &[u8]) -> Result<DebugInfo, String> {
+    let truncated = || "Truncated debug section.".to_string();
+    let name_len = *bytes.first().ok_or_else(&truncated)? as usize;
+    let name = byte_utils::get_slice(bytes, 1..1 + name_len).map_err(|_| truncated())?;
+    let lines_bytes = &bytes[1 + name_len..];
+    if !lines_bytes.len().is_multiple_of(4) {
+        return Err(truncated());
+    }
+    let lines = lines_bytes.chunks(4)
+        .map(|pair| {
+            (byte_utils::u16_from_bytes([pair[0], pair[1]]),
+             byte_utils::u16_from_bytes([pair[2], pair[3]]))
+        })
+        .collect();
+    Ok(DebugInfo {
+        source_file_name: String::from_utf8_lossy(name).into_owned(),
+        lines,
+    })
+}
+
+fn debug_info_to_bytes(debug: &DebugInfo) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![get_name_len(&debug.source_file_name)?];
+    bytes.extend_from_slice(debug.source_file_name.as_bytes());
+    for &(addr, line) in &debug.lines {
+        push_u16(&mut bytes, addr);
+        push_u16(&mut bytes, line);
+    }
+    Ok(bytes)
+}
+
+/// Returns the length of `name`, which is written in one byte.
+fn get_name_len(name: &str) -> Result<u8, String> {
+    if name.len() > 0xFF {
+        return Err(format!("The name {}... is longer than 255 bytes.",
+                           name.chars().take(16).collect::<String>()));
+    }
+    Ok(name.len() as u8)
+}
+
+fn push_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.extend_from_slice(&byte_utils::u16_to_bytes(val));
+}
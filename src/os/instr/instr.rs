@@ -17,6 +17,10 @@ impl Instruction {
         Instruction { bytes: byte_utils::u32_to_bytes(word) }
     }
 
+    pub fn to_bytes(self) -> [u8; INSTRUCTION_LEN] {
+        self.bytes
+    }
+
     pub fn get_type(&self) -> InstructionType {
         use enum_primitive::FromPrimitive;
 
@@ -58,7 +62,7 @@ impl fmt::Debug for Instruction {
 }
 
 enum_from_primitive! {
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum InstructionType {
     // Load / Store: 0x1N
     Load = 0x11,
@@ -80,6 +84,25 @@ pub enum InstructionType {
     CharPrint = 0x41,
     CharRead = 0x42,
 
+    // System calls: 0x5N
+    Sbrk = 0x51,
+
     Exit = 0xFF,
 }
 }
+
+/// Every type of instruction, in opcode order.
+pub const INSTRUCTION_TYPES: [InstructionType; 14] = [InstructionType::Load,
+                                                     InstructionType::LoadConstant,
+                                                     InstructionType::Store,
+                                                     InstructionType::Add,
+                                                     InstructionType::Subtract,
+                                                     InstructionType::Multiply,
+                                                     InstructionType::Divide,
+                                                     InstructionType::Equal,
+                                                     InstructionType::Goto,
+                                                     InstructionType::GotoIf,
+                                                     InstructionType::CharPrint,
+                                                     InstructionType::CharRead,
+                                                     InstructionType::Sbrk,
+                                                     InstructionType::Exit];
@@ -1,52 +1,48 @@
 use std::fmt;
-use byte_utils::{self, AccessResult};
-use super::{INSTRUCTION_LEN, Instruction};
+use super::{INSTRUCTION_LEN, Instruction, Op};
 
-pub const NUM_INSTRUCTIONS_PER_BLOCK: usize = 256;
-pub const INSTRUCTION_BLOCK_LEN: usize = NUM_INSTRUCTIONS_PER_BLOCK * INSTRUCTION_LEN;
+/// Instruction addresses are 16-bit, so no block can be longer.
+pub const MAX_INSTRUCTION_BLOCK_LEN: usize = 0x10000;
 
 pub struct InstructionBlock {
-    instructions: [Instruction; NUM_INSTRUCTIONS_PER_BLOCK],
+    instructions: Vec<Instruction>,
 }
 
 impl InstructionBlock {
     pub fn new(bytes: &[u8]) -> Result<InstructionBlock, String> {
-        if bytes.len() % INSTRUCTION_LEN == 0 {
-            let mut instrs = [Instruction::from_word(0); NUM_INSTRUCTIONS_PER_BLOCK];
-
-            let mut instr_bytes: [u8; 4] = [0; 4];
-            for i in 0..bytes.len() {
-                let byte_idx = i % INSTRUCTION_LEN;
-                instr_bytes[byte_idx] = bytes[i];
-                if byte_idx == INSTRUCTION_LEN - 1 {
-                    let instr = Instruction::from_bytes(instr_bytes);
-                    let instr_idx = i / INSTRUCTION_LEN;
-                    instrs[instr_idx] = instr;
-                    instr_bytes = [0; INSTRUCTION_LEN];
-                }
-            }
-            let block = InstructionBlock { instructions: instrs };
-            Ok(block)
-        } else {
-            Err(format!("An instruction block's size must be a multiple of the instruction size \
-                         ({} bytes).",
-                        INSTRUCTION_LEN))
+        if !bytes.len().is_multiple_of(INSTRUCTION_LEN) {
+            return Err(format!("An instruction block's size must be a multiple of the \
+                                instruction size ({} bytes).",
+                               INSTRUCTION_LEN));
+        }
+        if bytes.len() > MAX_INSTRUCTION_BLOCK_LEN {
+            return Err(format!("The program's {} bytes of instructions are past the {} bytes \
+                                that instruction addresses can reach.",
+                               bytes.len(),
+                               MAX_INSTRUCTION_BLOCK_LEN));
         }
+        let instrs = bytes.chunks(INSTRUCTION_LEN)
+            .map(|instr_bytes| {
+                Instruction::from_bytes([instr_bytes[0],
+                                         instr_bytes[1],
+                                         instr_bytes[2],
+                                         instr_bytes[3]])
+            })
+            .collect();
+        Ok(InstructionBlock { instructions: instrs })
     }
 
-    pub fn get_instruction_at(&self, addr: usize) -> AccessResult<&Instruction> {
-        let idx = addr / INSTRUCTION_LEN;
-        let is_in_bounds = idx < self.instructions.len();
-        if is_aligned(addr) && is_in_bounds {
-            Ok(&self.instructions[idx])
-        } else {
-            Err(())
-        }
+    /// Decodes every instruction, in order.
+    pub fn decode(&self) -> Vec<Op> {
+        self.instructions.iter().map(Op::decode).collect()
     }
-}
 
-fn is_aligned(addr: usize) -> bool {
-    byte_utils::is_aligned(addr, INSTRUCTION_LEN)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.instructions
+            .iter()
+            .flat_map(|instr| instr.to_bytes().to_vec())
+            .collect()
+    }
 }
 
 impl fmt::Debug for InstructionBlock {
@@ -1,5 +1,11 @@
+pub use self::exe::*;
 pub use self::instr::*;
 pub use self::instr_block::*;
+pub use self::op::*;
+pub use self::verify::*;
 
+mod exe;
 mod instr;
 mod instr_block;
+mod op;
+mod verify;
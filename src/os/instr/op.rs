@@ -0,0 +1,80 @@
+use super::{Instruction, InstructionType};
+
+/// An instruction decoded once, when its program is loaded, so that executing it does not
+/// decode its bytes again. Registers are `u8`s and addresses and constants `u16`s, as in the
+/// encoding (see `dispatch` in exec.rs for their meaning).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Load { dest: u8, addr: u16 },
+    LoadConstant { dest: u8, val: u16 },
+    Store { src: u8, addr: u16 },
+    Add { a: u8, b: u8, dest: u8 },
+    Subtract { a: u8, b: u8, dest: u8 },
+    Multiply { a: u8, b: u8, dest: u8 },
+    Divide { a: u8, b: u8, dest: u8 },
+    Equal { a: u8, b: u8, dest: u8 },
+    Goto { addr: u16 },
+    GotoIf { cond: u8, addr: u16 },
+    CharPrint { addr: u16 },
+    CharRead { addr: u16 },
+    Sbrk { delta: u8, dest: u8 },
+    Exit,
+    /// An unknown opcode, which fails when it is executed.
+    Invalid(u8),
+}
+
+impl Op {
+    pub fn decode(instr: &Instruction) -> Op {
+        use enum_primitive::FromPrimitive;
+        use self::InstructionType::*;
+
+        let opcode = instr.to_bytes()[0];
+        let instr_type = match InstructionType::from_u8(opcode) {
+            Some(instr_type) => instr_type,
+            None => return Op::Invalid(opcode),
+        };
+        let reg_1 = instr.get_reg_1();
+        let reg_2 = instr.get_reg_2();
+        let reg_3 = instr.get_reg_3();
+        let lit_1 = instr.get_literal_1();
+        let lit_2 = instr.get_literal_2();
+        match instr_type {
+            Load => Op::Load { dest: reg_1, addr: lit_2 },
+            LoadConstant => Op::LoadConstant { dest: reg_1, val: lit_2 },
+            Store => Op::Store { src: reg_3, addr: lit_1 },
+            Add => Op::Add { a: reg_1, b: reg_2, dest: reg_3 },
+            Subtract => Op::Subtract { a: reg_1, b: reg_2, dest: reg_3 },
+            Multiply => Op::Multiply { a: reg_1, b: reg_2, dest: reg_3 },
+            Divide => Op::Divide { a: reg_1, b: reg_2, dest: reg_3 },
+            Equal => Op::Equal { a: reg_1, b: reg_2, dest: reg_3 },
+            Goto => Op::Goto { addr: lit_1 },
+            GotoIf => Op::GotoIf { cond: reg_3, addr: lit_1 },
+            CharPrint => Op::CharPrint { addr: lit_1 },
+            CharRead => Op::CharRead { addr: lit_1 },
+            Sbrk => Op::Sbrk { delta: reg_1, dest: reg_3 },
+            Exit => Op::Exit,
+        }
+    }
+
+    /// Returns the type of the instruction, or `None` if its opcode is unknown.
+    pub fn get_type(&self) -> Option<InstructionType> {
+        let instr_type = match *self {
+            Op::Load { .. } => InstructionType::Load,
+            Op::LoadConstant { .. } => InstructionType::LoadConstant,
+            Op::Store { .. } => InstructionType::Store,
+            Op::Add { .. } => InstructionType::Add,
+            Op::Subtract { .. } => InstructionType::Subtract,
+            Op::Multiply { .. } => InstructionType::Multiply,
+            Op::Divide { .. } => InstructionType::Divide,
+            Op::Equal { .. } => InstructionType::Equal,
+            Op::Goto { .. } => InstructionType::Goto,
+            Op::GotoIf { .. } => InstructionType::GotoIf,
+            Op::CharPrint { .. } => InstructionType::CharPrint,
+            Op::CharRead { .. } => InstructionType::CharRead,
+            Op::Sbrk { .. } => InstructionType::Sbrk,
+            Op::Exit => InstructionType::Exit,
+            Op::Invalid(_) => return None,
+        };
+        Some(instr_type)
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use os::consts::{MAX_HEAP_LEN, WORD_LEN};
+use super::{Executable, Instruction, InstructionType, INSTRUCTION_LEN};
+
+/// A problem with the instruction at `addr`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub addr: u16,
+    pub msg: String,
+}
+
+/// What the operands of an instruction may refer to.
+struct Limits {
+    num_registers: usize,
+    code_len: usize,
+    data_seg_len: usize,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:04x}: {}", self.addr, self.msg)
+    }
+}
+
+/// Checks every instruction of `exe` before it runs on a machine with `num_registers` registers
+/// and stacks of `stack_len` bytes: opcodes must be valid, registers must exist, jump targets
+/// must be instructions and literal addresses must be in the data segment.
+/// The heap is unknown until the program runs, so a program that calls `Sbrk` may address up
+/// to `MAX_HEAP_LEN` bytes past its stack.
+pub fn verify(exe: &Executable, num_registers: usize, stack_len: usize) -> Vec<Diagnostic> {
+    let instrs = exe.code
+        .chunks(INSTRUCTION_LEN)
+        .map(|bytes| Instruction::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect::<Vec<Instruction>>();
+    let calls_sbrk = instrs.iter().any(|instr| instr.to_bytes()[0] == InstructionType::Sbrk as u8);
+    let limits = Limits {
+        num_registers,
+        code_len: exe.code.len(),
+        data_seg_len: exe.data.len() + stack_len + if calls_sbrk { MAX_HEAP_LEN } else { 0 },
+    };
+    instrs.iter()
+        .enumerate()
+        .flat_map(|(idx, instr)| {
+            let addr = (idx * INSTRUCTION_LEN) as u16;
+            check_instr(instr, &limits).into_iter().map(move |msg| {
+                Diagnostic {
+                    addr,
+                    msg,
+                }
+            })
+        })
+        .collect()
+}
+
+fn check_instr(instr: &Instruction, limits: &Limits) -> Vec<String> {
+    use enum_primitive::FromPrimitive;
+    use self::InstructionType::*;
+
+    let mut msgs = vec![];
+    let opcode = instr.to_bytes()[0];
+    let instr_type = match InstructionType::from_u8(opcode) {
+        Some(instr_type) => instr_type,
+        None => return vec![format!("invalid opcode 0x{:02x}", opcode)],
+    };
+    let (reg_1, reg_2, reg_3) = (instr.get_reg_1(), instr.get_reg_2(), instr.get_reg_3());
+    match instr_type {
+        Load => {
+            check_reg(&mut msgs, limits, reg_1);
+            check_data_addr(&mut msgs, limits, instr.get_literal_2(), WORD_LEN);
+        }
+        LoadConstant => check_reg(&mut msgs, limits, reg_1),
+        Store => {
+            check_reg(&mut msgs, limits, reg_3);
+            check_data_addr(&mut msgs, limits, instr.get_literal_1(), WORD_LEN);
+        }
+        Add | Subtract | Multiply | Divide | Equal => {
+            check_reg(&mut msgs, limits, reg_1);
+            check_reg(&mut msgs, limits, reg_2);
+            check_reg(&mut msgs, limits, reg_3);
+        }
+        Goto => check_target(&mut msgs, limits, instr.get_literal_1()),
+        GotoIf => {
+            check_reg(&mut msgs, limits, reg_3);
+            check_target(&mut msgs, limits, instr.get_literal_1());
+        }
+        CharPrint | CharRead => check_data_addr(&mut msgs, limits, instr.get_literal_1(), 1),
+        Sbrk => {
+            check_reg(&mut msgs, limits, reg_1);
+            check_reg(&mut msgs, limits, reg_3);
+        }
+        Exit => (),
+    }
+    msgs
+}
+
+fn check_reg(msgs: &mut Vec<String>, limits: &Limits, reg: u8) {
+    if reg as usize >= limits.num_registers {
+        msgs.push(format!("register {} does not exist ({} registers)",
+                          reg,
+                          limits.num_registers));
+    }
+}
+
+fn check_target(msgs: &mut Vec<String>, limits: &Limits, target: u16) {
+    if !(target as usize).is_multiple_of(INSTRUCTION_LEN) || target as usize >= limits.code_len {
+        msgs.push(format!("jump target 0x{:04x} is not an instruction", target));
+    }
+}
+
+/// Checks that the `len` bytes at `addr` are in the data segment.
+fn check_data_addr(msgs: &mut Vec<String>, limits: &Limits, addr: u16, len: usize) {
+    if addr as usize + len > limits.data_seg_len {
+        msgs.push(format!("address 0x{:04x} is past the data segment ({} bytes)",
+                          addr,
+                          limits.data_seg_len));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use byte_utils;
+use super::snapshot::Snapshot;
+
+/// The longest snapshot a receiver accepts, in bytes.
+const MAX_SNAPSHOT_LEN: usize = 0x20000;
+/// The longest answer either side reads, in bytes.
+const MAX_LINE_LEN: usize = 0x400;
+/// How long either side waits for the other to send or accept anything.
+pub const MIGRATION_TIMEOUT_SECS: u64 = 10;
+
+/// Makes reads and writes on `stream` fail after `MIGRATION_TIMEOUT_SECS`.
+pub fn set_timeouts(stream: &TcpStream) -> Result<(), String> {
+    let timeout = Some(Duration::from_secs(MIGRATION_TIMEOUT_SECS));
+    stream.set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+        .map_err(|err| err.to_string())
+}
+
+/// Sends `snapshot` over `stream` and waits for the receiver to restore it.
+/// A snapshot is sent as its length (4 bytes, big-endian) and its bytes; the receiver answers
+/// with a line, `ok PID` or `error MESSAGE`. Returns the Process ID on the receiving side,
+/// where the process waits for `confirm` before it runs.
+pub fn send<S: Read + Write>(stream: &mut S, snapshot: &Snapshot) -> Result<u16, String> {
+    let bytes = snapshot.to_bytes()?;
+    stream.write_all(&byte_utils::u32_to_bytes(bytes.len() as u32))
+        .and_then(|_| stream.write_all(&bytes))
+        .and_then(|_| stream.flush())
+        .map_err(|err| format!("Cannot send the process: {}", err))?;
+    let reply = read_line(stream).map_err(|err| format!("No answer from the receiver: {}", err))?;
+    let reply = reply.trim_end();
+    if let Some(proc_id) = reply.strip_prefix("ok ") {
+        proc_id.parse().map_err(|_| format!("Unexpected answer: {}", reply))
+    } else if let Some(err) = reply.strip_prefix("error ") {
+        Err(format!("The receiver refused the process: {}", err))
+    } else {
+        Err(format!("Unexpected answer: {}", reply))
+    }
+}
+
+/// Tells the receiver that the process it restored was freed here, with a `done` line.
+pub fn confirm<S: Write>(stream: &mut S) -> Result<(), String> {
+    stream.write_all(b"done\n")
+        .and_then(|_| stream.flush())
+        .map_err(|err| format!("Cannot confirm the migration: {}", err))
+}
+
+/// Reads a snapshot sent by `send` from `stream`, restores it with `restore` and answers with
+/// the result, which is returned. A restored process must not run before `wait_confirmation`
+/// returns.
+pub fn receive<S, F>(stream: &mut S, restore: F) -> Result<u16, String>
+    where S: Read + Write,
+          F: FnOnce(Snapshot) -> Result<u16, String>
+{
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).map_err(|err| format!("Cannot receive the process: {}", err))?;
+    let len = byte_utils::u32_from_bytes(len) as usize;
+    let result = if len > MAX_SNAPSHOT_LEN {
+        Err(format!("The snapshot is {} bytes (at most {}).", len, MAX_SNAPSHOT_LEN))
+    } else {
+        let mut bytes = vec![0; len];
+        stream.read_exact(&mut bytes)
+            .map_err(|err| format!("Cannot receive the process: {}", err))?;
+        Snapshot::parse(&bytes).and_then(restore)
+    };
+    let reply = match result {
+        Ok(proc_id) => format!("ok {}\n", proc_id),
+        Err(ref err) => format!("error {}\n", err.replace('\n', " ")),
+    };
+    stream.write_all(reply.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|err| format!("Cannot answer the sender: {}", err))?;
+    result
+}
+
+/// Waits for the sender to `confirm` that it freed the process.
+pub fn wait_confirmation<S: Read>(stream: &mut S) -> Result<(), String> {
+    match read_line(stream) {
+        Ok(ref line) if line.trim_end() == "done" => Ok(()),
+        Ok(line) => Err(format!("Unexpected confirmation: {}", line.trim_end())),
+        Err(err) => Err(format!("The sender did not confirm the migration: {}", err)),
+    }
+}
+
+/// Reads a line a byte at a time, so that nothing past it is consumed. Fails if `stream` ends
+/// first.
+fn read_line<S: Read>(stream: &mut S) -> Result<String, String> {
+    let mut line = vec![];
+    let mut byte = [0];
+    while line.len() < MAX_LINE_LEN {
+        match stream.read(&mut byte) {
+            Ok(0) => return Err("the connection was closed".to_string()),
+            Ok(_) if byte[0] == b'\n' => return Ok(String::from_utf8_lossy(&line).into_owned()),
+            Ok(_) => line.push(byte[0]),
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    Err(format!("the line is longer than {} bytes", MAX_LINE_LEN))
+}
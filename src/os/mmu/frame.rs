@@ -0,0 +1,68 @@
+use os::consts::PAGE_LEN;
+
+/// The process and page that a frame holds, along with the page table mapping it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameOwner {
+    pub proc_id: u16,
+    pub page_num: usize,
+    pub page_tbl_ptr: usize,
+}
+
+/// Tracks which page-sized frames of physical RAM are in use.
+#[derive(Debug)]
+pub struct FrameTable {
+    /// RAM address of frame 0.
+    addr: usize,
+    owners: Vec<Option<FrameOwner>>,
+}
+
+impl FrameTable {
+    pub fn new(addr: usize, num_frames: usize) -> Self {
+        FrameTable {
+            addr,
+            owners: vec![None; num_frames],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    pub fn get_addr(&self) -> usize {
+        self.addr
+    }
+
+    pub fn get_frame_addr(&self, frame_num: usize) -> usize {
+        self.addr + frame_num * PAGE_LEN
+    }
+
+    pub fn get_owner(&self, frame_num: usize) -> Option<FrameOwner> {
+        self.owners.get(frame_num).and_then(|owner| *owner)
+    }
+
+    pub fn get_num_free(&self) -> usize {
+        self.owners.iter().filter(|owner| owner.is_none()).count()
+    }
+
+    /// Returns the number of the lowest free frame after assigning it to `owner`.
+    pub fn alloc(&mut self, owner: FrameOwner) -> Option<usize> {
+        let frame_num = self.owners.iter().position(|owner| owner.is_none())?;
+        self.owners[frame_num] = Some(owner);
+        Some(frame_num)
+    }
+
+    /// Points every frame of `proc_id` at the page table at `page_tbl_ptr`.
+    pub fn move_page_tbl(&mut self, proc_id: u16, page_tbl_ptr: usize) {
+        for owner in self.owners.iter_mut().filter_map(|owner| owner.as_mut()) {
+            if owner.proc_id == proc_id {
+                owner.page_tbl_ptr = page_tbl_ptr;
+            }
+        }
+    }
+
+    pub fn free(&mut self, frame_num: usize) {
+        if let Some(owner) = self.owners.get_mut(frame_num) {
+            *owner = None;
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fmt;
+use byte_utils::AccessResult;
+use os::consts::{PAGE_LEN, WORD_LEN};
+use super::{Access, Fault, FrameOwner, FrameTable, PageRef, Replacer, ReplacerKind, Swap,
+            PTE_ACCESSED, PTE_DIRTY, PTE_FRAME_MASK, PTE_PRESENT};
+use super::super::alloc::{Allocator, AllocatorKind, Block};
+use super::super::ram::Ram;
+
+/// Address of the word holding the lowest free block of the block region.
+pub const NEXT_BLK_ADDR: usize = 0x0000;
+/// Marks a full block region.
+pub const NULL_ADDR: u16 = 0x0000;
+/// The page reference trace stops growing past this many references.
+pub const MAX_TRACE_LEN: usize = 100_000;
+/// The fewest frames a machine can boot with: one for a page of instructions and one for the
+/// page of data an instruction accesses.
+pub const MIN_FRAMES: usize = 2;
+
+/// Physical memory, split into a region of variable-sized blocks for kernel structures (PCBs
+/// and page tables) and a region of page frames for process memory.
+/// The frame region starts at the first page boundary at or past a fifth of RAM.
+/// When no frame is free, a page fault evicts the frame chosen by the replacer, writing the
+/// page to swap if it is dirty.
+#[derive(Debug)]
+pub struct Memory {
+    ram: Ram,
+    allocator: Box<dyn Allocator>,
+    frames: FrameTable,
+    replacer_kind: ReplacerKind,
+    replacer: Box<dyn Replacer>,
+    swap: Swap,
+    stats: VmStats,
+    /// Every page referenced since boot or the last reset, without consecutive repeats, as
+    /// (run, page number).
+    /// Runs number the processes in the order they first referenced a page, so that pages of
+    /// processes that reused a PID are told apart.
+    trace: Vec<(usize, usize)>,
+    runs: HashMap<u16, usize>,
+    num_runs: usize,
+}
+
+/// Paging counters since boot or the last reset.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VmStats {
+    pub page_faults: usize,
+    pub evictions: usize,
+    pub swap_ins: usize,
+    pub swap_outs: usize,
+}
+
+impl Memory {
+    /// Creates `ram_len` bytes of RAM whose block region starts at `blk_region_addr`.
+    /// Returns an error if `replacer_kind` cannot run live.
+    pub fn new(ram_len: usize,
+               blk_region_addr: usize,
+               alloc_kind: AllocatorKind,
+               replacer_kind: ReplacerKind,
+               swap: Swap)
+               -> Result<Self, String> {
+        let frames_addr = get_frames_addr(ram_len, blk_region_addr);
+        let num_frames = get_num_frames(ram_len, blk_region_addr);
+        if num_frames < MIN_FRAMES {
+            return Err(format!("{} bytes of RAM leave {} page frames; at least {} are needed.",
+                               ram_len,
+                               num_frames,
+                               MIN_FRAMES));
+        }
+        let blk_region = Block::new(blk_region_addr, frames_addr - blk_region_addr);
+        let mut mem = Memory {
+            ram: Ram::new(ram_len),
+            allocator: alloc_kind.new_allocator(blk_region),
+            frames: FrameTable::new(frames_addr, num_frames),
+            replacer_kind,
+            replacer: replacer_kind.new_replacer(num_frames)?,
+            swap,
+            stats: VmStats::default(),
+            trace: Vec::new(),
+            runs: HashMap::new(),
+            num_runs: 0,
+        };
+        mem.update_next_blk();
+        Ok(mem)
+    }
+
+    pub fn get_ram(&self) -> &Ram {
+        &self.ram
+    }
+
+    pub fn get_ram_mut(&mut self) -> &mut Ram {
+        &mut self.ram
+    }
+
+    pub fn get_allocator(&self) -> &dyn Allocator {
+        &*self.allocator
+    }
+
+    pub fn get_frames(&self) -> &FrameTable {
+        &self.frames
+    }
+
+    pub fn get_replacer_kind(&self) -> ReplacerKind {
+        self.replacer_kind
+    }
+
+    pub fn get_swap(&self) -> &Swap {
+        &self.swap
+    }
+
+    pub fn get_stats(&self) -> VmStats {
+        self.stats
+    }
+
+    pub fn get_trace(&self) -> &[(usize, usize)] {
+        &self.trace
+    }
+
+    /// Zeroes the counters and empties the trace.
+    pub fn reset_stats(&mut self) {
+        self.stats = VmStats::default();
+        self.trace.clear();
+        self.runs.clear();
+        self.num_runs = 0;
+    }
+
+    /// Allocates a block of `len` bytes in the block region.
+    pub fn alloc(&mut self, len: usize) -> Option<usize> {
+        let addr = self.allocator.alloc(len);
+        self.update_next_blk();
+        addr
+    }
+
+    pub fn dealloc(&mut self, addr: usize) {
+        self.allocator.dealloc(addr);
+        self.update_next_blk();
+    }
+
+    /// Handles a page fault: brings the page of `owner` into a frame, evicting another page if
+    /// no frame is free, and maps it in the owner's page table.
+    /// The page is read back from swap if it was swapped out, otherwise it is copied from
+    /// `image` (zero-filled past its end).
+    pub fn load_page(&mut self, owner: FrameOwner, image: &[u8]) -> AccessResult<usize> {
+        let frame_num = match self.frames.alloc(owner) {
+            Some(frame_num) => frame_num,
+            None => {
+                let victim = self.replacer.choose_victim().map_err(|err| {
+                    error!("Proc {}: cannot load page {}: {}", owner.proc_id, owner.page_num, err);
+                })?;
+                self.evict(victim)?;
+                self.frames.alloc(owner).unwrap()
+            }
+        };
+        let frame_addr = self.frames.get_frame_addr(frame_num);
+        let page = (owner.proc_id, owner.page_num);
+        if self.swap.contains(page) {
+            let frame = self.ram.get_slice_mut(frame_addr..frame_addr + PAGE_LEN)?;
+            self.swap.read(page, frame).map_err(|err| {
+                error!("Proc {}: could not read page {} from swap: {}",
+                       owner.proc_id,
+                       owner.page_num,
+                       err);
+            })?;
+            self.stats.swap_ins += 1;
+        } else {
+            self.ram.zero(frame_addr..frame_addr + PAGE_LEN)?;
+            let page_start = owner.page_num * PAGE_LEN;
+            if page_start < image.len() {
+                let page_end = image.len().min(page_start + PAGE_LEN);
+                let len = page_end - page_start;
+                self.ram
+                    .get_slice_mut(frame_addr..frame_addr + len)?
+                    .clone_from_slice(&image[page_start..page_end]);
+            }
+        }
+        let pte_addr = owner.page_tbl_ptr + owner.page_num * WORD_LEN;
+        let pte = self.ram.get_u16_at(pte_addr)?;
+        self.ram.set_u16_at(pte_addr, pte & !PTE_FRAME_MASK | PTE_PRESENT | frame_num as u16)?;
+        self.replacer.on_load(frame_num);
+        self.stats.page_faults += 1;
+        Ok(frame_num)
+    }
+
+    pub fn free_frame(&mut self, frame_num: usize) {
+        self.frames.free(frame_num);
+        self.replacer.on_free(frame_num);
+    }
+
+    /// Frees the swap slots of every page of `proc_id`, which exited.
+    pub fn release_proc(&mut self, proc_id: u16) {
+        self.swap.free_proc(proc_id);
+        self.runs.remove(&proc_id);
+    }
+
+    /// Frees the swap slot of `page`, if it has one.
+    pub fn free_swapped_page(&mut self, page: PageRef) {
+        self.swap.free_page(page);
+    }
+
+    /// Points the frames of `proc_id` at its page table, which moved to `page_tbl_ptr`.
+    pub fn move_page_tbl(&mut self, proc_id: u16, page_tbl_ptr: usize) {
+        self.frames.move_page_tbl(proc_id, page_tbl_ptr);
+    }
+
+    /// Returns the swapped copy of `page`, if it has one.
+    pub fn read_swapped(&mut self, page: PageRef) -> Option<Vec<u8>> {
+        let mut buf = vec![0; PAGE_LEN];
+        if self.swap.contains(page) && self.swap.read(page, &mut buf).is_ok() {
+            Some(buf)
+        } else {
+            None
+        }
+    }
+
+    /// Unmaps the page held by `frame_num` from its owner's page table, writing it to swap
+    /// first if it is dirty.
+    fn evict(&mut self, frame_num: usize) -> AccessResult<()> {
+        let owner = self.frames.get_owner(frame_num).ok_or(())?;
+        let pte_addr = owner.page_tbl_ptr + owner.page_num * WORD_LEN;
+        let pte = self.ram.get_u16_at(pte_addr)?;
+        if pte & PTE_DIRTY != 0 {
+            let frame_addr = self.frames.get_frame_addr(frame_num);
+            let frame = self.ram.get_slice(frame_addr..frame_addr + PAGE_LEN)?;
+            self.swap.write((owner.proc_id, owner.page_num), frame).map_err(|err| {
+                error!("Proc {}: could not write page {} to swap: {}",
+                       owner.proc_id,
+                       owner.page_num,
+                       err);
+            })?;
+            self.stats.swap_outs += 1;
+        }
+        let flags = PTE_FRAME_MASK | PTE_PRESENT | PTE_ACCESSED | PTE_DIRTY;
+        self.ram.set_u16_at(pte_addr, pte & !flags)?;
+        self.free_frame(frame_num);
+        self.stats.evictions += 1;
+        debug!("Proc {}: evicted page {} from frame {}",
+               owner.proc_id,
+               owner.page_num,
+               frame_num);
+        Ok(())
+    }
+
+    /// The MMU: translates `vaddr` to a RAM address through the page table at `page_tbl_ptr`,
+    /// which has `num_pages` entries.
+    /// Marks the page as accessed (and dirty when written).
+    pub fn translate(&mut self,
+                     page_tbl_ptr: usize,
+                     num_pages: usize,
+                     vaddr: usize,
+                     access: Access)
+                     -> Result<usize, Fault> {
+        let page_num = vaddr / PAGE_LEN;
+        if page_num >= num_pages {
+            return Err(Fault::OutOfRange(vaddr));
+        }
+        let pte_addr = page_tbl_ptr + page_num * WORD_LEN;
+        let pte = self.ram.get_u16_at(pte_addr).unwrap();
+        if pte & access.get_pte_bit() == 0 {
+            return Err(Fault::Protection(vaddr));
+        }
+        if pte & PTE_PRESENT == 0 {
+            return Err(Fault::PageNotPresent(page_num));
+        }
+        let new_pte = if access == Access::Write {
+            pte | PTE_ACCESSED | PTE_DIRTY
+        } else {
+            pte | PTE_ACCESSED
+        };
+        self.ram.set_u16_at(pte_addr, new_pte).unwrap();
+        let frame_num = (pte & PTE_FRAME_MASK) as usize;
+        self.replacer.on_access(frame_num);
+        if let Some(owner) = self.frames.get_owner(frame_num) {
+            self.record_ref(owner.proc_id, page_num);
+        }
+        Ok(self.frames.get_frame_addr(frame_num) + vaddr % PAGE_LEN)
+    }
+
+    fn record_ref(&mut self, proc_id: u16, page_num: usize) {
+        let num_runs = &mut self.num_runs;
+        let run = *self.runs.entry(proc_id).or_insert_with(|| {
+            *num_runs += 1;
+            *num_runs - 1
+        });
+        let page = (run, page_num);
+        if self.trace.last() == Some(&page) {
+            return;
+        }
+        if self.trace.len() < MAX_TRACE_LEN {
+            self.trace.push(page);
+            if self.trace.len() == MAX_TRACE_LEN {
+                warn!("The page reference trace is full at {} references", MAX_TRACE_LEN);
+            }
+        }
+    }
+
+    /// Points NEXT_BLK at the lowest free block.
+    fn update_next_blk(&mut self) {
+        let next_blk = self.allocator
+            .get_free_blks()
+            .first()
+            .map(|blk| blk.addr as u16)
+            .unwrap_or(NULL_ADDR);
+        self.ram.set_u16_at(NEXT_BLK_ADDR, next_blk).unwrap();
+    }
+}
+
+/// Returns the number of page frames of `ram_len` bytes of RAM whose block region starts at
+/// `blk_region_addr`.
+pub fn get_num_frames(ram_len: usize, blk_region_addr: usize) -> usize {
+    ram_len.saturating_sub(get_frames_addr(ram_len, blk_region_addr)) / PAGE_LEN
+}
+
+fn get_frames_addr(ram_len: usize, blk_region_addr: usize) -> usize {
+    round_up(ram_len / 5, PAGE_LEN).max(round_up(blk_region_addr, PAGE_LEN))
+}
+
+pub fn round_up(len: usize, multiple: usize) -> usize {
+    len.div_ceil(multiple) * multiple
+}
+
+impl fmt::Display for VmStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} page faults, {} evictions, {} swap ins, {} swap outs",
+               self.page_faults,
+               self.evictions,
+               self.swap_ins,
+               self.swap_outs)
+    }
+}
@@ -0,0 +1,11 @@
+pub use self::frame::*;
+pub use self::memory::*;
+pub use self::page::*;
+pub use self::replace::*;
+pub use self::swap::*;
+
+mod frame;
+mod memory;
+mod page;
+mod replace;
+mod swap;
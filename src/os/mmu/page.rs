@@ -0,0 +1,39 @@
+// A page table entry (PTE) is one word:
+//
+//   15   14   13   12   11   10   9 .. 0
+//   EXEC WRIT READ PRES DIRT ACCS FRAME
+pub const PTE_FRAME_MASK: u16 = 0x03FF;
+pub const PTE_ACCESSED: u16 = 1 << 10;
+pub const PTE_DIRTY: u16 = 1 << 11;
+pub const PTE_PRESENT: u16 = 1 << 12;
+pub const PTE_READ: u16 = 1 << 13;
+pub const PTE_WRITE: u16 = 1 << 14;
+pub const PTE_EXEC: u16 = 1 << 15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    Exec,
+}
+
+impl Access {
+    /// Returns the protection bit that allows this kind of access.
+    pub fn get_pte_bit(&self) -> u16 {
+        match *self {
+            Access::Read => PTE_READ,
+            Access::Write => PTE_WRITE,
+            Access::Exec => PTE_EXEC,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    /// The page (by number) is valid but is not in a frame.
+    PageNotPresent(usize),
+    /// The page's protection bits do not allow the access to the address.
+    Protection(usize),
+    /// The address is past the end of the page table.
+    OutOfRange(usize),
+}
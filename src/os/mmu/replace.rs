@@ -0,0 +1,337 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// A reference to a page of a process: (process ID, page number).
+pub type PageRef = (u16, usize);
+
+/// Chooses which frame to evict when a page fault finds no free frame.
+/// The MMU reports every frame that is loaded, accessed or freed.
+pub trait Replacer: fmt::Debug + Send {
+    fn on_load(&mut self, frame_num: usize);
+
+    fn on_access(&mut self, frame_num: usize);
+
+    fn on_free(&mut self, frame_num: usize);
+
+    /// Returns the frame to evict. Only called when every frame is in use.
+    /// Returns an error if there is no frame in use to evict.
+    fn choose_victim(&mut self) -> Result<usize, String>;
+}
+
+/// Evicts the frame that was loaded first.
+#[derive(Debug)]
+pub struct FifoReplacer {
+    queue: VecDeque<usize>,
+}
+
+/// Evicts the frame that was accessed the longest time ago.
+#[derive(Debug)]
+pub struct LruReplacer {
+    clock: u64,
+    last_access: HashMap<usize, u64>,
+}
+
+/// Sweeps a hand over the frames, giving every frame that was accessed since the last sweep a
+/// second chance.
+#[derive(Debug)]
+pub struct ClockReplacer {
+    hand: usize,
+    /// The reference bit of every frame, `None` for free frames.
+    referenced: Vec<Option<bool>>,
+}
+
+impl FifoReplacer {
+    pub fn new() -> Self {
+        FifoReplacer { queue: VecDeque::new() }
+    }
+}
+
+impl LruReplacer {
+    pub fn new() -> Self {
+        LruReplacer {
+            clock: 0,
+            last_access: HashMap::new(),
+        }
+    }
+}
+
+impl ClockReplacer {
+    pub fn new(num_frames: usize) -> Self {
+        ClockReplacer {
+            hand: 0,
+            referenced: vec![None; num_frames],
+        }
+    }
+}
+
+impl Replacer for FifoReplacer {
+    fn on_load(&mut self, frame_num: usize) {
+        self.queue.push_back(frame_num);
+    }
+
+    fn on_access(&mut self, _: usize) {}
+
+    fn on_free(&mut self, frame_num: usize) {
+        self.queue.retain(|num| *num != frame_num);
+    }
+
+    fn choose_victim(&mut self) -> Result<usize, String> {
+        self.queue.front().cloned().ok_or_else(no_victim)
+    }
+}
+
+impl Replacer for LruReplacer {
+    fn on_load(&mut self, frame_num: usize) {
+        self.on_access(frame_num);
+    }
+
+    fn on_access(&mut self, frame_num: usize) {
+        self.clock += 1;
+        self.last_access.insert(frame_num, self.clock);
+    }
+
+    fn on_free(&mut self, frame_num: usize) {
+        self.last_access.remove(&frame_num);
+    }
+
+    fn choose_victim(&mut self) -> Result<usize, String> {
+        self.last_access
+            .iter()
+            .min_by_key(|&(_, time)| *time)
+            .map(|(frame_num, _)| *frame_num)
+            .ok_or_else(no_victim)
+    }
+}
+
+impl Replacer for ClockReplacer {
+    fn on_load(&mut self, frame_num: usize) {
+        self.referenced[frame_num] = Some(true);
+    }
+
+    fn on_access(&mut self, frame_num: usize) {
+        self.referenced[frame_num] = Some(true);
+    }
+
+    fn on_free(&mut self, frame_num: usize) {
+        self.referenced[frame_num] = None;
+    }
+
+    fn choose_victim(&mut self) -> Result<usize, String> {
+        // The first sweep clears every reference bit, so the second stops at a frame in use.
+        for _ in 0..2 * self.referenced.len() {
+            let frame_num = self.hand;
+            self.hand = (self.hand + 1) % self.referenced.len();
+            match self.referenced[frame_num] {
+                Some(true) => self.referenced[frame_num] = Some(false),
+                Some(false) => return Ok(frame_num),
+                None => (),
+            }
+        }
+        Err(no_victim())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplacerKind {
+    Fifo,
+    Lru,
+    Clock,
+    /// Evicts the page whose next use is the farthest in the future.
+    /// Only usable on a recorded trace of page references (see `simulate`).
+    Optimal,
+}
+
+pub const REPLACER_KINDS: [ReplacerKind; 4] =
+    [ReplacerKind::Fifo, ReplacerKind::Lru, ReplacerKind::Clock, ReplacerKind::Optimal];
+
+impl ReplacerKind {
+    /// Returns a replacer of this kind for `num_frames` frames.
+    pub fn new_replacer(&self, num_frames: usize) -> Result<Box<dyn Replacer>, String> {
+        match *self {
+            ReplacerKind::Fifo => Ok(Box::new(FifoReplacer::new())),
+            ReplacerKind::Lru => Ok(Box::new(LruReplacer::new())),
+            ReplacerKind::Clock => Ok(Box::new(ClockReplacer::new(num_frames))),
+            ReplacerKind::Optimal => {
+                Err("The optimal replacer needs future references; it can only be simulated \
+                     on a recorded trace."
+                    .to_string())
+            }
+        }
+    }
+}
+
+impl FromStr for ReplacerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(ReplacerKind::Fifo),
+            "lru" => Ok(ReplacerKind::Lru),
+            "clock" => Ok(ReplacerKind::Clock),
+            "optimal" => Ok(ReplacerKind::Optimal),
+            _ => Err(format!("{}: unknown replacer (expected fifo, lru, clock or optimal)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ReplacerKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ReplacerKind::Fifo => "fifo",
+            ReplacerKind::Lru => "lru",
+            ReplacerKind::Clock => "clock",
+            ReplacerKind::Optimal => "optimal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the number of page faults `kind` causes when the pages of `trace` are referenced in
+/// order on `num_frames` frames.
+/// Returns an error if there are no frames.
+pub fn simulate<T>(trace: &[T], num_frames: usize, kind: ReplacerKind) -> Result<usize, String>
+    where T: Copy + Eq + Hash
+{
+    if num_frames == 0 {
+        return Err(no_victim());
+    }
+    if kind == ReplacerKind::Optimal {
+        return Ok(simulate_optimal(trace, num_frames));
+    }
+    let mut replacer = kind.new_replacer(num_frames)?;
+    let mut frames: Vec<Option<T>> = vec![None; num_frames];
+    let mut num_faults = 0;
+    for page_ref in trace {
+        let frame_num = match frames.iter().position(|page| *page == Some(*page_ref)) {
+            Some(frame_num) => frame_num,
+            None => {
+                num_faults += 1;
+                let frame_num = match frames.iter().position(|page| page.is_none()) {
+                    Some(frame_num) => frame_num,
+                    None => {
+                        let victim = replacer.choose_victim()?;
+                        replacer.on_free(victim);
+                        victim
+                    }
+                };
+                frames[frame_num] = Some(*page_ref);
+                replacer.on_load(frame_num);
+                frame_num
+            }
+        };
+        replacer.on_access(frame_num);
+    }
+    Ok(num_faults)
+}
+
+fn no_victim() -> String {
+    "No frame is in use to evict.".to_string()
+}
+
+fn simulate_optimal<T>(trace: &[T], num_frames: usize) -> usize
+    where T: Copy + Eq + Hash
+{
+    // The index of the next reference to the same page, for every reference of the trace.
+    let mut next_uses = vec![trace.len(); trace.len()];
+    let mut later_uses: HashMap<T, usize> = HashMap::new();
+    for (idx, page_ref) in trace.iter().enumerate().rev() {
+        if let Some(next_use) = later_uses.insert(*page_ref, idx) {
+            next_uses[idx] = next_use;
+        }
+    }
+    // The resident pages along with their next use.
+    let mut frames: Vec<(T, usize)> = Vec::with_capacity(num_frames);
+    let mut num_faults = 0;
+    for (idx, page_ref) in trace.iter().enumerate() {
+        if let Some(frame) = frames.iter_mut().find(|frame| frame.0 == *page_ref) {
+            frame.1 = next_uses[idx];
+            continue;
+        }
+        num_faults += 1;
+        if frames.len() < num_frames {
+            frames.push((*page_ref, next_uses[idx]));
+        } else {
+            let (victim, _) = frames.iter()
+                .enumerate()
+                .max_by_key(|&(_, frame)| frame.1)
+                .unwrap();
+            frames[victim] = (*page_ref, next_uses[idx]);
+        }
+    }
+    num_faults
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simulate, ReplacerKind};
+
+    /// The usual textbook reference string.
+    const TRACE: [u16; 20] = [7, 0, 1, 2, 0, 3, 0, 4, 2, 3, 0, 3, 2, 1, 2, 0, 1, 7, 0, 1];
+
+    /// Returns the pages that a live replacer of `kind` evicts, in order, as `simulate`
+    /// drives it.
+    fn victims(kind: ReplacerKind, num_frames: usize) -> Vec<u16> {
+        let mut replacer = kind.new_replacer(num_frames).unwrap();
+        let mut frames: Vec<Option<u16>> = vec![None; num_frames];
+        let mut victims = vec![];
+        for page in TRACE.iter() {
+            let frame_num = match frames.iter().position(|frame| *frame == Some(*page)) {
+                Some(frame_num) => frame_num,
+                None => {
+                    let frame_num = match frames.iter().position(|frame| frame.is_none()) {
+                        Some(frame_num) => frame_num,
+                        None => {
+                            let victim = replacer.choose_victim().unwrap();
+                            replacer.on_free(victim);
+                            victims.push(frames[victim].unwrap());
+                            victim
+                        }
+                    };
+                    frames[frame_num] = Some(*page);
+                    replacer.on_load(frame_num);
+                    frame_num
+                }
+            };
+            replacer.on_access(frame_num);
+        }
+        victims
+    }
+
+    #[test]
+    fn replacers_evict_in_order() {
+        assert_eq!(victims(ReplacerKind::Fifo, 3),
+                   vec![7, 0, 1, 2, 3, 0, 4, 2, 3, 0, 1, 2]);
+        assert_eq!(victims(ReplacerKind::Lru, 3), vec![7, 1, 2, 3, 0, 4, 0, 3, 2]);
+        assert_eq!(victims(ReplacerKind::Clock, 3),
+                   vec![7, 1, 2, 0, 3, 4, 2, 0, 3, 1, 2]);
+    }
+
+    #[test]
+    fn simulated_faults_match_the_textbook() {
+        assert_eq!(simulate(&TRACE, 3, ReplacerKind::Fifo), Ok(15));
+        assert_eq!(simulate(&TRACE, 3, ReplacerKind::Lru), Ok(12));
+        assert_eq!(simulate(&TRACE, 3, ReplacerKind::Clock), Ok(14));
+        assert_eq!(simulate(&TRACE, 3, ReplacerKind::Optimal), Ok(9));
+        // Every page fits.
+        assert_eq!(simulate(&TRACE, 6, ReplacerKind::Lru), Ok(6));
+        assert!(simulate(&TRACE, 0, ReplacerKind::Fifo).is_err());
+    }
+
+    #[test]
+    fn no_victim_without_frames_in_use() {
+        for kind in &[ReplacerKind::Fifo, ReplacerKind::Lru, ReplacerKind::Clock] {
+            assert!(kind.new_replacer(0).unwrap().choose_victim().is_err());
+            let mut replacer = kind.new_replacer(2).unwrap();
+            assert!(replacer.choose_victim().is_err());
+            replacer.on_load(1);
+            assert_eq!(replacer.choose_victim(), Ok(1));
+        }
+    }
+
+    #[test]
+    fn optimal_cannot_run_live() {
+        assert!(ReplacerKind::Optimal.new_replacer(3).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use os::consts::PAGE_LEN;
+use super::PageRef;
+
+/// Numbers the swap files of the machines of this host process.
+static NUM_SWAP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Page-sized slots of a host file holding the evicted pages that were written to.
+/// A page keeps its slot until its process exits, so a clean page that was swapped in can be
+/// evicted again without writing it back.
+/// The file is hidden in the directory mounted as the file system, where `ls` does not list
+/// it, and is removed along with the swap.
+#[derive(Debug)]
+pub struct Swap {
+    path: PathBuf,
+    file: fs::File,
+    slots: HashMap<PageRef, usize>,
+    free_slots: BTreeSet<usize>,
+    num_slots: usize,
+}
+
+impl Swap {
+    /// Creates an empty swap file of its own in `dir`.
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        let num = NUM_SWAP_FILES.fetch_add(1, Ordering::SeqCst);
+        let path = dir.join(format!(".swap-{}-{}", process::id(), num));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Swap {
+            path,
+            file,
+            slots: HashMap::new(),
+            free_slots: BTreeSet::new(),
+            num_slots: 0,
+        })
+    }
+
+    pub fn contains(&self, page: PageRef) -> bool {
+        self.slots.contains_key(&page)
+    }
+
+    /// Returns the number of slots holding a page.
+    pub fn get_num_used(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of slots the file has grown to.
+    pub fn get_num_slots(&self) -> usize {
+        self.num_slots
+    }
+
+    /// Reads the swapped copy of `page` into `buf`, which is one page long.
+    pub fn read(&mut self, page: PageRef, buf: &mut [u8]) -> io::Result<()> {
+        let slot = *self.slots
+            .get(&page)
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "page is not in swap"))?;
+        self.file.seek(SeekFrom::Start((slot * PAGE_LEN) as u64))?;
+        self.file.read_exact(buf)
+    }
+
+    /// Writes `buf`, which is one page long, to the slot of `page`, giving it one if needed.
+    pub fn write(&mut self, page: PageRef, buf: &[u8]) -> io::Result<()> {
+        let slot = match self.slots.get(&page) {
+            Some(slot) => *slot,
+            None => self.alloc_slot(),
+        };
+        self.slots.insert(page, slot);
+        self.file.seek(SeekFrom::Start((slot * PAGE_LEN) as u64))?;
+        self.file.write_all(buf)
+    }
+
+    pub fn free_page(&mut self, page: PageRef) {
+        if let Some(slot) = self.slots.remove(&page) {
+            self.free_slots.insert(slot);
+        }
+    }
+
+    /// Frees the slots of every page of `proc_id`.
+    pub fn free_proc(&mut self, proc_id: u16) {
+        let pages = self.slots
+            .keys()
+            .filter(|&&(pid, _)| pid == proc_id)
+            .cloned()
+            .collect::<Vec<PageRef>>();
+        for page in pages {
+            self.free_page(page);
+        }
+    }
+
+    /// Returns the lowest free slot, growing the file by a slot if none is free.
+    fn alloc_slot(&mut self) -> usize {
+        match self.free_slots.iter().next().cloned() {
+            Some(slot) => {
+                self.free_slots.remove(&slot);
+                slot
+            }
+            None => {
+                self.num_slots += 1;
+                self.num_slots - 1
+            }
+        }
+    }
+}
+
+impl Drop for Swap {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Could not remove the swap file {}: {}", self.path.display(), err);
+        }
+    }
+}
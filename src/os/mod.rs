@@ -1,8 +1,35 @@
+pub use self::cache::{CachePolicy, CacheStats};
+pub use self::config::{Config, CONFIG_ALIASES, CONFIG_KEYS};
+pub use self::console::{Console, OutputBuffer};
+pub use self::cycles::CycleCosts;
+pub use self::debug::{Debugger, StopReason};
+pub use self::exec::ExecResult;
+pub use self::gdb::{GdbStub, DATA_ADDR_BASE};
+pub use self::instr::{DebugInfo, Executable, InstructionType, SectionKind, Symbol, EXE_VERSION};
+pub use self::ps::{ExitedProc, ProcInfo, Status as ProcessStatus, MAX_EXITED_PROCS};
+pub use self::replay::{RecordedProgram, RecordedSlice, RunLog, RUN_LOG_VERSION};
+pub use self::snapshot::{ProcSnapshot, Snapshot, SNAPSHOT_VERSION};
 pub use self::sys::*;
+pub use self::timeline::{Event, EventKind, Timeline, MAX_TIMELINE_EVENTS};
+pub use self::trace::{TraceFilter, Tracer};
 
+mod alloc;
+mod cache;
+mod config;
+mod console;
 mod cpu;
+mod cycles;
+mod debug;
 mod exec;
 mod fs;
+mod gdb;
 mod instr;
+mod migrate;
+mod mmu;
 mod ps;
+mod ram;
+mod replay;
+mod snapshot;
 mod sys;
+mod timeline;
+mod trace;
@@ -1,125 +1,712 @@
+use std::collections::HashMap;
 use std::fmt;
-use os::consts::STACK_LEN;
-use super::super::cpu::Cpu;
-use super::super::instr::{InstructionBlock, INSTRUCTION_LEN};
-
-// Effectively `std::mem::size_of::<Pcb>()`
-pub const PCB_LEN: usize = HEADER_LEN + CTX_LEN + INSTRUCTION_BLK_LEN;
-const HEADER_LEN: usize = 2 + 2 + CTX_LEN;
-const CTX_LEN: usize = 14;
-// 256 instructions limit
-pub const INSTRUCTION_BLK_LEN: usize = 256 * INSTRUCTION_LEN;
-
-#[derive(Debug)]
-pub struct Pcb {
-    pub header: Header,
-    pub stack: Stack,
-    pub instr: InstructionBlock,
-}
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use byte_utils::AccessResult;
+use os::consts::{MAX_HEAP_LEN, PAGE_LEN, WORD_LEN};
+use super::super::cache::CacheStats;
+use super::super::cpu::{Cpu, Segment};
+use super::super::instr::{Executable, Instruction, InstructionBlock, Op, INSTRUCTION_LEN};
+use super::super::mmu::{self, Access, Fault, FrameOwner, Memory, PTE_EXEC, PTE_FRAME_MASK,
+                        PTE_PRESENT, PTE_READ, PTE_WRITE};
 
-#[derive(Debug)]
-pub struct Header {
-    pub id: u16,
-    pub exe_file_name: String,
-    pub status: Status,
-    pub ctx: Context,
+// Offsets of the PCB fields relative to the start of the PCB (see design.md).
+const PID_OFFSET: usize = 0x0000;
+const STATUS_OFFSET: usize = 0x0002;
+const DATA_PTR_OFFSET: usize = 0x0004;
+const STACK_PTR_OFFSET: usize = 0x0006;
+const PAGE_TBL_PTR_OFFSET: usize = 0x0008;
+const NUM_PAGES_OFFSET: usize = 0x000a;
+const DATA_LEN_OFFSET: usize = 0x000c;
+const HEAP_PTR_OFFSET: usize = 0x000e;
+const HEAP_LEN_OFFSET: usize = 0x0010;
+/// The CPU context comes last, as its length depends on the number of registers.
+const CTX_OFFSET: usize = 0x0012;
+
+/// A handle to a PCB that lives in RAM.
+/// Every accessor reads or writes the PCB's bytes at their documented offsets, and process
+/// memory is reached through the PCB's page table.
+pub struct Pcb {
+    addr: usize,
+    exe_file_name: String,
+    /// The program's instructions, followed by its initialized data from the first stack
+    /// page on. These back the pages that have never been written to.
+    image: Vec<u8>,
+    /// The program's instructions, decoded when it was loaded.
+    ops: Vec<Op>,
+    /// The program's entry point, symbols and debug information; its code and data are in
+    /// `image`.
+    exe_info: Executable,
+    /// The cycles the process has executed. Kept out of RAM, as it does not fit in a word.
+    cycles: u64,
+    /// The cache lookups of its fetches, loads and stores.
+    cache_stats: CacheStats,
+    num_registers: usize,
+    mem: Arc<Mutex<Memory>>,
+    is_released: bool,
+    /// Whether the process is kept from running, e.g. while it is being migrated.
+    is_frozen: bool,
+    /// How many processes the process table allocated before this one. Unlike the PID, it is
+    /// never reused.
+    load_seq: u64,
 }
 
-#[derive(Debug, PartialEq)]
+enum_from_primitive! {
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Status {
-    Ready,
-    Blocked,
-    Executing,
-    Killed,
+    Ready = 0x00,
+    Blocked = 0x01,
+    Executing = 0x02,
+    Killed = 0x03,
+}
 }
 
-type Context = Cpu;
+pub type Context = Cpu;
 
-pub struct Stack {
-    pub bytes: [u8; STACK_LEN],
+/// A snapshot of the state of a process.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcInfo {
+    pub id: u16,
+    pub exe_file_name: String,
+    pub status: Status,
+    pub instr_ptr: u16,
+    pub registers: Vec<u16>,
+    pub data_len: u16,
+    pub stack: Vec<u8>,
+    pub heap_len: u16,
+    pub cycles: u64,
+    pub cache_stats: CacheStats,
 }
 
 impl Pcb {
-    pub fn new(proc_id: u16, exe_file_name: String, instr: InstructionBlock) -> Pcb {
-        Pcb {
-            header: Header {
-                id: proc_id,
-                exe_file_name: exe_file_name,
-                status: Status::Ready,
-                ctx: Context::new(),
+    /// Allocates and writes a fresh PCB, with room for the context of `num_registers`
+    /// registers, and its page table, with a stack of `stack_len` bytes past the executable's
+    /// initialized data and an empty heap.
+    /// No page is brought into a frame until it is first accessed.
+    pub fn new(mem: Arc<Mutex<Memory>>,
+               proc_id: u16,
+               exe_file_name: String,
+               exe: &Executable,
+               num_registers: usize,
+               stack_len: usize)
+               -> Result<Pcb, String> {
+        let block = InstructionBlock::new(&exe.code)?;
+        let code = block.to_bytes();
+        let header_len = get_header_len(num_registers);
+        let num_data_pages = mmu::round_up(code.len(), PAGE_LEN) / PAGE_LEN;
+        let stack_ptr = num_data_pages * PAGE_LEN;
+        let heap_ptr = stack_ptr + exe.data.len() + stack_len;
+        if heap_ptr + MAX_HEAP_LEN > 0x10000 {
+            return Err(format!("{}: the program does not fit in 64 KiB of virtual memory.",
+                               exe_file_name));
+        }
+        let mut image = code.clone();
+        image.resize(stack_ptr, 0);
+        image.extend_from_slice(&exe.data);
+        let num_pages = mmu::round_up(heap_ptr, PAGE_LEN) / PAGE_LEN;
+        let page_tbl_len = num_pages * WORD_LEN;
+        let addr = {
+            let mut mem = mem.lock().unwrap();
+            let addr = mem.alloc(header_len)
+                .ok_or(format!("Out of memory: no free block of {} bytes.", header_len))?;
+            let page_tbl_ptr = match mem.alloc(page_tbl_len) {
+                Some(page_tbl_ptr) => page_tbl_ptr,
+                None => {
+                    mem.dealloc(addr);
+                    return Err(format!("Out of memory: no free block of {} bytes.",
+                                       page_tbl_len));
+                }
+            };
+            let ram = mem.get_ram_mut();
+            ram.zero(addr..addr + header_len).unwrap();
+            ram.set_u16_at(addr + PID_OFFSET, proc_id).unwrap();
+            ram.set_u16_at(addr + STATUS_OFFSET, Status::Ready as u16).unwrap();
+            ram.set_u16_at(addr + DATA_PTR_OFFSET, 0).unwrap();
+            ram.set_u16_at(addr + STACK_PTR_OFFSET, stack_ptr as u16).unwrap();
+            ram.set_u16_at(addr + PAGE_TBL_PTR_OFFSET, page_tbl_ptr as u16).unwrap();
+            ram.set_u16_at(addr + NUM_PAGES_OFFSET, num_pages as u16).unwrap();
+            ram.set_u16_at(addr + DATA_LEN_OFFSET, code.len() as u16).unwrap();
+            ram.set_u16_at(addr + HEAP_PTR_OFFSET, heap_ptr as u16).unwrap();
+            ram.set_u16_at(addr + HEAP_LEN_OFFSET, 0).unwrap();
+            ram.set_u16_at(addr + CTX_OFFSET, exe.entry).unwrap();
+            for page_num in 0..num_pages {
+                let prot = if page_num < num_data_pages {
+                    PTE_READ | PTE_EXEC
+                } else {
+                    PTE_READ | PTE_WRITE
+                };
+                ram.set_u16_at(page_tbl_ptr + page_num * WORD_LEN, prot).unwrap();
+            }
+            addr
+        };
+        Ok(Pcb {
+            addr,
+            exe_file_name,
+            image,
+            ops: block.decode(),
+            exe_info: Executable {
+                code: vec![],
+                data: vec![],
+                ..exe.clone()
             },
-            stack: Stack::new(),
-            instr: instr,
+            cycles: 0,
+            cache_stats: CacheStats::default(),
+            num_registers,
+            mem,
+            is_released: false,
+            is_frozen: false,
+            load_seq: 0,
+        })
+    }
+
+    /// Frees the PCB's frames, page table and block, detaching the handle from RAM so that it
+    /// can no longer be executed.
+    pub fn release(&mut self) {
+        if self.is_released {
+            return;
+        }
+        self.set_status(Status::Killed);
+        let mut mem = self.mem.lock().unwrap();
+        let page_tbl_ptr = get_field_in(&mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize;
+        for pte in self.get_ptes_in(&mem) {
+            if pte & PTE_PRESENT != 0 {
+                mem.free_frame((pte & PTE_FRAME_MASK) as usize);
+            }
         }
+        let proc_id = get_field_in(&mem, self.addr, PID_OFFSET);
+        mem.release_proc(proc_id);
+        mem.dealloc(page_tbl_ptr);
+        mem.dealloc(self.addr);
+        self.is_released = true;
     }
 
+    /// Restores the CPU context and points the segment registers at the process's memory.
     pub fn load_cpu_ctx(&self, cpu: &mut Cpu) {
-        let ctx = &self.header.ctx;
+        let ctx = self.get_ctx();
         cpu.instr_ptr = ctx.instr_ptr;
         cpu.registers.clone_from_slice(&ctx.registers);
+        cpu.code_seg = self.get_code_seg();
+        cpu.data_seg = self.get_data_seg();
+    }
+
+    /// Returns the segment of the program's instructions.
+    pub fn get_code_seg(&self) -> Segment {
+        Segment {
+            base: self.get_data_ptr(),
+            limit: self.get_data_len(),
+        }
+    }
+
+    /// Returns the segment addressed by `Load` and `Store`: the stack, directly followed by
+    /// the heap.
+    pub fn get_data_seg(&self) -> Segment {
+        let stack_ptr = self.get_stack_ptr();
+        Segment {
+            base: stack_ptr,
+            limit: self.get_heap_ptr() - stack_ptr + self.get_heap_len(),
+        }
+    }
+
+    /// Moves the end of the heap by `delta` bytes, adding or removing pages as needed.
+    /// Returns the previous end of the heap, as an address of the data segment.
+    pub fn sbrk(&mut self, delta: i16) -> Result<u16, String> {
+        let mut mem = self.mem.lock().unwrap();
+        let proc_id = get_field_in(&mem, self.addr, PID_OFFSET);
+        let stack_ptr = get_field_in(&mem, self.addr, STACK_PTR_OFFSET) as usize;
+        let heap_ptr = get_field_in(&mem, self.addr, HEAP_PTR_OFFSET) as usize;
+        let heap_len = get_field_in(&mem, self.addr, HEAP_LEN_OFFSET) as usize;
+        let new_heap_len = heap_len as isize + delta as isize;
+        if new_heap_len < 0 || new_heap_len as usize > MAX_HEAP_LEN {
+            return Err(format!("The heap cannot hold {} bytes (at most {}).",
+                               new_heap_len,
+                               MAX_HEAP_LEN));
+        }
+        let new_heap_len = new_heap_len as usize;
+        let num_pages = get_field_in(&mem, self.addr, NUM_PAGES_OFFSET) as usize;
+        let new_num_pages = mmu::round_up(heap_ptr + new_heap_len, PAGE_LEN) / PAGE_LEN;
+        if new_num_pages != num_pages {
+            self.resize_page_tbl(&mut mem, proc_id, new_num_pages)?;
+        }
+        mem.get_ram_mut().set_u16_at(self.addr + HEAP_LEN_OFFSET, new_heap_len as u16).unwrap();
+        debug!("Proc {}: heap resized from {} to {} bytes",
+               proc_id,
+               heap_len,
+               new_heap_len);
+        Ok((heap_ptr - stack_ptr + heap_len) as u16)
+    }
+
+    /// Moves the page table to a block of the allocator that holds `new_num_pages` entries.
+    /// Pages past the new end lose their frame and swap slot; new pages are read-write.
+    fn resize_page_tbl(&self,
+                       mem: &mut Memory,
+                       proc_id: u16,
+                       new_num_pages: usize)
+                       -> Result<(), String> {
+        let page_tbl_ptr = get_field_in(mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize;
+        let ptes = self.get_ptes_in(mem);
+        let new_page_tbl_ptr = mem.alloc(new_num_pages * WORD_LEN)
+            .ok_or(format!("Out of memory: no free block of {} bytes.",
+                           new_num_pages * WORD_LEN))?;
+        for (page_num, pte) in ptes.iter().enumerate().skip(new_num_pages) {
+            if pte & PTE_PRESENT != 0 {
+                mem.free_frame((pte & PTE_FRAME_MASK) as usize);
+            }
+            mem.free_swapped_page((proc_id, page_num));
+        }
+        for page_num in 0..new_num_pages {
+            let pte = ptes.get(page_num).cloned().unwrap_or(PTE_READ | PTE_WRITE);
+            mem.get_ram_mut().set_u16_at(new_page_tbl_ptr + page_num * WORD_LEN, pte).unwrap();
+        }
+        mem.dealloc(page_tbl_ptr);
+        mem.move_page_tbl(proc_id, new_page_tbl_ptr);
+        let ram = mem.get_ram_mut();
+        ram.set_u16_at(self.addr + PAGE_TBL_PTR_OFFSET, new_page_tbl_ptr as u16).unwrap();
+        ram.set_u16_at(self.addr + NUM_PAGES_OFFSET, new_num_pages as u16).unwrap();
+        Ok(())
     }
 
     pub fn save_cpu_ctx(&mut self, cpu: &Cpu) {
-        let mut ctx = &mut self.header.ctx;
-        ctx.instr_ptr = cpu.instr_ptr;
-        ctx.registers.clone_from_slice(&cpu.registers[..]);
+        let mut mem = self.mem.lock().unwrap();
+        let ram = mem.get_ram_mut();
+        let ctx_addr = self.addr + CTX_OFFSET;
+        ram.set_u16_at(ctx_addr, cpu.instr_ptr).unwrap();
+        for (idx, reg) in cpu.registers.iter().enumerate() {
+            ram.set_u16_at(ctx_addr + WORD_LEN * (idx + 1), *reg).unwrap();
+        }
+    }
+
+    pub fn get_ctx(&self) -> Context {
+        let mem = self.mem.lock().unwrap();
+        let ram = mem.get_ram();
+        let ctx_addr = self.addr + CTX_OFFSET;
+        let mut ctx = Context::init(self.num_registers);
+        ctx.instr_ptr = ram.get_u16_at(ctx_addr).unwrap();
+        for (idx, reg) in ctx.registers.iter_mut().enumerate() {
+            *reg = ram.get_u16_at(ctx_addr + WORD_LEN * (idx + 1)).unwrap();
+        }
+        ctx
+    }
+
+    pub fn get_info(&self) -> ProcInfo {
+        let ctx = self.get_ctx();
+        ProcInfo {
+            id: self.get_id(),
+            exe_file_name: self.exe_file_name.clone(),
+            status: self.get_status(),
+            instr_ptr: ctx.instr_ptr,
+            registers: ctx.registers,
+            data_len: self.get_data_len(),
+            stack: self.get_stack(),
+            heap_len: self.get_heap_len(),
+            cycles: self.cycles,
+            cache_stats: self.cache_stats,
+        }
+    }
+
+    /// Fetches the instruction at virtual address `vaddr`.
+    pub fn get_instruction_at(&self, vaddr: usize) -> AccessResult<Instruction> {
+        if !is_instr_aligned(vaddr) {
+            return Err(());
+        }
+        let mut mem = self.mem.lock().unwrap();
+        // Instructions are aligned, so they never straddle a page.
+        let instr_addr = self.translate(&mut mem, vaddr, Access::Exec)?;
+        let bytes = mem.get_ram().get_slice(instr_addr..instr_addr + INSTRUCTION_LEN)?;
+        Ok(Instruction::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Fetches the decoded instruction at virtual address `vaddr`. Its page is translated as
+    /// by `get_instruction_at`, with the same faults and page references, but the instruction
+    /// is not read back from RAM: code pages are never written to.
+    pub fn get_op_at(&self, vaddr: usize) -> AccessResult<Op> {
+        if !is_instr_aligned(vaddr) {
+            return Err(());
+        }
+        let mut mem = self.mem.lock().unwrap();
+        self.translate(&mut mem, vaddr, Access::Exec)?;
+        self.ops.get(vaddr / INSTRUCTION_LEN).cloned().ok_or(())
+    }
+
+    /// Returns the RAM address that `vaddr` translates to, or `None` if its page is not in a
+    /// frame. Only the page table is read: no page is brought in, and the entry is not marked
+    /// accessed.
+    pub fn find_ram_addr(&self, vaddr: usize) -> Option<usize> {
+        let mem = self.mem.lock().unwrap();
+        self.find_ram_addr_in(&mem, vaddr)
+    }
+
+    fn find_ram_addr_in(&self, mem: &Memory, vaddr: usize) -> Option<usize> {
+        let page_tbl_ptr = get_field_in(mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize;
+        let num_pages = get_field_in(mem, self.addr, NUM_PAGES_OFFSET) as usize;
+        let page_num = vaddr / PAGE_LEN;
+        if page_num >= num_pages {
+            return None;
+        }
+        let pte = mem.get_ram().get_u16_at(page_tbl_ptr + page_num * WORD_LEN).ok()?;
+        if pte & PTE_PRESENT == 0 {
+            return None;
+        }
+        let frame_addr = mem.get_frames().get_frame_addr((pte & PTE_FRAME_MASK) as usize);
+        Some(frame_addr + vaddr % PAGE_LEN)
+    }
+
+    /// Returns a copy of the bytes at `range`, as the process would read them, without side
+    /// effects on paging: a page that is not in a frame is read from swap, or else from the
+    /// program's image as a page fault would load it.
+    pub fn peek(&self, range: Range<usize>) -> Vec<u8> {
+        let mut mem = self.mem.lock().unwrap();
+        self.peek_in(&mut mem, range)
+    }
+
+    fn peek_in(&self, mem: &mut Memory, range: Range<usize>) -> Vec<u8> {
+        let proc_id = get_field_in(mem, self.addr, PID_OFFSET);
+        let mut swapped_pages = HashMap::new();
+        range
+            .map(|vaddr| {
+                if let Some(addr) = self.find_ram_addr_in(mem, vaddr) {
+                    return mem.get_ram().get_byte_at(addr).unwrap();
+                }
+                let page_num = vaddr / PAGE_LEN;
+                swapped_pages.entry(page_num)
+                    .or_insert_with(|| mem.read_swapped((proc_id, page_num)))
+                    .as_ref()
+                    .map_or(self.image.get(vaddr).cloned().unwrap_or(0),
+                            |page| page[vaddr % PAGE_LEN])
+            })
+            .collect()
+    }
+
+    pub fn get_byte(&self, vaddr: usize) -> AccessResult<u8> {
+        self.read_byte(vaddr)
+    }
+
+    pub fn set_byte(&mut self, vaddr: usize, val: u8) -> AccessResult<()> {
+        self.write_byte(vaddr, val)
+    }
+
+    pub fn get_u16(&self, vaddr: usize) -> AccessResult<u16> {
+        use byte_utils;
+        // Read byte by byte, as the word may straddle a page.
+        let high_byte = self.read_byte(vaddr)?;
+        let low_byte = self.read_byte(vaddr + 1)?;
+        Ok(byte_utils::u16_from_bytes([high_byte, low_byte]))
+    }
+
+    pub fn set_u16(&mut self, vaddr: usize, val: u16) -> AccessResult<()> {
+        use byte_utils;
+        let bytes = byte_utils::u16_to_bytes(val);
+        self.write_byte(vaddr, bytes[0])?;
+        self.write_byte(vaddr + 1, bytes[1])
+    }
+
+    /// Returns a copy of the stack, read like `peek`.
+    pub fn get_stack(&self) -> Vec<u8> {
+        let mut mem = self.mem.lock().unwrap();
+        let stack_ptr = get_field_in(&mem, self.addr, STACK_PTR_OFFSET) as usize;
+        let heap_ptr = get_field_in(&mem, self.addr, HEAP_PTR_OFFSET) as usize;
+        self.peek_in(&mut mem, stack_ptr..heap_ptr)
+    }
+
+    /// Returns every entry of the page table.
+    pub fn get_ptes(&self) -> Vec<u16> {
+        let mem = self.mem.lock().unwrap();
+        self.get_ptes_in(&mem)
+    }
+
+    fn get_ptes_in(&self, mem: &Memory) -> Vec<u16> {
+        let page_tbl_ptr = get_field_in(mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize;
+        let num_pages = get_field_in(mem, self.addr, NUM_PAGES_OFFSET) as usize;
+        (0..num_pages)
+            .map(|page_num| mem.get_ram().get_u16_at(page_tbl_ptr + page_num * WORD_LEN).unwrap())
+            .collect()
+    }
+
+    fn read_byte(&self, vaddr: usize) -> AccessResult<u8> {
+        let mut mem = self.mem.lock().unwrap();
+        let addr = self.translate(&mut mem, vaddr, Access::Read)?;
+        mem.get_ram().get_byte_at(addr)
     }
 
-    pub fn get_stack(&self) -> &[u8] {
-        &self.stack.bytes
+    fn write_byte(&mut self, vaddr: usize, val: u8) -> AccessResult<()> {
+        let mut mem = self.mem.lock().unwrap();
+        let addr = self.translate(&mut mem, vaddr, Access::Write)?;
+        mem.get_ram_mut().set_byte_at(addr, val)
     }
 
-    pub fn get_stack_mut(&mut self) -> &mut [u8] {
-        &mut self.stack.bytes
+    /// Translates `vaddr` to a RAM address, handling a page fault if its page is not in a
+    /// frame yet.
+    fn translate(&self, mem: &mut Memory, vaddr: usize, access: Access) -> AccessResult<usize> {
+        let page_tbl_ptr = get_field_in(mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize;
+        let num_pages = get_field_in(mem, self.addr, NUM_PAGES_OFFSET) as usize;
+        let result = match mem.translate(page_tbl_ptr, num_pages, vaddr, access) {
+            Err(Fault::PageNotPresent(page_num)) => {
+                self.handle_page_fault(mem, page_num)?;
+                mem.translate(page_tbl_ptr, num_pages, vaddr, access)
+            }
+            result => result,
+        };
+        result.map_err(|fault| {
+            warn!("Proc {}: {:?} on {:?} of 0x{:04x}",
+                  get_field_in(mem, self.addr, PID_OFFSET),
+                  fault,
+                  access,
+                  vaddr);
+        })
     }
 
-    pub fn get_instr_blk(&self) -> &InstructionBlock {
-        &self.instr
+    /// Brings `page_num` into a frame: pages of the data block are backed by the program's
+    /// instructions, and stack pages start zeroed.
+    fn handle_page_fault(&self, mem: &mut Memory, page_num: usize) -> AccessResult<()> {
+        let proc_id = get_field_in(mem, self.addr, PID_OFFSET);
+        let owner = FrameOwner {
+            proc_id,
+            page_num,
+            page_tbl_ptr: get_field_in(mem, self.addr, PAGE_TBL_PTR_OFFSET) as usize,
+        };
+        let frame_num = mem.load_page(owner, &self.image)
+            .map_err(|_| warn!("Proc {}: could not load page {}", proc_id, page_num))?;
+        debug!("Proc {}: page fault on page {}, mapped to frame {}",
+               proc_id,
+               page_num,
+               frame_num);
+        Ok(())
+    }
+
+    pub fn get_addr(&self) -> usize {
+        self.addr
+    }
+
+    pub fn get_exe_file_name(&self) -> &str {
+        &self.exe_file_name
+    }
+
+    /// Returns the program's instructions.
+    pub fn get_code(&self) -> &[u8] {
+        &self.image[..self.get_data_len() as usize]
+    }
+
+    /// Returns the program the process runs, without its initialized data, which is in the
+    /// data segment.
+    pub fn get_exe(&self) -> Executable {
+        Executable { code: self.get_code().to_vec(), ..self.exe_info.clone() }
+    }
+
+    pub fn get_header_len(&self) -> usize {
+        get_header_len(self.num_registers)
+    }
+
+    pub fn get_page_tbl_ptr(&self) -> u16 {
+        self.get_field(PAGE_TBL_PTR_OFFSET)
+    }
+
+    pub fn get_num_pages(&self) -> u16 {
+        self.get_field(NUM_PAGES_OFFSET)
+    }
+
+    /// Returns the number of pages holding the program's instructions.
+    pub fn get_num_data_pages(&self) -> usize {
+        (self.get_stack_ptr() - self.get_data_ptr()) as usize / PAGE_LEN
     }
 
     pub fn get_id(&self) -> u16 {
-        self.header.id
+        self.get_field(PID_OFFSET)
     }
 
     pub fn set_id(&mut self, proc_id: u16) {
-        self.header.id = proc_id;
+        self.set_field(PID_OFFSET, proc_id);
     }
 
-    pub fn get_status(&self) -> &Status {
-        &self.header.status
+    pub fn get_status(&self) -> Status {
+        use enum_primitive::FromPrimitive;
+
+        if self.is_released {
+            return Status::Killed;
+        }
+        let status = self.get_field(STATUS_OFFSET);
+        match Status::from_u16(status) {
+            Some(status) => status,
+            None => panic!("Could not create Status from {:?}", status),
+        }
     }
 
     pub fn set_status(&mut self, proc_status: Status) {
-        self.header.status = proc_status;
+        if !self.is_released {
+            self.set_field(STATUS_OFFSET, proc_status as u16);
+        }
+    }
+
+    pub fn get_data_ptr(&self) -> u16 {
+        self.get_field(DATA_PTR_OFFSET)
+    }
+
+    pub fn get_stack_ptr(&self) -> u16 {
+        self.get_field(STACK_PTR_OFFSET)
+    }
+
+    /// Returns the length of the program's instructions in bytes.
+    pub fn get_data_len(&self) -> u16 {
+        self.get_field(DATA_LEN_OFFSET)
+    }
+
+    pub fn get_heap_ptr(&self) -> u16 {
+        self.get_field(HEAP_PTR_OFFSET)
+    }
+
+    pub fn get_heap_len(&self) -> u16 {
+        self.get_field(HEAP_LEN_OFFSET)
+    }
+
+    /// Returns the number of pages of the stack, including the page it shares with the heap
+    /// if the stack does not end on a page boundary.
+    pub fn get_num_stack_pages(&self) -> usize {
+        let stack_ptr = self.get_stack_ptr() as usize;
+        (mmu::round_up(self.get_heap_ptr() as usize, PAGE_LEN) - stack_ptr) / PAGE_LEN
+    }
+
+    pub fn get_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn set_cycles(&mut self, cycles: u64) {
+        self.cycles = cycles;
+    }
+
+    pub fn get_load_seq(&self) -> u64 {
+        self.load_seq
+    }
+
+    pub fn set_load_seq(&mut self, load_seq: u64) {
+        self.load_seq = load_seq;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.is_frozen
+    }
+
+    /// Keeps the process from running until it is thawed, without holding its lock.
+    pub fn set_frozen(&mut self, is_frozen: bool) {
+        self.is_frozen = is_frozen;
+    }
+
+    pub fn get_cache_stats(&self) -> CacheStats {
+        self.cache_stats
+    }
+
+    pub fn set_cache_stats(&mut self, cache_stats: CacheStats) {
+        self.cache_stats = cache_stats;
     }
 
     pub fn get_instr_ptr(&self) -> u16 {
-        self.header.ctx.instr_ptr
+        self.get_field(CTX_OFFSET)
     }
 
     pub fn set_instr_ptr(&mut self, instr_ptr: u16) {
-        self.header.ctx.instr_ptr = instr_ptr;
+        self.set_field(CTX_OFFSET, instr_ptr);
     }
-}
 
-impl Stack {
-    fn new() -> Self {
-        Stack { bytes: [0; STACK_LEN] }
+    fn get_field(&self, offset: usize) -> u16 {
+        let mem = self.mem.lock().unwrap();
+        get_field_in(&mem, self.addr, offset)
     }
-}
 
-impl Context {
-    fn new() -> Self {
-        use os::consts::NUM_REGISTERS;
-        Cpu {
-            instr_ptr: 0,
-            registers: [0; NUM_REGISTERS],
-        }
+    fn set_field(&mut self, offset: usize, val: u16) {
+        let mut mem = self.mem.lock().unwrap();
+        mem.get_ram_mut().set_u16_at(self.addr + offset, val).unwrap();
     }
 }
 
-impl fmt::Debug for Stack {
+/// Returns the length of a PCB whose context holds `num_registers` registers.
+pub fn get_header_len(num_registers: usize) -> usize {
+    CTX_OFFSET + WORD_LEN + num_registers * WORD_LEN
+}
+
+/// Reads the field at `offset` of the PCB at `addr` from already locked memory.
+fn get_field_in(mem: &Memory, addr: usize, offset: usize) -> u16 {
+    mem.get_ram().get_u16_at(addr + offset).unwrap()
+}
+
+fn is_instr_aligned(addr: usize) -> bool {
+    use byte_utils;
+    byte_utils::is_aligned(addr, INSTRUCTION_LEN)
+}
+
+impl fmt::Debug for Pcb {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Stack {:?}", &self.bytes[..])
+        write!(f,
+               "Pcb: {{ addr: 0x{:04x}, id: {}, exe_file_name: {:?}, status: {:?}, data_ptr: \
+                0x{:04x}, stack_ptr: 0x{:04x}, heap_ptr: 0x{:04x}, heap_len: {}, ctx: {:?}, \
+                page_tbl_ptr: 0x{:04x}, page_tbl: {:?}, stack: {:?} }}",
+               self.addr,
+               self.get_id(),
+               self.exe_file_name,
+               self.get_status(),
+               self.get_data_ptr(),
+               self.get_stack_ptr(),
+               self.get_heap_ptr(),
+               self.get_heap_len(),
+               self.get_ctx(),
+               self.get_page_tbl_ptr(),
+               self.get_ptes(),
+               self.get_stack())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use os::consts::PAGE_LEN;
+    use super::Pcb;
+    use super::super::super::alloc::AllocatorKind;
+    use super::super::super::instr::Executable;
+    use super::super::super::mmu::{Memory, ReplacerKind, Swap};
+
+    /// Creates a process whose program is a single `Exit`, with a 64-byte stack: its code
+    /// takes page 0 and its stack page 1, and its heap starts at 0x80, a page boundary.
+    fn boot() -> (Arc<Mutex<Memory>>, Pcb) {
+        let mem = Memory::new(0x1000,
+                              0x10,
+                              AllocatorKind::FirstFit,
+                              ReplacerKind::Clock,
+                              Swap::create(&env::temp_dir()).unwrap())
+            .unwrap();
+        let mem = Arc::new(Mutex::new(mem));
+        let exe = Executable::parse(&[0xff, 0x00, 0x00, 0x00]).unwrap();
+        let pcb = Pcb::new(mem.clone(), 0, "exit".to_string(), &exe, 6, PAGE_LEN).unwrap();
+        (mem, pcb)
+    }
+
+    #[test]
+    fn sbrk_adds_and_removes_pages() {
+        let (mem, mut pcb) = boot();
+        let num_free_frames = mem.lock().unwrap().get_frames().get_num_free();
+        assert_eq!(pcb.get_ptes().len(), 2);
+        assert_eq!(pcb.sbrk(1), Ok(0x40));
+        assert_eq!(pcb.get_ptes().len(), 3);
+        assert_eq!(pcb.sbrk(0x3f), Ok(0x41));
+        assert_eq!(pcb.get_ptes().len(), 3);
+        assert_eq!(pcb.sbrk(1), Ok(0x80));
+        assert_eq!(pcb.get_ptes().len(), 4);
+        assert_eq!(pcb.get_data_seg().limit, 0x81);
+        // Touch the last heap page, which takes a frame.
+        pcb.set_byte(0xc0, 0x41).unwrap();
+        assert_eq!(mem.lock().unwrap().get_frames().get_num_free(), num_free_frames - 1);
+
+        assert_eq!(pcb.sbrk(-0x41), Ok(0x81));
+        assert_eq!(pcb.get_ptes().len(), 2);
+        assert_eq!(pcb.get_data_seg().limit, 0x40);
+        assert_eq!(mem.lock().unwrap().get_frames().get_num_free(), num_free_frames);
+        assert!(pcb.set_byte(0xc0, 0x41).is_err());
+        assert!(pcb.sbrk(-1).is_err());
+    }
+
+    #[test]
+    fn sbrk_stops_at_the_largest_heap() {
+        use os::consts::MAX_HEAP_LEN;
+
+        let (_, mut pcb) = boot();
+        assert!(pcb.sbrk(MAX_HEAP_LEN as i16 + 1).is_err());
+        assert_eq!(pcb.sbrk(MAX_HEAP_LEN as i16), Ok(0x40));
+        assert!(pcb.sbrk(1).is_err());
     }
 }
@@ -1,59 +1,200 @@
-use std::collections::{self, HashMap, LinkedList};
+use std::collections::{self, HashMap, LinkedList, VecDeque};
 use std::sync::{Arc, Mutex};
-use os::consts::MAX_PROCS;
+use os::consts::WORD_LEN;
 use super::pcb::Pcb;
-use super::super::instr::InstructionBlock;
+use super::super::cache::CacheStats;
+use super::super::alloc::AllocStats;
+use super::super::config::Config;
+use super::super::instr::Executable;
+use super::super::mmu::{Memory, NEXT_BLK_ADDR, NULL_ADDR};
+
+// RAM layout (see design.md).
+pub const PS_TBL_ADDR: usize = NEXT_BLK_ADDR + WORD_LEN;
+const PS_TBL_ENTRY_LEN: usize = 2 * WORD_LEN;
+
+/// How many of the processes that exited last the table remembers.
+pub const MAX_EXITED_PROCS: usize = 0x40;
 
 pub type PcbIter<'a> = collections::hash_map::Values<'a, u16, Arc<Mutex<Pcb>>>;
-pub type PcbIterMut<'a> = collections::hash_map::ValuesMut<'a, u16, Arc<Mutex<Pcb>>>;
 
+/// The cycles a process took and its cache lookups, before it exited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExitedProc {
+    pub id: u16,
+    pub exe_file_name: String,
+    pub cycles: u64,
+    pub cache_stats: CacheStats,
+}
+
+/// The process table lives in RAM as `max_procs` (PID, PCB_ADDR) entries; an entry whose
+/// PCB_ADDR is `NULL_ADDR` is unused.
+/// Handles to the PCBs are cached so that executors can lock them individually.
 #[derive(Debug)]
 pub struct ProcessTable {
+    mem: Arc<Mutex<Memory>>,
+    max_procs: usize,
+    num_registers: usize,
+    stack_len: usize,
     next_ids: LinkedList<u16>,
-    // TODO: could easily use a fixed-sized array of blocks
     procs: HashMap<u16, Arc<Mutex<Pcb>>>,
+    /// The last `MAX_EXITED_PROCS` processes that exited, oldest first.
+    exited: VecDeque<ExitedProc>,
+    /// How many PCBs were allocated since boot.
+    num_loads: u64,
 }
 
 impl ProcessTable {
-    pub fn new() -> Self {
+    pub fn new(mem: Arc<Mutex<Memory>>, config: &Config) -> Self {
         use std::iter::FromIterator;
+        let max_procs = config.max_procs;
+        mem.lock()
+            .unwrap()
+            .get_ram_mut()
+            .zero(PS_TBL_ADDR..get_blk_region_addr(max_procs))
+            .unwrap();
         ProcessTable {
-            next_ids: LinkedList::from_iter((0..MAX_PROCS).map(|idx| idx as u16)),
-            procs: HashMap::with_capacity(MAX_PROCS),
+            mem,
+            max_procs,
+            num_registers: config.num_registers,
+            stack_len: config.stack_len,
+            next_ids: LinkedList::from_iter((0..max_procs).map(|idx| idx as u16)),
+            procs: HashMap::with_capacity(max_procs),
+            exited: VecDeque::with_capacity(MAX_EXITED_PROCS),
+            num_loads: 0,
         }
     }
 
+    pub fn get_alloc_stats(&self) -> AllocStats {
+        self.mem.lock().unwrap().get_allocator().get_stats()
+    }
+
+    /// Returns the RAM address, PID and PCB_ADDR of every entry, used or not.
+    pub fn get_entries(&self) -> Vec<(usize, u16, u16)> {
+        let mem = self.mem.lock().unwrap();
+        let ram = mem.get_ram();
+        (0..self.max_procs)
+            .map(|idx| {
+                let entry_addr = PS_TBL_ADDR + idx * PS_TBL_ENTRY_LEN;
+                let pid = ram.get_u16_at(entry_addr).unwrap();
+                let pcb_addr = ram.get_u16_at(entry_addr + WORD_LEN).unwrap();
+                (entry_addr, pid, pcb_addr)
+            })
+            .collect()
+    }
+
     pub fn contains(&self, proc_id: u16) -> bool {
-        self.procs.contains_key(&proc_id)
+        self.find_entry(|pid, pcb_addr| pid == proc_id && pcb_addr != NULL_ADDR).is_some()
     }
 
     pub fn get_pcb(&self, proc_id: u16) -> Arc<Mutex<Pcb>> {
         self.procs[&proc_id].clone()
     }
 
-    pub fn get_running_procs(&self) -> PcbIter {
+    pub fn get_running_procs(&self) -> PcbIter<'_> {
         self.procs.values()
     }
 
-    pub fn get_running_procs_mut(&mut self) -> PcbIterMut {
-        self.procs.values_mut()
+    pub fn get_exited_procs(&self) -> &VecDeque<ExitedProc> {
+        &self.exited
     }
 
     /// Returns the Process ID of the allocated PCB.
-    /// Returns `None` if there were no more available slots in the table.
-    pub fn alloc_pcb(&mut self, exe_file_name: String, instr: InstructionBlock) -> Option<u16> {
-        self.next_ids.pop_front().map(|proc_id| {
-            let pcb = Pcb::new(proc_id, exe_file_name, instr);
-            let pcb = Arc::new(Mutex::new(pcb));
-            self.procs.insert(proc_id, pcb);
-            proc_id
-        })
+    /// Returns an error if there were no more available slots in the table or if no free block
+    /// of RAM is large enough for the PCB or its page table.
+    pub fn alloc_pcb(&mut self,
+                     exe_file_name: String,
+                     exe: &Executable)
+                     -> Result<u16, String> {
+        let proc_id = *self.next_ids
+            .front()
+            .ok_or("Could not allocate another process.".to_string())?;
+        self.alloc_pcb_as(proc_id, exe_file_name, exe)
+    }
+
+    /// Like `alloc_pcb`, but with the Process ID `proc_id`, which must be free.
+    pub fn alloc_pcb_as(&mut self,
+                        proc_id: u16,
+                        exe_file_name: String,
+                        exe: &Executable)
+                        -> Result<u16, String> {
+        let entry_addr = self.find_entry(|_, pcb_addr| pcb_addr == NULL_ADDR)
+            .ok_or("Could not allocate another process.".to_string())?;
+        if !self.next_ids.contains(&proc_id) {
+            return Err(format!("Process ID {} is not free.", proc_id));
+        }
+        let mut pcb = Pcb::new(self.mem.clone(),
+                               proc_id,
+                               exe_file_name,
+                               exe,
+                               self.num_registers,
+                               self.stack_len)?;
+        pcb.set_load_seq(self.num_loads);
+        self.num_loads += 1;
+        self.next_ids = self.next_ids.iter().cloned().filter(|id| *id != proc_id).collect();
+        {
+            let mut mem = self.mem.lock().unwrap();
+            let ram = mem.get_ram_mut();
+            ram.set_u16_at(entry_addr, proc_id).unwrap();
+            ram.set_u16_at(entry_addr + WORD_LEN, pcb.get_addr() as u16).unwrap();
+        }
+        debug!("Proc {}: allocated PCB at 0x{:04x}; {}",
+               proc_id,
+               pcb.get_addr(),
+               self.get_alloc_stats());
+        self.procs.insert(proc_id, Arc::new(Mutex::new(pcb)));
+        Ok(proc_id)
     }
 
     pub fn dealloc_pcb(&mut self, proc_id: u16) {
         match self.procs.remove(&proc_id) {
-            Some(_) => self.next_ids.push_front(proc_id),
+            Some(pcb) => {
+                let mut pcb = pcb.lock().unwrap();
+                pcb.release();
+                if let Some(entry_addr) =
+                       self.find_entry(|pid, pcb_addr| pid == proc_id && pcb_addr != NULL_ADDR) {
+                    let mut mem = self.mem.lock().unwrap();
+                    let ram = mem.get_ram_mut();
+                    ram.set_u16_at(entry_addr, 0).unwrap();
+                    ram.set_u16_at(entry_addr + WORD_LEN, NULL_ADDR).unwrap();
+                }
+                self.next_ids.push_front(proc_id);
+                self.push_exited_proc(ExitedProc {
+                    id: proc_id,
+                    exe_file_name: pcb.get_exe_file_name().to_string(),
+                    cycles: pcb.get_cycles(),
+                    cache_stats: pcb.get_cache_stats(),
+                });
+                debug!("Proc {}: freed PCB at 0x{:04x}; {}",
+                       proc_id,
+                       pcb.get_addr(),
+                       self.get_alloc_stats());
+            }
             None => (),
         }
     }
+
+    /// Remembers a process that exited, forgetting the oldest one if there are
+    /// `MAX_EXITED_PROCS` already.
+    pub fn push_exited_proc(&mut self, exited_proc: ExitedProc) {
+        if self.exited.len() == MAX_EXITED_PROCS {
+            self.exited.pop_front();
+        }
+        self.exited.push_back(exited_proc);
+    }
+
+    /// Returns the RAM address of the first entry satisfying `pred(pid, pcb_addr)`.
+    fn find_entry<F>(&self, pred: F) -> Option<usize>
+        where F: Fn(u16, u16) -> bool
+    {
+        self.get_entries()
+            .into_iter()
+            .find(|&(_, pid, pcb_addr)| pred(pid, pcb_addr))
+            .map(|(entry_addr, _, _)| entry_addr)
+    }
+}
+
+/// Returns the RAM address of the block region, which follows a process table of `max_procs`
+/// entries.
+pub fn get_blk_region_addr(max_procs: usize) -> usize {
+    PS_TBL_ADDR + max_procs * PS_TBL_ENTRY_LEN
 }
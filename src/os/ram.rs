@@ -0,0 +1,64 @@
+use std::fmt;
+use std::ops::Range;
+use byte_utils::{self, AccessResult};
+
+/// Byte-addressable physical memory.
+/// Every process table entry and PCB lives somewhere in here (see design.md).
+pub struct Ram {
+    bytes: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(len: usize) -> Self {
+        Ram { bytes: vec![0; len] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn get_byte_at(&self, addr: usize) -> AccessResult<u8> {
+        self.bytes.get(addr).copied().ok_or(())
+    }
+
+    pub fn set_byte_at(&mut self, addr: usize, val: u8) -> AccessResult<()> {
+        self.bytes.get_mut(addr).map(|byte| *byte = val).ok_or(())
+    }
+
+    pub fn get_u16_at(&self, addr: usize) -> AccessResult<u16> {
+        byte_utils::get_u16_at(&self.bytes, addr)
+    }
+
+    pub fn set_u16_at(&mut self, addr: usize, val: u16) -> AccessResult<()> {
+        byte_utils::set_u16_at(&mut self.bytes, addr, val)
+    }
+
+    pub fn get_slice(&self, range: Range<usize>) -> AccessResult<&[u8]> {
+        byte_utils::get_slice(&self.bytes, range)
+    }
+
+    pub fn get_slice_mut(&mut self, range: Range<usize>) -> AccessResult<&mut [u8]> {
+        byte_utils::get_slice_mut(&mut self.bytes, range)
+    }
+
+    pub fn zero(&mut self, range: Range<usize>) -> AccessResult<()> {
+        let slice = self.get_slice_mut(range)?;
+        for byte in slice.iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+
+    /// Returns a hex dump of `range`, 16 bytes per line, prefixed by the address of the first
+    /// byte on the line.
+    pub fn dump(&self, range: Range<usize>) -> AccessResult<String> {
+        let start = range.start;
+        Ok(byte_utils::dump_hex(start, self.get_slice(range)?))
+    }
+}
+
+impl fmt::Debug for Ram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ram: {{ len: {} }}", self.bytes.len())
+    }
+}
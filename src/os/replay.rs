@@ -0,0 +1,199 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use byte_utils;
+use super::console::Console;
+use super::exec::ExecResult;
+
+/// The version of the run log format written by this build.
+/// Version 1 had no lengths before the file names, which could not contain spaces.
+pub const RUN_LOG_VERSION: u16 = 2;
+
+const RUN_LOG_MAGIC: &str = "snoss-run-log";
+
+/// Everything that made a recorded run what it was: the programs, the bytes they read and how
+/// the time slices fell, with the state of the process after each slice to compare against.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunLog {
+    pub programs: Vec<RecordedProgram>,
+    pub slices: Vec<RecordedSlice>,
+    /// Every byte the programs read, in order.
+    pub input: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedProgram {
+    pub file_name: String,
+    /// The FNV-1a hash of the file, to check that the replay runs the same program.
+    pub hash: u64,
+}
+
+/// A time slice given to a process.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedSlice {
+    /// The index of the process's program in `RunLog::programs`.
+    pub proc_idx: usize,
+    pub num_instrs: usize,
+    /// The state of the process at the end of the slice.
+    pub instr_ptr: u16,
+    pub registers: Vec<u16>,
+    /// `running`, or how the process ended.
+    pub result: String,
+}
+
+impl RunLog {
+    /// Parses a run log written by `to_string`. Returns an error with the line number if it is
+    /// malformed.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines().enumerate();
+        let header = lines.next().map_or(vec![], |(_, header)| header.split(' ').collect());
+        let version = match header.split_first() {
+            Some((&RUN_LOG_MAGIC, &[version])) => {
+                match version.parse::<u16>() {
+                    Ok(version) if (1..=RUN_LOG_VERSION).contains(&version) => version,
+                    _ => return Err(format!("{}: unsupported run log version", version)),
+                }
+            }
+            _ => return Err("not a run log".to_string()),
+        };
+        let mut log = RunLog::default();
+        for (idx, line) in lines {
+            log.parse_line(line, version).map_err(|err| format!("line {}: {}", idx + 1, err))?;
+        }
+        Ok(log)
+    }
+
+    fn parse_line(&mut self, line: &str, version: u16) -> Result<(), String> {
+        if version >= 2 && line.starts_with("program ") {
+            return self.parse_program(&line["program ".len()..]);
+        }
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        match tokens.split_first() {
+            Some((&"program", &[hash, file_name])) => {
+                self.programs.push(RecordedProgram {
+                    file_name: file_name.to_string(),
+                    hash: u64::from_str_radix(hash, 16).map_err(|_| "bad hash")?,
+                });
+            }
+            Some((&"slice", &[proc_idx, num_instrs, instr_ptr, result, registers])) => {
+                let proc_idx = proc_idx.parse::<usize>().map_err(|_| "bad process")?;
+                if proc_idx >= self.programs.len() {
+                    return Err(format!("no program {}", proc_idx));
+                }
+                let registers = registers.split(',')
+                    .map(|reg| u16::from_str_radix(reg, 16))
+                    .collect::<Result<Vec<u16>, _>>()
+                    .map_err(|_| "bad registers")?;
+                self.slices.push(RecordedSlice {
+                    proc_idx,
+                    num_instrs: num_instrs.parse().map_err(|_| "bad instruction count")?,
+                    instr_ptr: u16::from_str_radix(instr_ptr.trim_start_matches("0x"), 16)
+                        .map_err(|_| "bad instruction pointer")?,
+                    registers,
+                    result: result.to_string(),
+                });
+            }
+            Some((&"input", &[])) => (),
+            Some((&"input", &[hex])) => {
+                self.input = byte_utils::decode_hex(hex)
+                    .map_err(|err| format!("bad input: {}", err))?;
+            }
+            None => (),
+            Some(_) => return Err(format!("{}: unexpected entry", line)),
+        }
+        Ok(())
+    }
+
+    /// Parses `HASH LEN NAME`, where `NAME` is the rest of the line, `LEN` bytes long.
+    fn parse_program(&mut self, fields: &str) -> Result<(), String> {
+        let mut tokens = fields.splitn(3, ' ');
+        let hash = tokens.next().and_then(|hash| u64::from_str_radix(hash, 16).ok());
+        let len = tokens.next().and_then(|len| len.parse::<usize>().ok());
+        match (hash, len, tokens.next()) {
+            (Some(hash), Some(len), Some(file_name)) if file_name.len() == len => {
+                self.programs.push(RecordedProgram {
+                    file_name: file_name.to_string(),
+                    hash,
+                });
+                Ok(())
+            }
+            _ => Err(format!("program {}: expected `program HASH LEN NAME`", fields)),
+        }
+    }
+}
+
+impl fmt::Display for RunLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", RUN_LOG_MAGIC, RUN_LOG_VERSION)?;
+        for program in &self.programs {
+            writeln!(f,
+                     "program {:016x} {} {}",
+                     program.hash,
+                     program.file_name.len(),
+                     program.file_name)?;
+        }
+        for slice in &self.slices {
+            writeln!(f, "slice {}", slice)?;
+        }
+        writeln!(f, "input {}", byte_utils::encode_hex(&self.input))
+    }
+}
+
+impl fmt::Display for RecordedSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registers = self.registers
+            .iter()
+            .map(|reg| format!("{:04x}", reg))
+            .collect::<Vec<String>>();
+        write!(f,
+               "{} {} 0x{:04x} {} {}",
+               self.proc_idx,
+               self.num_instrs,
+               self.instr_ptr,
+               self.result,
+               registers.join(","))
+    }
+}
+
+/// Returns the name of `result` in run logs.
+pub fn get_result_name(result: &ExecResult) -> &'static str {
+    match *result {
+        ExecResult::Success => "running",
+        ExecResult::Exit => "exit",
+        ExecResult::AccessErr => "access-error",
+        ExecResult::SegFault(_) => "segfault",
+    }
+}
+
+/// The FNV-1a hash of `bytes`.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The input of a console, keeping a copy of every byte read.
+pub struct RecordingInput {
+    console: Console,
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl RecordingInput {
+    pub fn new(console: Console, bytes: Arc<Mutex<Vec<u8>>>) -> Self {
+        RecordingInput {
+            console,
+            bytes,
+        }
+    }
+}
+
+impl Read for RecordingInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.console.read_byte();
+        self.bytes.lock().unwrap().push(buf[0]);
+        Ok(1)
+    }
+}
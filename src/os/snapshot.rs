@@ -0,0 +1,274 @@
+use byte_utils;
+use super::cache::CacheStats;
+use super::instr::Executable;
+use super::ps::{ExitedProc, Status};
+
+/// The bytes every snapshot starts with.
+pub const SNAPSHOT_MAGIC: [u8; 4] = [0x7f, b'S', b'N', b'S'];
+/// The version of the snapshot format written by this build.
+/// Version 1 had no cycle counts; its processes are restored with 0 cycles. Versions 1 and 2
+/// had the code of the processes instead of their executables, and no consoles or exited
+/// processes.
+pub const SNAPSHOT_VERSION: u16 = 3;
+
+/// The state of every process of a `System`, from which they can be recreated.
+/// Snapshots are big-endian: `MAGIC VERSION NUM_REGISTERS STACK_LEN NUM_PROCS`, followed by the
+/// processes (see `ProcSnapshot`), then `NUM_EXITED` and the processes that exited last, each
+/// as `PID CYCLES NAME_LEN NAME HITS MISSES`, where the counts are 8 bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The machine parameters the processes were laid out for.
+    pub num_registers: usize,
+    pub stack_len: usize,
+    pub procs: Vec<ProcSnapshot>,
+    /// The processes that exited last, oldest first.
+    pub exited: Vec<ExitedProc>,
+}
+
+/// The state of a process: its context, the contents of its segments and its console.
+/// Written as `PID STATUS CYCLES NAME_LEN NAME IP NUM_REGISTERS REGISTERS.. EXE_LEN EXE
+/// HEAP_LEN DATA_LEN DATA HAS_CONSOLE`, followed by `INPUT_LEN INPUT` if `HAS_CONSOLE` is 1,
+/// where `CYCLES` is 8 bytes, the lengths of the executable and of the input 4, and `EXE` is
+/// an executable file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcSnapshot {
+    pub id: u16,
+    pub status: Status,
+    pub cycles: u64,
+    pub exe_file_name: String,
+    pub instr_ptr: u16,
+    pub registers: Vec<u16>,
+    /// The program, without its initialized data.
+    pub exe: Executable,
+    /// The whole data segment: initialized data, stack and heap.
+    pub data_seg: Vec<u8>,
+    pub heap_len: u16,
+    /// The buffered input of the process's own console that it has not read yet, or `None` if
+    /// it uses the system's console.
+    pub console_input: Option<Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Parses a snapshot written by `to_bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(&SNAPSHOT_MAGIC) {
+            return Err("Not a snapshot.".to_string());
+        }
+        let mut reader = Reader::new(&bytes[SNAPSHOT_MAGIC.len()..]);
+        let version = reader.read_u16()?;
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(format!("Unsupported snapshot version {} (at most {}).",
+                               version,
+                               SNAPSHOT_VERSION));
+        }
+        let num_registers = reader.read_u16()? as usize;
+        let stack_len = reader.read_u16()? as usize;
+        let num_procs = reader.read_u16()?;
+        let procs = (0..num_procs)
+            .map(|_| ProcSnapshot::read(&mut reader, version))
+            .collect::<Result<Vec<ProcSnapshot>, String>>()?;
+        let num_exited = if version >= 3 { reader.read_u16()? } else { 0 };
+        let exited = (0..num_exited)
+            .map(|_| read_exited_proc(&mut reader))
+            .collect::<Result<Vec<ExitedProc>, String>>()?;
+        reader.finish()?;
+        Ok(Snapshot {
+            num_registers,
+            stack_len,
+            procs,
+            exited,
+        })
+    }
+
+    /// Returns an error if an executable cannot be written (see `Executable::to_bytes`).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        push_u16(&mut bytes, SNAPSHOT_VERSION);
+        push_u16(&mut bytes, self.num_registers as u16);
+        push_u16(&mut bytes, self.stack_len as u16);
+        push_u16(&mut bytes, self.procs.len() as u16);
+        for proc_snapshot in &self.procs {
+            proc_snapshot.write(&mut bytes)?;
+        }
+        push_u16(&mut bytes, self.exited.len() as u16);
+        for exited_proc in &self.exited {
+            write_exited_proc(&mut bytes, exited_proc);
+        }
+        Ok(bytes)
+    }
+}
+
+impl ProcSnapshot {
+    /// Parses a process written by `to_bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(bytes);
+        let proc_snapshot = ProcSnapshot::read(&mut reader, SNAPSHOT_VERSION)?;
+        reader.finish()?;
+        Ok(proc_snapshot)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![];
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a process written with the snapshot format `version`.
+    fn read(reader: &mut Reader, version: u16) -> Result<Self, String> {
+        use enum_primitive::FromPrimitive;
+
+        let id = reader.read_u16()?;
+        let status = Status::from_u16(reader.read_u16()?)
+            .ok_or(format!("Process {} has an unknown status.", id))?;
+        let cycles = if version >= 2 { reader.read_u64()? } else { 0 };
+        let name_len = reader.read_u16()? as usize;
+        let exe_file_name = String::from_utf8(reader.read_bytes(name_len)?.to_vec())
+            .map_err(|_| format!("The name of process {} is not UTF-8.", id))?;
+        let instr_ptr = reader.read_u16()?;
+        let num_registers = reader.read_u16()?;
+        let registers = (0..num_registers)
+            .map(|_| reader.read_u16())
+            .collect::<Result<Vec<u16>, String>>()?;
+        let exe = if version >= 3 {
+            let exe_len = reader.read_u32()? as usize;
+            Executable::parse(reader.read_bytes(exe_len)?)
+                .map_err(|err| format!("The program of process {}: {}", id, err))?
+        } else {
+            let code_len = reader.read_u16()? as usize;
+            Executable::new(reader.read_bytes(code_len)?.to_vec())
+        };
+        let heap_len = reader.read_u16()?;
+        let data_len = reader.read_u16()? as usize;
+        let data_seg = reader.read_bytes(data_len)?.to_vec();
+        let console_input = if version >= 3 && reader.read_u16()? != 0 {
+            let input_len = reader.read_u32()? as usize;
+            Some(reader.read_bytes(input_len)?.to_vec())
+        } else {
+            None
+        };
+        Ok(ProcSnapshot {
+            id,
+            status,
+            cycles,
+            exe_file_name,
+            instr_ptr,
+            registers,
+            exe,
+            data_seg,
+            heap_len,
+            console_input,
+        })
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) -> Result<(), String> {
+        let exe = self.exe.to_bytes()?;
+        push_u16(bytes, self.id);
+        push_u16(bytes, self.status as u16);
+        push_u64(bytes, self.cycles);
+        push_u16(bytes, self.exe_file_name.len() as u16);
+        bytes.extend_from_slice(self.exe_file_name.as_bytes());
+        push_u16(bytes, self.instr_ptr);
+        push_u16(bytes, self.registers.len() as u16);
+        for reg in &self.registers {
+            push_u16(bytes, *reg);
+        }
+        bytes.extend_from_slice(&byte_utils::u32_to_bytes(exe.len() as u32));
+        bytes.extend_from_slice(&exe);
+        push_u16(bytes, self.heap_len);
+        push_u16(bytes, self.data_seg.len() as u16);
+        bytes.extend_from_slice(&self.data_seg);
+        match self.console_input {
+            Some(ref input) => {
+                push_u16(bytes, 1);
+                bytes.extend_from_slice(&byte_utils::u32_to_bytes(input.len() as u32));
+                bytes.extend_from_slice(input);
+            }
+            None => push_u16(bytes, 0),
+        }
+        Ok(())
+    }
+}
+
+fn read_exited_proc(reader: &mut Reader) -> Result<ExitedProc, String> {
+    let id = reader.read_u16()?;
+    let cycles = reader.read_u64()?;
+    let name_len = reader.read_u16()? as usize;
+    let exe_file_name = String::from_utf8(reader.read_bytes(name_len)?.to_vec())
+        .map_err(|_| format!("The name of exited process {} is not UTF-8.", id))?;
+    let cache_stats = CacheStats {
+        hits: reader.read_u64()?,
+        misses: reader.read_u64()?,
+    };
+    Ok(ExitedProc {
+        id,
+        exe_file_name,
+        cycles,
+        cache_stats,
+    })
+}
+
+fn write_exited_proc(bytes: &mut Vec<u8>, exited_proc: &ExitedProc) {
+    push_u16(bytes, exited_proc.id);
+    push_u64(bytes, exited_proc.cycles);
+    push_u16(bytes, exited_proc.exe_file_name.len() as u16);
+    bytes.extend_from_slice(exited_proc.exe_file_name.as_bytes());
+    push_u64(bytes, exited_proc.cache_stats.hits);
+    push_u64(bytes, exited_proc.cache_stats.misses);
+}
+
+/// Reads the fields of a snapshot in order.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader {
+            bytes,
+            pos: 0,
+        }
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let bytes = self.read_bytes(2)?;
+        Ok(byte_utils::u16_from_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.read_bytes(4)?;
+        Ok(byte_utils::u32_from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        let high = self.read_u32()? as u64;
+        let low = self.read_u32()? as u64;
+        Ok(high << 32 | low)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = byte_utils::get_slice(self.bytes, self.pos..self.pos + len)
+            .map_err(|_| "Truncated snapshot.".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Returns an error if there are bytes left.
+    fn finish(&self) -> Result<(), String> {
+        if self.pos == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(format!("{} unexpected byte(s) at the end of the snapshot.",
+                        self.bytes.len() - self.pos))
+        }
+    }
+}
+
+fn push_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.extend_from_slice(&byte_utils::u16_to_bytes(val));
+}
+
+fn push_u64(bytes: &mut Vec<u8>, val: u64) {
+    bytes.extend_from_slice(&byte_utils::u32_to_bytes((val >> 32) as u32));
+    bytes.extend_from_slice(&byte_utils::u32_to_bytes(val as u32));
+}
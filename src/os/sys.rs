@@ -1,59 +1,112 @@
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::sync::mpsc::{self, Sender, Receiver};
 use std::thread;
+use super::alloc::AllocStats;
+use super::cache::{Cache, CacheStats};
+use super::config::Config;
+use super::console::Console;
 use super::cpu::Cpu;
+use super::debug::Debugger;
 use super::exec::{Executor, ExecResult};
 use super::fs::FileSystem;
-use super::instr::InstructionBlock;
-use super::ps::{Pcb, Header as PcbHeader, ProcessTable, Status as ProcessStatus};
+use super::instr::{self, Executable};
+use super::migrate;
+use super::mmu::{self, Memory, Swap, NEXT_BLK_ADDR, NULL_ADDR, PTE_PRESENT, REPLACER_KINDS};
+use super::ps::{self, ExitedProc, Pcb, ProcInfo, ProcessTable};
+use super::replay::{self, RecordedProgram, RecordedSlice, RecordingInput, RunLog};
+use super::snapshot::{ProcSnapshot, Snapshot};
+use super::timeline::Timeline;
+use super::trace::Tracer;
 
+/// `NUM_REGISTERS`, `RAM_LEN`, `STACK_LEN`, `MAX_PROCS`, `TIME_SLICE_MS` and `FS_PATH` are the
+/// defaults of the machine parameters (see `Config`).
 pub mod consts {
     pub const NUM_REGISTERS: usize = 6;
     pub const RAM_LEN: usize = 10_000;
     pub const WORD_LEN: usize = 2;
     pub const STACK_LEN: usize = 64;
-    pub const MAX_PROCS: usize = 10;
+    pub const MAX_HEAP_LEN: usize = 4096;
+    pub const PAGE_LEN: usize = 64;
+    pub const MAX_PROCS: usize = 16;
     pub const CORE_DUMP_FILE_NAME: &'static str = "coredump";
+    pub const FS_PATH: &str = "./fs";
     pub const TIME_SLICE_MS: i64 = 1;
 }
 
-#[derive(Debug)]
 pub struct System {
     // sched: Scheduler,
+    config: Config,
     cpu: Arc<Mutex<Cpu>>,
+    /// The cache between the CPU and RAM, if `cache_len` is not 0.
+    cache: Option<Arc<Mutex<Cache>>>,
+    mem: Arc<Mutex<Memory>>,
     proc_tbl: Arc<Mutex<ProcessTable>>,
     exit_tx: Sender<u16>,
     fs: FileSystem,
+    /// The console of the processes that were not attached to one of their own.
+    console: Console,
+    consoles: HashMap<u16, Console>,
+    /// Records the instructions that are executed, if set.
+    tracer: Option<Tracer>,
+    /// When the processes had the CPU.
+    timeline: Option<Timeline>,
 }
 
 impl System {
-    pub fn init() -> Self {
-        let cpu = Arc::new(Mutex::new(Cpu::init()));
-        let proc_tbl = ProcessTable::new();
+    /// Boots a machine with the parameters of `config`.
+    /// Returns an error if the parameters are out of range.
+    pub fn init(config: Config) -> Result<Self, String> {
+        config.validate()?;
+        let fs = FileSystem::new(&config.fs_path);
+        let swap = Swap::create(&config.fs_path)
+            .map_err(|err| format!("Cannot create a swap file: {}", err))?;
+        let mem = Memory::new(config.ram_len,
+                              ps::get_blk_region_addr(config.max_procs),
+                              config.alloc_kind,
+                              config.replacer_kind,
+                              swap)?;
+        let cpu = Arc::new(Mutex::new(Cpu::init(config.num_registers)));
+        let cache = if config.cache_len > 0 {
+            Some(Arc::new(Mutex::new(Cache::new(config.cache_len,
+                                                config.cache_line_len,
+                                                config.cache_ways,
+                                                config.cache_policy))))
+        } else {
+            None
+        };
+        let mem = Arc::new(Mutex::new(mem));
+        let proc_tbl = ProcessTable::new(mem.clone(), &config);
         let proc_tbl = Arc::new(Mutex::new(proc_tbl));
 
         // Channel that informs threads of process completion.
         let (exit_tx, exit_rx): (Sender<u16>, Receiver<u16>) = mpsc::channel();
         let mut sys = System {
+            config,
             cpu: cpu,
+            cache,
+            mem,
             proc_tbl: proc_tbl,
             exit_tx: exit_tx,
-            fs: FileSystem::new("./fs"),
+            fs,
+            console: Console::std(),
+            consoles: HashMap::new(),
+            tracer: None,
+            timeline: None,
         };
         sys.listen_for_exit(exit_rx);
-        sys
+        Ok(sys)
     }
 
     /// Spawns a "daemon" that removes processes from the process table.
+    /// It stops once the system and every executor are dropped.
     fn listen_for_exit(&mut self, exit_rx: Receiver<u16>) -> thread::JoinHandle<()> {
         let proc_tbl = self.proc_tbl.clone();
         thread::spawn(move || {
-            loop {
-                let exited_proc_id = exit_rx.recv().unwrap();
+            while let Ok(exited_proc_id) = exit_rx.recv() {
                 let mut proc_tbl = proc_tbl.lock().unwrap();
-                let pcb = proc_tbl.get_pcb(exited_proc_id);
-                let mut pcb = pcb.lock().unwrap();
-                pcb.set_status(ProcessStatus::Killed);
                 proc_tbl.dealloc_pcb(exited_proc_id);
             }
         })
@@ -64,62 +117,970 @@ impl System {
     }
 
     pub fn list_procs(&self) -> String {
-        let header = format!("pid\tstate\tip\t1\t2\t3\t4\t5\t6\texe");
+        let reg_names = (1..self.config.num_registers + 1)
+            .map(|num| num.to_string())
+            .collect::<Vec<String>>();
+        let header = format!("pid\tstate\tip\t{}\tcycles\thits\tmisses\texe",
+                             reg_names.join("\t"));
         let proc_tbl = self.proc_tbl.lock().unwrap();
         let procs = proc_tbl.get_running_procs();
         procs.map(|arc_pcb| {
                 let pcb = arc_pcb.lock().unwrap();
-                let Pcb { header: PcbHeader { id, ref exe_file_name, ref status, ref ctx }, .. } =
-                    *pcb;
+                let ctx = pcb.get_ctx();
                 let ip = ctx.instr_ptr;
-                let reg = ctx.registers;
-                let row = format!("{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}",
-                                  id,
-                                  status,
+                let regs = ctx.registers
+                    .iter()
+                    .map(|reg| format!("{:?}", reg))
+                    .collect::<Vec<String>>();
+                let cache_stats = pcb.get_cache_stats();
+                let row = format!("{:?}\t{:?}\t{:?}\t{}\t{}\t{}\t{}\t{:?}",
+                                  pcb.get_id(),
+                                  pcb.get_status(),
                                   ip,
-                                  reg[0],
-                                  reg[1],
-                                  reg[2],
-                                  reg[3],
-                                  reg[4],
-                                  reg[5],
-                                  exe_file_name);
+                                  regs.join("\t"),
+                                  pcb.get_cycles(),
+                                  cache_stats.hits,
+                                  cache_stats.misses,
+                                  pcb.get_exe_file_name());
                 row
             })
             .fold(header + "\n", |acc, row| acc + &row + "\n")
     }
 
+    /// Lists the cycles taken by the processes in the process table, then by the last ones that
+    /// exited, and by the CPU since boot.
+    pub fn list_cycles(&self) -> String {
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        let mut running = proc_tbl.get_running_procs()
+            .map(|arc_pcb| {
+                let pcb = arc_pcb.lock().unwrap();
+                (pcb.get_id(), pcb.get_cycles(), pcb.get_exe_file_name().to_string())
+            })
+            .collect::<Vec<(u16, u64, String)>>();
+        running.sort();
+        let rows = running.iter()
+            .map(|&(id, cycles, ref exe_file_name)| {
+                format!("{}\trunning\t{}\t{:?}", id, cycles, exe_file_name)
+            })
+            .chain(proc_tbl.get_exited_procs().iter().map(|exited| {
+                format!("{}\texited\t{}\t{:?}", exited.id, exited.cycles, exited.exe_file_name)
+            }));
+        let header = "pid\tstate\tcycles\texe\n".to_string();
+        let footer = format!("cpu: {} cycles since boot", self.get_cpu_cycles());
+        rows.fold(header, |acc, row| acc + &row + "\n") + &footer
+    }
+
+    /// Returns how many cycles the CPU executed since boot.
+    pub fn get_cpu_cycles(&self) -> u64 {
+        self.cpu.lock().unwrap().cycles
+    }
+
+    /// Returns the last processes that exited, oldest first.
+    pub fn get_exited_procs(&self) -> Vec<ExitedProc> {
+        self.proc_tbl.lock().unwrap().get_exited_procs().iter().cloned().collect()
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_console(&self) -> &Console {
+        &self.console
+    }
+
+    /// Sets the console of the processes that are not attached to one of their own.
+    /// Defaults to the standard input and output.
+    pub fn set_console(&mut self, console: Console) {
+        self.console = console;
+    }
+
+    pub fn get_timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    /// Records on `timeline` when the processes started or stepped from now on run, or stops
+    /// recording with `None`.
+    pub fn set_timeline(&mut self, timeline: Option<Timeline>) {
+        self.timeline = timeline;
+    }
+
+    pub fn get_tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Traces the instructions of the processes started or stepped from now on, or stops
+    /// tracing with `None`.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        if let Some(ref old_tracer) = self.tracer {
+            old_tracer.flush();
+        }
+        self.tracer = tracer;
+    }
+
+    /// Gives a loaded process a console of its own, before it is started.
+    pub fn attach_console(&mut self, proc_id: u16, console: Console) -> Result<(), String> {
+        self.get_pcb(proc_id)?;
+        self.consoles.insert(proc_id, console);
+        Ok(())
+    }
+
+    /// Returns the IDs of the processes in the process table, in ascending order.
+    pub fn get_proc_ids(&self) -> Vec<u16> {
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        let mut proc_ids = proc_tbl.get_running_procs()
+            .map(|arc_pcb| arc_pcb.lock().unwrap().get_id())
+            .collect::<Vec<u16>>();
+        proc_ids.sort();
+        proc_ids
+    }
+
+    pub fn get_proc_info(&self, proc_id: u16) -> Result<ProcInfo, String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let pcb = pcb.lock().unwrap();
+        Ok(pcb.get_info())
+    }
+
+    /// Returns `len` bytes of the data segment (the stack and the heap) of a process, from
+    /// `addr`.
+    pub fn read_mem(&self, proc_id: u16, addr: u16, len: usize) -> Result<Vec<u8>, String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let pcb = pcb.lock().unwrap();
+        let vaddr = get_data_vaddr(&pcb, addr, len)?;
+        (vaddr..vaddr + len)
+            .map(|vaddr| pcb.get_byte(vaddr))
+            .collect::<Result<Vec<u8>, ()>>()
+            .map_err(|_| format!("Cannot read the memory of process {}.", proc_id))
+    }
+
+    /// Writes `bytes` to the data segment of a process, from `addr`.
+    pub fn write_mem(&mut self, proc_id: u16, addr: u16, bytes: &[u8]) -> Result<(), String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let mut pcb = pcb.lock().unwrap();
+        let vaddr = get_data_vaddr(&pcb, addr, bytes.len())?;
+        for (idx, byte) in bytes.iter().enumerate() {
+            pcb.set_byte(vaddr + idx, *byte)
+                .map_err(|_| format!("Cannot write the memory of process {}.", proc_id))?;
+        }
+        Ok(())
+    }
+
+    /// Sets a register of a process that is not running.
+    pub fn set_reg(&mut self, proc_id: u16, reg: u8, val: u16) -> Result<(), String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let mut pcb = pcb.lock().unwrap();
+        let mut ctx = pcb.get_ctx();
+        ctx.set_reg(reg, val)
+            .map_err(|_| format!("Register {} does not exist.", reg))?;
+        pcb.save_cpu_ctx(&ctx);
+        Ok(())
+    }
+
+    /// Moves the instruction pointer of a process that is not running.
+    pub fn set_instr_ptr(&mut self, proc_id: u16, instr_ptr: u16) -> Result<(), String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let mut pcb = pcb.lock().unwrap();
+        let mut ctx = pcb.get_ctx();
+        ctx.instr_ptr = instr_ptr;
+        pcb.save_cpu_ctx(&ctx);
+        Ok(())
+    }
+
     pub fn kill(&mut self, proc_id: u16) -> Result<(), String> {
         if self.proc_tbl.lock().unwrap().contains(proc_id) {
-            self.exit_tx.send(proc_id);
-            Ok(())
+            self.exit_tx
+                .send(proc_id)
+                .map_err(|_| "The process table stopped freeing processes.".to_string())
         } else {
             Err(format!("No process with {} exists.", proc_id))
         }
     }
 
-    fn load_instr(&self, file_name: &str) -> Result<InstructionBlock, String> {
+    pub fn get_alloc_stats(&self) -> AllocStats {
+        self.proc_tbl.lock().unwrap().get_alloc_stats()
+    }
+
+    /// Reports how much RAM is used by every process and how fragmented the free RAM is.
+    pub fn mem_usage(&self) -> String {
+        use self::consts::{PAGE_LEN, WORD_LEN};
+
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        let stats = proc_tbl.get_alloc_stats();
+        let mut rows = proc_tbl.get_running_procs()
+            .map(|arc_pcb| {
+                let pcb = arc_pcb.lock().unwrap();
+                let ptes = pcb.get_ptes();
+                let num_data_pages = pcb.get_num_data_pages();
+                let heap_page_num = num_data_pages + pcb.get_num_stack_pages();
+                let count_present = |ptes: &[u16]| {
+                    ptes.iter().filter(|pte| *pte & PTE_PRESENT != 0).count()
+                };
+                (pcb.get_id(),
+                 pcb.get_header_len(),
+                 ptes.len() * WORD_LEN,
+                 count_present(&ptes[..num_data_pages]) * PAGE_LEN,
+                 count_present(&ptes[num_data_pages..heap_page_num]) * PAGE_LEN,
+                 count_present(&ptes[heap_page_num..]) * PAGE_LEN,
+                 pcb.get_exe_file_name().to_string())
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| row.0);
+        let header = "pid\theader\tpg_tbl\tdata\tstack\theap\ttotal\texe\n".to_string();
+        let table = rows.iter()
+            .map(|&(id, header_len, page_tbl_len, data_len, stack_len, heap_len,
+                    ref exe_file_name)| {
+                format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:?}",
+                        id,
+                        header_len,
+                        page_tbl_len,
+                        data_len,
+                        stack_len,
+                        heap_len,
+                        header_len + page_tbl_len + data_len + stack_len + heap_len,
+                        exe_file_name)
+            })
+            .fold(header, |acc, row| acc + &row + "\n");
+        let mem = self.mem.lock().unwrap();
+        let frames = mem.get_frames();
+        format!("total: {} bytes\nkernel: {} bytes\n\n{}\nallocator: {}\nused: {} bytes ({} \
+                 requested)\nfree: {} bytes in {} blocks\nlargest free block: {} bytes\n\
+                 fragmentation: {:.3}\nframes: {} of {} used ({} bytes each)",
+                self.config.ram_len,
+                ps::get_blk_region_addr(self.config.max_procs),
+                table,
+                stats.kind,
+                stats.used,
+                stats.requested,
+                stats.free,
+                stats.num_free_blks,
+                stats.largest_free_blk,
+                stats.get_external_fragmentation(),
+                frames.len() - frames.get_num_free(),
+                frames.len(),
+                PAGE_LEN)
+    }
+
+    /// Draws the RAM layout of design.md with the actual addresses.
+    pub fn mem_map(&self) -> String {
+        use self::consts::WORD_LEN;
+
+        let ram_len = self.config.ram_len;
+        let header_len = ps::get_header_len(self.config.num_registers);
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        let pcbs = proc_tbl.get_running_procs()
+            .map(|arc_pcb| {
+                let pcb = arc_pcb.lock().unwrap();
+                (pcb.get_id(),
+                 pcb.get_addr(),
+                 pcb.get_page_tbl_ptr() as usize,
+                 pcb.get_num_pages() as usize,
+                 pcb.get_exe_file_name().to_string())
+            })
+            .collect::<Vec<_>>();
+        let entries = proc_tbl.get_entries();
+        let mem = self.mem.lock().unwrap();
+        let next_blk = mem.get_ram().get_u16_at(NEXT_BLK_ADDR).unwrap();
+        let mut lines = vec!["        +==RAM_LYT==+".to_string(),
+                             format_map_row(NEXT_BLK_ADDR, "NEXT_BLK", &format_addr(next_blk)),
+                             format_map_sep("PS_TBL")];
+        for (entry_addr, pid, pcb_addr) in entries {
+            let pid = if pcb_addr == NULL_ADDR { "-".to_string() } else { pid.to_string() };
+            lines.push(format_map_row(entry_addr, "PID", &pid));
+            lines.push(format_map_row(entry_addr + 2, "PCB_ADDR", &format_addr(pcb_addr)));
+        }
+
+        let allocator = mem.get_allocator();
+        let mut blks = allocator.get_used_blks()
+            .into_iter()
+            .map(|(blk, _)| (blk, true))
+            .chain(allocator.get_free_blks().into_iter().map(|blk| (blk, false)))
+            .collect::<Vec<_>>();
+        blks.sort_by_key(|&(blk, _)| blk.addr);
+        let mut curr_addr = allocator.get_region().addr;
+        for (blk, is_used) in blks {
+            if blk.addr > curr_addr {
+                lines.push(format_map_sep(""));
+                lines.push(format_map_row(curr_addr,
+                                          "(unused)",
+                                          &format!("{} bytes", blk.addr - curr_addr)));
+            }
+            let header = pcbs.iter().find(|pcb| pcb.1 == blk.addr);
+            let page_tbl = pcbs.iter().find(|pcb| pcb.2 == blk.addr);
+            let used_len = match (is_used, header, page_tbl) {
+                (true, Some(&(id, _, _, _, ref exe_file_name)), _) => {
+                    lines.push(format_map_sep(&format!("PCB {}", id)));
+                    lines.push(format_map_row(blk.addr, "HEADER", exe_file_name));
+                    header_len
+                }
+                (true, _, Some(&(id, _, _, num_pages, _))) => {
+                    lines.push(format_map_sep(&format!("PG_TBL {}", id)));
+                    lines.push(format_map_row(blk.addr,
+                                              "PAGE_TBL",
+                                              &format!("{} pages", num_pages)));
+                    num_pages * WORD_LEN
+                }
+                _ => {
+                    let label = if is_used { "(used)" } else { "(free)" };
+                    lines.push(format_map_sep(""));
+                    lines.push(format_map_row(blk.addr, label, &format!("{} bytes", blk.len)));
+                    blk.len
+                }
+            };
+            if used_len < blk.len {
+                lines.push(format_map_row(blk.addr + used_len,
+                                          "(slack)",
+                                          &format!("{} bytes", blk.len - used_len)));
+            }
+            curr_addr = blk.end();
+        }
+
+        let frames = mem.get_frames();
+        if curr_addr < frames.get_addr() {
+            lines.push(format_map_sep(""));
+            lines.push(format_map_row(curr_addr,
+                                      "(unused)",
+                                      &format!("{} bytes", frames.get_addr() - curr_addr)));
+        }
+        // Runs of consecutive frames owned by the same process.
+        lines.push(format_map_sep("FRAMES"));
+        let mut frame_num = 0;
+        while frame_num < frames.len() {
+            let proc_id = frames.get_owner(frame_num).map(|owner| owner.proc_id);
+            let run_len = (frame_num..frames.len())
+                .take_while(|num| frames.get_owner(*num).map(|owner| owner.proc_id) == proc_id)
+                .count();
+            let label = match proc_id {
+                Some(proc_id) => format!("PROC {}", proc_id),
+                None => "(free)".to_string(),
+            };
+            lines.push(format_map_row(frames.get_frame_addr(frame_num),
+                                      &label,
+                                      &format!("{} frames", run_len)));
+            frame_num += run_len;
+        }
+        curr_addr = frames.get_frame_addr(frames.len());
+        if curr_addr < ram_len {
+            lines.push(format_map_sep(""));
+            lines.push(format_map_row(curr_addr,
+                                      "(unused)",
+                                      &format!("{} bytes", ram_len - curr_addr)));
+        }
+        lines.push(format!("0x{:04x}  +-----------+", ram_len));
+        lines.join("\n")
+    }
+
+    /// Reports paging activity since boot or the last `reset_vm_stats`.
+    pub fn vm_stats(&self) -> String {
+        let mem = self.mem.lock().unwrap();
+        let frames = mem.get_frames();
+        let swap = mem.get_swap();
+        format!("replacer: {}\nframes: {} of {} used\nswap: {} pages in {} slots\n{}\n\
+                 trace: {} references",
+                mem.get_replacer_kind(),
+                frames.len() - frames.get_num_free(),
+                frames.len(),
+                swap.get_num_used(),
+                swap.get_num_slots(),
+                mem.get_stats(),
+                mem.get_trace().len())
+    }
+
+    /// Zeroes the paging counters and forgets the pages referenced so far, so that the next
+    /// workload is measured and simulated on its own.
+    pub fn reset_vm_stats(&mut self) {
+        self.mem.lock().unwrap().reset_stats();
+    }
+
+    /// Describes the cache and its lookups since boot, then those of every process in the
+    /// process table and of the last ones that exited.
+    pub fn cache_stats(&self) -> Result<String, String> {
+        let cache = self.cache.as_ref().ok_or("There is no cache (cache_len is 0).".to_string())?;
+        let summary = {
+            let cache = cache.lock().unwrap();
+            format!("cache: {} bytes, {} sets of {} lines of {} bytes, {}\n{}\n",
+                    cache.get_len(),
+                    cache.get_num_sets(),
+                    cache.get_num_ways(),
+                    cache.get_line_len(),
+                    cache.get_policy(),
+                    cache.get_stats())
+        };
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        let mut running = proc_tbl.get_running_procs()
+            .map(|arc_pcb| {
+                let pcb = arc_pcb.lock().unwrap();
+                (pcb.get_id(), pcb.get_cache_stats(), pcb.get_exe_file_name().to_string())
+            })
+            .collect::<Vec<(u16, CacheStats, String)>>();
+        running.sort_by_key(|&(id, _, _)| id);
+        let rows = running.iter()
+            .map(|&(id, stats, ref exe_file_name)| {
+                format!("{}\trunning\t{}\t{:?}", id, stats, exe_file_name)
+            })
+            .chain(proc_tbl.get_exited_procs().iter().map(|exited| {
+                format!("{}\texited\t{}\t{:?}",
+                        exited.id,
+                        exited.cache_stats,
+                        exited.exe_file_name)
+            }));
+        Ok(rows.fold(summary + "pid\tstate\tlookups\texe\n", |acc, row| acc + &row + "\n"))
+    }
+
+    /// Empties the cache, as if the machine had just booted; the counters are kept.
+    pub fn flush_cache(&mut self) -> Result<(), String> {
+        let cache = self.cache.as_ref().ok_or("There is no cache (cache_len is 0).".to_string())?;
+        cache.lock().unwrap().flush();
+        Ok(())
+    }
+
+    /// Returns the cache lookups since boot, or `None` if there is no cache.
+    pub fn get_cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.lock().unwrap().get_stats())
+    }
+
+    /// Replays the pages referenced since boot or the last `reset_vm_stats` on `num_frames`
+    /// frames (all of them by default) with every replacer, optimal included.
+    pub fn simulate_replacers(&self, num_frames: Option<usize>) -> Result<String, String> {
+        let mem = self.mem.lock().unwrap();
+        let num_frames = num_frames.unwrap_or(mem.get_frames().len());
+        if num_frames == 0 {
+            return Err("Cannot simulate with no frames.".to_string());
+        }
+        let trace = mem.get_trace();
+        let header = format!("{} references on {} frames\nreplacer\tfaults\n",
+                             trace.len(),
+                             num_frames);
+        let mut table = header;
+        for kind in REPLACER_KINDS.iter() {
+            let num_faults = mmu::simulate(trace, num_frames, *kind)?;
+            table += &format!("{}\t\t{}\n", kind, num_faults);
+        }
+        Ok(table)
+    }
+
+    /// Returns a hex dump of `len` bytes of RAM starting at `addr`.
+    pub fn dump_ram(&self, addr: usize, len: usize) -> Result<String, String> {
+        let mem = self.mem.lock().unwrap();
+        let ram = mem.get_ram();
+        let end = addr.checked_add(len)
+            .ok_or(format!("Cannot dump {} bytes from 0x{:04x}.", len, addr))?;
+        ram.dump(addr..end)
+            .map_err(|_| format!("Cannot dump 0x{:04x}..0x{:04x}: RAM is {} bytes.",
+                                 addr,
+                                 end,
+                                 ram.len()))
+    }
+
+    fn load_exe(&self, file_name: &str) -> Result<Executable, String> {
         let file_bytes = match self.fs.open_bytes_as_vec(file_name) {
             Ok(vec) => Ok(vec),
             Err(err) => Err(err.to_string()),
         }?;
-        Ok(InstructionBlock::new(&file_bytes)?)
+        Executable::parse(&file_bytes).map_err(|err| format!("{}: {}", file_name, err))
+    }
+
+    /// Creates a process running the program `bytes` (an executable or legacy file), named
+    /// `exe_file_name`, without starting it. Returns its Process ID.
+    pub fn load(&mut self, exe_file_name: &str, bytes: &[u8]) -> Result<u16, String> {
+        let exe = Executable::parse(bytes)?;
+        self.alloc_proc(exe_file_name, &exe)
+    }
+
+    /// Creates a process running the program `file_name` of the file system, without starting
+    /// it. Returns its Process ID.
+    pub fn load_file(&mut self, file_name: &str) -> Result<u16, String> {
+        let exe = self.load_exe(file_name)?;
+        self.alloc_proc(file_name, &exe)
+    }
+
+    /// Loads the program `file_name` of the file system paused, under a debugger.
+    pub fn debug(&mut self, file_name: &str) -> Result<Debugger, String> {
+        let exe = self.load_exe(file_name)?;
+        let proc_id = self.alloc_proc(file_name, &exe)?;
+        Ok(Debugger::new(proc_id, exe))
+    }
+
+    /// Checks the program `file_name` of the file system without running it.
+    /// Returns an error listing the problems with its instructions.
+    pub fn verify(&self, file_name: &str) -> Result<String, String> {
+        let exe = self.load_exe(file_name)?;
+        self.verify_exe(file_name, &exe)?;
+        Ok(format!("{}: OK", file_name))
+    }
+
+    fn verify_exe(&self, exe_file_name: &str, exe: &Executable) -> Result<(), String> {
+        let diagnostics = instr::verify(exe, self.config.num_registers, self.config.stack_len);
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+        Err(diagnostics.iter()
+            .map(|diagnostic| format!("\n{}: {}", exe_file_name, diagnostic))
+            .fold(format!("{}: {} problem(s)", exe_file_name, diagnostics.len()),
+                  |acc, line| acc + &line))
+    }
+
+    /// Verifies a program, then creates a process running it.
+    fn alloc_proc(&mut self, exe_file_name: &str, exe: &Executable) -> Result<u16, String> {
+        self.verify_exe(exe_file_name, exe)?;
+        let mut proc_tbl = self.proc_tbl.lock().unwrap();
+        let proc_id = proc_tbl.alloc_pcb(exe_file_name.to_string(), exe)?;
+        // The console of an exited process with the same ID.
+        self.consoles.remove(&proc_id);
+        Ok(proc_id)
+    }
+
+    /// Runs a loaded process on a thread of its own.
+    pub fn start(&mut self,
+                 proc_id: u16,
+                 use_term: bool)
+                 -> Result<thread::JoinHandle<ExecResult>, String> {
+        let exec = self.get_executor(proc_id, use_term)?;
+        Ok(exec.start(self.exit_tx.clone()))
     }
 
     pub fn exec(&mut self,
                 file_name: &str,
                 use_term: bool)
                 -> Result<thread::JoinHandle<ExecResult>, String> {
-        let instr_blk = self.load_instr(file_name)?;
+        let proc_id = self.load_file(file_name)?;
+        self.start(proc_id, use_term)
+    }
+
+    /// Executes the next instruction of a loaded process on the calling thread.
+    /// The process is freed once the result is not `Success`.
+    pub fn step(&mut self, proc_id: u16) -> Result<ExecResult, String> {
+        let result = self.get_executor(proc_id, true)?.step();
+        if result != ExecResult::Success {
+            self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+        }
+        Ok(result)
+    }
+
+    /// Runs a loaded process on the calling thread until it stops, then frees it.
+    /// Returns `Success` if it was killed instead.
+    pub fn run(&mut self, proc_id: u16) -> Result<ExecResult, String> {
+        let result = self.get_executor(proc_id, true)?.run();
+        if result != ExecResult::Success {
+            self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+        }
+        Ok(result)
+    }
+
+    /// Runs the programs `file_names` of the file system to completion on the calling thread,
+    /// giving them time slices in turn, and returns a log of everything that could make
+    /// another run differ: the input they read and how many instructions each slice executed.
+    pub fn record(&mut self, file_names: &[String]) -> Result<RunLog, String> {
+        let mut log = RunLog::default();
+        let mut programs = vec![];
+        for file_name in file_names {
+            if file_name.contains('\n') || file_name.contains('\r') {
+                return Err(format!("{:?}: file names in run logs cannot span lines", file_name));
+            }
+            let bytes = self.fs.open_bytes_as_vec(file_name).map_err(|err| err.to_string())?;
+            log.programs.push(RecordedProgram {
+                file_name: file_name.to_string(),
+                hash: replay::hash(&bytes),
+            });
+            programs.push((file_name.as_str(), bytes));
+        }
+        let input = Arc::new(Mutex::new(vec![]));
+        let recording_input = RecordingInput::new(self.console.clone(), input.clone());
+        let console = self.console.with_input(Box::new(recording_input));
+        let proc_ids = self.load_all(&programs, &console)?;
+        let slices = self.record_slices(&proc_ids);
+        if slices.is_err() {
+            self.kill_all(&proc_ids);
+        }
+        log.slices = slices?;
+        log.input = input.lock().unwrap().clone();
+        Ok(log)
+    }
+
+    /// Gives the loaded processes `proc_ids` time slices in turn until they have all stopped.
+    fn record_slices(&mut self, proc_ids: &[u16]) -> Result<Vec<RecordedSlice>, String> {
+        let mut slices = vec![];
+        let mut queue = (0..proc_ids.len()).collect::<VecDeque<usize>>();
+        while let Some(proc_idx) = queue.pop_front() {
+            let slice = self.run_slice(proc_ids[proc_idx], proc_idx, None)?;
+            if slice.result == replay::get_result_name(&ExecResult::Success) {
+                queue.push_back(proc_idx);
+            }
+            slices.push(slice);
+        }
+        Ok(slices)
+    }
+
+    /// Runs the programs of `log` again, feeding them the recorded input and giving them the
+    /// recorded slices. Returns an error describing the first slice that ends differently.
+    pub fn replay(&mut self, log: &RunLog) -> Result<String, String> {
+        let mut programs = vec![];
+        for program in &log.programs {
+            let bytes = self.fs
+                .open_bytes_as_vec(&program.file_name)
+                .map_err(|err| err.to_string())?;
+            if replay::hash(&bytes) != program.hash {
+                return Err(format!("{}: not the recorded program", program.file_name));
+            }
+            programs.push((program.file_name.as_str(), bytes));
+        }
+        let console = self.console.with_input(Box::new(io::Cursor::new(log.input.clone())));
+        let proc_ids = self.load_all(&programs, &console)?;
+        let replayed = self.replay_slices(log, &proc_ids);
+        if replayed.is_err() {
+            self.kill_all(&proc_ids);
+        }
+        replayed?;
+        Ok(format!("Replayed {} slice(s) of {} program(s)", log.slices.len(), programs.len()))
+    }
+
+    /// Gives the loaded processes `proc_ids` the slices of `log`, checking that each ends as
+    /// recorded and that they have all stopped at the end.
+    fn replay_slices(&mut self, log: &RunLog, proc_ids: &[u16]) -> Result<(), String> {
+        for (idx, recorded) in log.slices.iter().enumerate() {
+            let proc_id = *proc_ids.get(recorded.proc_idx)
+                .ok_or(format!("Slice {}: no program {}", idx, recorded.proc_idx))?;
+            let replayed = self.run_slice(proc_id, recorded.proc_idx, Some(recorded.num_instrs))
+                .map_err(|err| format!("Diverged at slice {}: {}", idx, err))?;
+            if replayed != *recorded {
+                return Err(format!("Diverged at slice {}:\n  recorded: {}\n  replayed: {}",
+                                   idx,
+                                   recorded,
+                                   replayed));
+            }
+        }
+        let running = proc_ids.iter().filter(|proc_id| self.get_pcb(**proc_id).is_ok()).count();
+        if running > 0 {
+            return Err(format!("Diverged at the end: {} process(es) still running", running));
+        }
+        Ok(())
+    }
+
+    /// Kills those of the processes `proc_ids` that have not exited yet.
+    fn kill_all(&mut self, proc_ids: &[u16]) {
+        for &proc_id in proc_ids {
+            // Those that exited are gone already.
+            let _ = self.kill(proc_id);
+        }
+    }
+
+    /// Loads `programs`, as `(file name, bytes)`, on `console`. Frees them all if one fails.
+    fn load_all(&mut self,
+                programs: &[(&str, Vec<u8>)],
+                console: &Console)
+                -> Result<Vec<u16>, String> {
+        let mut proc_ids = vec![];
+        for &(file_name, ref bytes) in programs {
+            match self.load(file_name, bytes) {
+                Ok(proc_id) => {
+                    self.attach_console(proc_id, console.clone())?;
+                    proc_ids.push(proc_id);
+                }
+                Err(err) => {
+                    for proc_id in proc_ids {
+                        self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(proc_ids)
+    }
+
+    /// Runs a time slice of a process on the calling thread (see `Executor::run_slice`) and
+    /// returns it as recorded for the `proc_idx`th program. The process is freed once the
+    /// result is not `Success`.
+    fn run_slice(&mut self,
+                 proc_id: u16,
+                 proc_idx: usize,
+                 max_instrs: Option<usize>)
+                 -> Result<RecordedSlice, String> {
+        let exec = self.get_executor(proc_id, true)?;
+        let (result, num_instrs) = exec.run_slice(max_instrs)
+            .ok_or(format!("Process {} was killed.", proc_id))?;
+        let info = self.get_proc_info(proc_id)?;
+        if result != ExecResult::Success {
+            exec.report(&result);
+            self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+        }
+        Ok(RecordedSlice {
+            proc_idx,
+            num_instrs,
+            instr_ptr: info.instr_ptr,
+            registers: info.registers,
+            result: replay::get_result_name(&result).to_string(),
+        })
+    }
+
+    /// Writes the state of every process to `file_name` of the file system (see `Snapshot`).
+    /// Returns how many processes were saved.
+    pub fn save(&self, file_name: &str) -> Result<usize, String> {
+        let snapshot = {
+            let proc_tbl = self.proc_tbl.lock().unwrap();
+            // Hold every PCB, so that no process runs while the others are saved.
+            let pcbs = proc_tbl.get_running_procs().collect::<Vec<&Arc<Mutex<Pcb>>>>();
+            let pcbs = pcbs.iter()
+                .map(|pcb| pcb.lock().unwrap())
+                .collect::<Vec<MutexGuard<Pcb>>>();
+            let mut procs = pcbs.iter()
+                .filter(|pcb| pcb.get_status() != ps::Status::Killed)
+                .map(|pcb| snapshot_proc(pcb, self.consoles.get(&pcb.get_id())))
+                .collect::<Result<Vec<ProcSnapshot>, String>>()?;
+            procs.sort_by_key(|proc_snapshot| proc_snapshot.id);
+            Snapshot {
+                num_registers: self.config.num_registers,
+                stack_len: self.config.stack_len,
+                procs,
+                exited: proc_tbl.get_exited_procs().iter().cloned().collect(),
+            }
+        };
+        let bytes = snapshot.to_bytes().map_err(|err| format!("{}: {}", file_name, err))?;
+        self.fs
+            .create(file_name)
+            .and_then(|mut file| file.write_all(&bytes))
+            .map_err(|err| format!("{}: {}", file_name, err))?;
+        Ok(snapshot.procs.len())
+    }
+
+    /// Recreates the processes saved to `file_name` of the file system, with the same IDs and
+    /// their consoles, without starting them, and adds the processes that had exited to those
+    /// that did here. Returns their IDs. Frees them all if one cannot be restored.
+    pub fn restore(&mut self, file_name: &str) -> Result<Vec<u16>, String> {
+        let bytes = self.fs
+            .open_bytes_as_vec(file_name)
+            .map_err(|err| format!("{}: {}", file_name, err))?;
+        let snapshot = Snapshot::parse(&bytes)
+            .and_then(|snapshot| self.check_snapshot(&snapshot).map(|_| snapshot))
+            .map_err(|err| format!("{}: {}", file_name, err))?;
+        let mut proc_ids = vec![];
+        for proc_snapshot in &snapshot.procs {
+            match self.restore_proc(proc_snapshot, true) {
+                Ok(proc_id) => proc_ids.push(proc_id),
+                Err(err) => {
+                    for proc_id in proc_ids {
+                        self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
         let mut proc_tbl = self.proc_tbl.lock().unwrap();
-        let proc_id = proc_tbl.alloc_pcb(file_name.to_string(), instr_blk)
-            .ok_or("Could not allocate another process.".to_string())?;
-        let pcb = proc_tbl.get_pcb(proc_id);
-        let exec = Executor::new(self.cpu.clone(), pcb, use_term);
-        Ok(exec.start(self.exit_tx.clone()))
+        for exited_proc in snapshot.exited {
+            proc_tbl.push_exited_proc(exited_proc);
+        }
+        Ok(proc_ids)
+    }
+
+    /// Returns the state of a single process, as a snapshot that `receive` can restore.
+    pub fn checkpoint(&self, proc_id: u16) -> Result<Snapshot, String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let pcb = pcb.lock().unwrap();
+        self.snapshot_one(&pcb)
+    }
+
+    /// Moves a process to the system at the other end of `stream` (see `migrate::send`),
+    /// freezing it until the other system answers. The process is freed once the other system
+    /// has restored it and been told so, and resumes here otherwise. Either side gives up
+    /// after `MIGRATION_TIMEOUT_SECS` without an answer. Returns its Process ID on the other
+    /// system.
+    pub fn migrate(&mut self, proc_id: u16, stream: &mut TcpStream) -> Result<u16, String> {
+        let pcb = self.get_pcb(proc_id)?;
+        let snapshot = {
+            let mut pcb = pcb.lock().unwrap();
+            let snapshot = self.snapshot_one(&pcb)?;
+            // Its executor skips its slices, so the process stays as it was sent.
+            pcb.set_frozen(true);
+            snapshot
+        };
+        let result = migrate::set_timeouts(stream)
+            .and_then(|_| migrate::send(stream, &snapshot))
+            .and_then(|remote_proc_id| migrate::confirm(stream).map(|_| remote_proc_id));
+        let mut pcb = pcb.lock().unwrap();
+        match result {
+            Ok(remote_proc_id) => {
+                pcb.set_status(ps::Status::Killed);
+                drop(pcb);
+                self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+                self.consoles.remove(&proc_id);
+                Ok(remote_proc_id)
+            }
+            Err(err) => {
+                pcb.set_frozen(false);
+                Err(err)
+            }
+        }
+    }
+
+    /// Receives a process sent by `migrate` from `stream` and recreates it, with a new ID and
+    /// its console, without starting it. Frees it again if the sender does not confirm that
+    /// it freed its own copy. Returns its ID.
+    pub fn receive(&mut self, stream: &mut TcpStream) -> Result<u16, String> {
+        migrate::set_timeouts(stream)?;
+        let proc_id = migrate::receive(stream, |snapshot| {
+            self.check_snapshot(&snapshot)?;
+            match snapshot.procs.first() {
+                Some(proc_snapshot) if snapshot.procs.len() == 1 => {
+                    self.restore_proc(proc_snapshot, false)
+                }
+                _ => Err(format!("Expected 1 process, got {}.", snapshot.procs.len())),
+            }
+        })?;
+        if let Err(err) = migrate::wait_confirmation(stream) {
+            self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+            self.consoles.remove(&proc_id);
+            return Err(err);
+        }
+        Ok(proc_id)
+    }
+
+    fn snapshot_one(&self, pcb: &Pcb) -> Result<Snapshot, String> {
+        if pcb.get_status() == ps::Status::Killed {
+            return Err(format!("No process with {} exists.", pcb.get_id()));
+        }
+        Ok(Snapshot {
+            num_registers: self.config.num_registers,
+            stack_len: self.config.stack_len,
+            procs: vec![snapshot_proc(pcb, self.consoles.get(&pcb.get_id()))?],
+            exited: vec![],
+        })
+    }
+
+    /// Returns an error if the processes of `snapshot` were laid out for another machine.
+    fn check_snapshot(&self, snapshot: &Snapshot) -> Result<(), String> {
+        if snapshot.num_registers != self.config.num_registers ||
+           snapshot.stack_len != self.config.stack_len {
+            return Err(format!("Saved with {} registers and stacks of {} bytes, not {} and {}.",
+                               snapshot.num_registers,
+                               snapshot.stack_len,
+                               self.config.num_registers,
+                               self.config.stack_len));
+        }
+        Ok(())
+    }
+
+    /// Recreates a saved process, with the same ID if `keep_id`, without starting it. A process
+    /// that had a console of its own gets one with the input it had not read, on the streams of
+    /// the system's console.
+    fn restore_proc(&mut self, proc_snapshot: &ProcSnapshot, keep_id: bool) -> Result<u16, String> {
+        use self::consts::MAX_HEAP_LEN;
+
+        let name = &proc_snapshot.exe_file_name;
+        let stack_len = self.config.stack_len;
+        let heap_len = proc_snapshot.heap_len as usize;
+        let data_seg = &proc_snapshot.data_seg;
+        if proc_snapshot.registers.len() != self.config.num_registers ||
+           heap_len > MAX_HEAP_LEN || data_seg.len() < stack_len + heap_len {
+            return Err(format!("{}: process {} was not saved by this machine.",
+                               name,
+                               proc_snapshot.id));
+        }
+        let exe = Executable {
+            data: data_seg[..data_seg.len() - stack_len - heap_len].to_vec(),
+            ..proc_snapshot.exe.clone()
+        };
+        self.verify_exe(name, &exe)?;
+        let proc_id = {
+            let mut proc_tbl = self.proc_tbl.lock().unwrap();
+            if keep_id {
+                proc_tbl.alloc_pcb_as(proc_snapshot.id, name.to_string(), &exe)?
+            } else {
+                proc_tbl.alloc_pcb(name.to_string(), &exe)?
+            }
+        };
+        self.consoles.remove(&proc_id);
+        let result = {
+            let pcb = self.get_pcb(proc_id)?;
+            let mut pcb = pcb.lock().unwrap();
+            set_proc_state(&mut pcb, proc_snapshot)
+        };
+        if let Err(err) = result {
+            self.proc_tbl.lock().unwrap().dealloc_pcb(proc_id);
+            return Err(format!("{}: {}", name, err));
+        }
+        if let Some(ref input) = proc_snapshot.console_input {
+            self.consoles.insert(proc_id, self.console.with_buffered(input.clone()));
+        }
+        Ok(proc_id)
+    }
+
+    fn get_executor(&self, proc_id: u16, use_term: bool) -> Result<Executor, String> {
+        Ok(Executor::new(self.cpu.clone(),
+                         self.get_pcb(proc_id)?,
+                         use_term,
+                         self.consoles.get(&proc_id).unwrap_or(&self.console).clone(),
+                         self.config.time_slice_ms,
+                         self.config.slice_cycles,
+                         self.config.cycle_costs.clone(),
+                         self.cache.clone(),
+                         self.tracer.clone(),
+                         self.timeline.clone()))
+    }
+
+    fn get_pcb(&self, proc_id: u16) -> Result<Arc<Mutex<Pcb>>, String> {
+        let proc_tbl = self.proc_tbl.lock().unwrap();
+        if proc_tbl.contains(proc_id) {
+            Ok(proc_tbl.get_pcb(proc_id))
+        } else {
+            Err(format!("No process with {} exists.", proc_id))
+        }
     }
 }
 
-fn get_core_dump_str(cpu: &Cpu, pcb: &Pcb) -> String {
-    format!("{:?}\n{:?}\n", cpu, pcb)
+/// Returns the virtual address of the `len` bytes at `addr` of the data segment of `pcb`.
+fn get_data_vaddr(pcb: &Pcb, addr: u16, len: usize) -> Result<usize, String> {
+    let data_seg = pcb.get_data_seg();
+    if addr as usize + len > data_seg.limit as usize {
+        return Err(format!("Cannot access 0x{:04x}..0x{:04x}: the data segment is {} bytes.",
+                           addr,
+                           addr as usize + len,
+                           data_seg.limit));
+    }
+    Ok(data_seg.base as usize + addr as usize)
+}
+
+/// Returns the state of the process of `pcb`, attached to `console` if it has one of its own.
+fn snapshot_proc(pcb: &Pcb, console: Option<&Console>) -> Result<ProcSnapshot, String> {
+    let ctx = pcb.get_ctx();
+    let data_seg = pcb.get_data_seg();
+    let base = data_seg.base as usize;
+    // Saving must not page the process in, nor count as its accesses.
+    let data_seg = pcb.peek(base..base + data_seg.limit as usize);
+    Ok(ProcSnapshot {
+        id: pcb.get_id(),
+        status: pcb.get_status(),
+        cycles: pcb.get_cycles(),
+        exe_file_name: pcb.get_exe_file_name().to_string(),
+        instr_ptr: ctx.instr_ptr,
+        registers: ctx.registers,
+        exe: pcb.get_exe(),
+        data_seg,
+        heap_len: pcb.get_heap_len(),
+        console_input: console.map(|console| console.get_buffered()),
+    })
+}
+
+/// Gives the fresh process of `pcb` the status, heap, memory and context of a saved one.
+fn set_proc_state(pcb: &mut Pcb, proc_snapshot: &ProcSnapshot) -> Result<(), String> {
+    pcb.sbrk(proc_snapshot.heap_len as i16)?;
+    let base = pcb.get_data_seg().base as usize;
+    for (idx, byte) in proc_snapshot.data_seg.iter().enumerate() {
+        pcb.set_byte(base + idx, *byte).map_err(|_| "Cannot write the memory.".to_string())?;
+    }
+    let mut ctx = pcb.get_ctx();
+    ctx.instr_ptr = proc_snapshot.instr_ptr;
+    ctx.registers = proc_snapshot.registers.clone();
+    pcb.save_cpu_ctx(&ctx);
+    pcb.set_status(proc_snapshot.status);
+    pcb.set_cycles(proc_snapshot.cycles);
+    Ok(())
+}
+
+fn format_addr(addr: u16) -> String {
+    format!("0x{:04x}", addr)
+}
+
+fn format_map_row(addr: usize, label: &str, val: &str) -> String {
+    format!("0x{:04x}  | {:<9} | {}", addr, label, val)
+}
+
+fn format_map_sep(label: &str) -> String {
+    format!("        +--{:-<9}+", label)
 }
@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use time_utils;
+use super::ps::Pcb;
+
+/// How many events a `Timeline` keeps; older ones are dropped.
+pub const MAX_TIMELINE_EVENTS: usize = 0x10000;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EventKind {
+    /// The process got the CPU.
+    SliceStart,
+    /// The process gave the CPU back.
+    SliceEnd,
+    /// The process waits for input.
+    Block,
+    /// The input the process waited for arrived.
+    Wakeup,
+    /// The process stopped for good.
+    Exit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub proc_id: u16,
+    /// The load sequence number of the process (see `Pcb::get_load_seq`), which tells apart
+    /// the processes that had the same PID.
+    pub load_seq: u64,
+    pub kind: EventKind,
+    /// Microseconds since the timeline started.
+    pub time_us: i64,
+}
+
+/// Records which process had the CPU when, for the executors of a `System`.
+/// Clones share the same events.
+#[derive(Clone)]
+pub struct Timeline {
+    start: time_utils::Tm,
+    events: Arc<Mutex<VecDeque<Event>>>,
+    /// The PID and executable of every process seen, by load sequence number.
+    names: Arc<Mutex<HashMap<u64, (u16, String)>>>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            start: time_utils::now(),
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            names: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn record(&self, pcb: &Pcb, kind: EventKind) {
+        let time_us = time_utils::since(&self.start)
+            .num_microseconds()
+            .unwrap_or(i64::MAX);
+        self.record_at(pcb.get_id(),
+                       pcb.get_load_seq(),
+                       pcb.get_exe_file_name(),
+                       kind,
+                       time_us);
+    }
+
+    /// Records an event of the process `proc_id`, loaded `load_seq`th, which runs
+    /// `exe_file_name`, `time_us` microseconds after the timeline started.
+    pub fn record_at(&self,
+                     proc_id: u16,
+                     load_seq: u64,
+                     exe_file_name: &str,
+                     kind: EventKind,
+                     time_us: i64) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == MAX_TIMELINE_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(Event {
+            proc_id,
+            load_seq,
+            kind,
+            time_us,
+        });
+        if kind == EventKind::SliceStart {
+            self.names.lock().unwrap().insert(load_seq, (proc_id, exe_file_name.to_string()));
+        }
+    }
+
+    pub fn get_events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+
+    /// Returns the events in the Chrome trace-event format (JSON), with a row per process:
+    /// time slices and the waits for input nested in them are durations, exits are instants.
+    /// The ends of durations whose beginning was dropped are left out.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut entries = vec![];
+        for (load_seq, label) in self.get_labels() {
+            entries.push(format!("{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\
+                                  \"args\":{{\"name\":\"{}\"}}}}",
+                                 load_seq,
+                                 escape_json(&label)));
+        }
+        let mut begun = HashSet::new();
+        for event in self.get_events() {
+            let (name, phase) = match event.kind {
+                EventKind::SliceStart | EventKind::Block => {
+                    begun.insert((event.load_seq, event.kind));
+                    let name = if event.kind == EventKind::Block { "blocked" } else { "running" };
+                    (name, "B")
+                }
+                EventKind::SliceEnd => {
+                    if !begun.remove(&(event.load_seq, EventKind::SliceStart)) {
+                        continue;
+                    }
+                    ("running", "E")
+                }
+                EventKind::Wakeup => {
+                    if !begun.remove(&(event.load_seq, EventKind::Block)) {
+                        continue;
+                    }
+                    ("blocked", "E")
+                }
+                EventKind::Exit => ("exit", "i"),
+            };
+            entries.push(format!("{{\"name\":\"{}\",\"ph\":\"{}\",\"pid\":1,\"tid\":{},\
+                                  \"ts\":{}}}",
+                                 name,
+                                 phase,
+                                 event.load_seq,
+                                 event.time_us));
+        }
+        format!("{{\"traceEvents\":[\n{}\n]}}\n", entries.join(",\n"))
+    }
+
+    /// Draws which process had the CPU when, `width` columns wide: `#` for running, `b` for
+    /// blocked on input and `.` for waiting.
+    pub fn to_gantt_chart(&self, width: usize) -> String {
+        let events = self.get_events();
+        let (first, last) = match (events.first(), events.last()) {
+            (Some(first), Some(last)) if width > 0 => (first.time_us, last.time_us),
+            _ => return "No events recorded.".to_string(),
+        };
+        let col_len = ((last - first) as f64 / width as f64).max(1.0);
+        let to_col = |time_us: i64| (((time_us - first) as f64 / col_len) as usize).min(width - 1);
+        let mut rows = BTreeMap::new();
+        let mut starts = HashMap::new();
+        for event in &events {
+            let row = rows.entry(event.load_seq).or_insert(vec!['.'; width]);
+            match event.kind {
+                EventKind::SliceStart | EventKind::Block => {
+                    starts.insert((event.load_seq, event.kind), event.time_us);
+                }
+                EventKind::SliceEnd | EventKind::Wakeup => {
+                    let (start_kind, ch) = if event.kind == EventKind::SliceEnd {
+                        (EventKind::SliceStart, '#')
+                    } else {
+                        (EventKind::Block, 'b')
+                    };
+                    let start = starts.remove(&(event.load_seq, start_kind)).unwrap_or(first);
+                    for cell in &mut row[to_col(start)..to_col(event.time_us) + 1] {
+                        if *cell != 'b' {
+                            *cell = ch;
+                        }
+                    }
+                }
+                EventKind::Exit => (),
+            }
+        }
+        let labels = self.get_labels();
+        let label_len = labels.values().map(|label| label.len()).max().unwrap_or(0);
+        let mut chart = format!("{} us, {:.0} us per column\n", last - first, col_len);
+        for (load_seq, row) in rows {
+            let proc_id = events.iter()
+                .find(|event| event.load_seq == load_seq)
+                .map_or(0, |event| event.proc_id);
+            let label = labels.get(&load_seq).cloned().unwrap_or(proc_id.to_string());
+            chart += &format!("{:width$} |{}|\n",
+                              label,
+                              row.into_iter().collect::<String>(),
+                              width = label_len);
+        }
+        chart + "# running, b blocked on input, . waiting"
+    }
+
+    /// Returns the PID and executable of every process seen, by load sequence number.
+    fn get_labels(&self) -> BTreeMap<u64, String> {
+        let names = self.names.lock().unwrap();
+        names.iter()
+            .map(|(load_seq, &(proc_id, ref name))| (*load_seq, format!("{} {}", proc_id, name)))
+            .collect()
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    s.chars()
+        .flat_map(|ch| {
+            match ch {
+                '"' | '\\' => vec!['\\', ch],
+                ch if (ch as u32) < 0x20 => format!("\\u{:04x}", ch as u32).chars().collect(),
+                ch => vec![ch],
+            }
+        })
+        .collect()
+}
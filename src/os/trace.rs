@@ -0,0 +1,164 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use byte_utils;
+use os::consts::WORD_LEN;
+use super::cpu::{Cpu, SegmentKind};
+use super::debug;
+use super::exec::ExecResult;
+use super::instr::{Instruction, InstructionType};
+use super::ps::Pcb;
+
+/// Which instructions a `Tracer` records. Everything by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceFilter {
+    /// Only the instructions of this process.
+    pub proc_id: Option<u16>,
+    /// Only the instructions at these code addresses.
+    pub addrs: Option<Range<u16>>,
+}
+
+/// Records the instructions that processes execute, one line each:
+/// `PID IP  INSTRUCTION  EFFECTS`, where the effects are the registers and the data words the
+/// instruction wrote (`r0=0x002a`, `[0x0010]=0x002a`), and how the process ended if it did.
+#[derive(Clone)]
+pub struct Tracer {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+    filter: TraceFilter,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write + Send>, filter: TraceFilter) -> Self {
+        Tracer {
+            out: Arc::new(Mutex::new(out)),
+            filter,
+        }
+    }
+
+    /// Traces to the file at `path`, replacing it.
+    pub fn create(path: &str, filter: TraceFilter) -> Result<Self, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(Tracer::new(Box::new(BufWriter::new(file)), filter))
+    }
+
+    pub fn get_filter(&self) -> &TraceFilter {
+        &self.filter
+    }
+
+    /// Whether the instruction at `instr_ptr` of `proc_id` passes the filter.
+    pub fn is_traced(&self, proc_id: u16, instr_ptr: u16) -> bool {
+        self.filter.proc_id.is_none_or(|traced_id| traced_id == proc_id) &&
+        self.filter.addrs.as_ref().is_none_or(|addrs| addrs.contains(&instr_ptr))
+    }
+
+    /// Records `instr`, which was at `instr_ptr` and ended with `result`, given the registers
+    /// before it (`old_registers`) and the state of `cpu` and `pcb` after it.
+    pub fn record(&self,
+                  cpu: &Cpu,
+                  pcb: &Pcb,
+                  instr_ptr: u16,
+                  instr: &Instruction,
+                  old_registers: &[u16],
+                  result: &ExecResult) {
+        let mut line = format!("{} 0x{:04x}  {}",
+                               pcb.get_id(),
+                               instr_ptr,
+                               debug::disassemble(instr));
+        for (idx, (old_val, new_val)) in old_registers.iter().zip(&cpu.registers).enumerate() {
+            if old_val != new_val {
+                line += &format!("  r{}=0x{:04x}", idx, new_val);
+            }
+        }
+        if *result == ExecResult::Success {
+            let written = match instr.get_type() {
+                InstructionType::Store => Some((instr.get_literal_1(), WORD_LEN)),
+                InstructionType::CharRead => Some((instr.get_literal_1(), 1)),
+                _ => None,
+            };
+            if let Some((addr, len)) = written {
+                line += &format!("  [0x{:04x}]={}", addr, read_data(cpu, pcb, addr, len));
+            }
+        } else {
+            line += &format!("  -> {:?}", result);
+        }
+        if let Err(err) = writeln!(self.out.lock().unwrap(), "{}", line) {
+            warn!("Cannot write the trace: {}", err);
+        }
+    }
+
+    pub fn flush(&self) {
+        if let Err(err) = self.out.lock().unwrap().flush() {
+            warn!("Cannot write the trace: {}", err);
+        }
+    }
+}
+
+impl FromStr for TraceFilter {
+    type Err = String;
+
+    /// Parses comma-separated conditions: `pid=N` and `addrs=START..END`, e.g.
+    /// `pid=1,addrs=0x10..0x40`. An empty string traces everything.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = TraceFilter::default();
+        for cond in s.split(',').filter(|cond| !cond.is_empty()) {
+            let mut tokens = cond.splitn(2, '=');
+            let key = tokens.next().unwrap();
+            let val = tokens.next().ok_or(format!("{}: expected `key=value`", cond))?;
+            match key {
+                "pid" => filter.proc_id = Some(parse_num(val)?),
+                "addrs" => {
+                    let mut bounds = val.splitn(2, "..");
+                    let start = parse_num(bounds.next().unwrap())?;
+                    let end = parse_num(bounds.next().ok_or(format!("{}: expected START..END",
+                                                                     val))?)?;
+                    filter.addrs = Some(start..end);
+                }
+                _ => return Err(format!("{}: unknown condition (expected pid or addrs)", key)),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl fmt::Display for TraceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut conds = vec![];
+        if let Some(proc_id) = self.proc_id {
+            conds.push(format!("pid={}", proc_id));
+        }
+        if let Some(ref addrs) = self.addrs {
+            conds.push(format!("addrs=0x{:04x}..0x{:04x}", addrs.start, addrs.end));
+        }
+        if conds.is_empty() {
+            write!(f, "everything")
+        } else {
+            write!(f, "{}", conds.join(","))
+        }
+    }
+}
+
+fn parse_num(s: &str) -> Result<u16, String> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse::<u16>(),
+    };
+    result.map_err(|_| format!("{}: not a number", s))
+}
+
+/// Returns the `len`-byte value at data address `addr`, in hexadecimal.
+/// Tracing must not change what it observes, so the value is peeked rather than read.
+fn read_data(cpu: &Cpu, pcb: &Pcb, addr: u16, len: usize) -> String {
+    let vaddr = match cpu.translate(SegmentKind::Data, addr, len) {
+        Ok(vaddr) => vaddr,
+        Err(_) => return "?".to_string(),
+    };
+    let bytes = pcb.peek(vaddr..vaddr + len);
+    if len == WORD_LEN {
+        format!("0x{:04x}", byte_utils::u16_from_bytes([bytes[0], bytes[1]]))
+    } else {
+        format!("0x{:02x}", bytes[0])
+    }
+}
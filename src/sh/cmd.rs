@@ -26,9 +26,28 @@ impl CommandWithArgs {
 pub enum Command {
     ListFiles,
     ListProcesses,
+    ListCycles,
     Execute,
     ExecuteAsync,
     Kill,
+    DumpRam,
+    MemUsage,
+    MemMap,
+    CacheStats,
+    VmStats,
+    VmSim,
+    Verify,
+    Debug,
+    GdbServer,
+    Trace,
+    Timeline,
+    Gantt,
+    Record,
+    Replay,
+    Save,
+    Restore,
+    Migrate,
+    Accept,
     Exit,
 }
 
@@ -39,6 +58,7 @@ fn extract_cmd(tokens: &Vec<&str>) -> Option<Command> {
     match cmd {
         "ls" => Some(ListFiles),
         "ps" => Some(ListProcesses),
+        "cycles" => Some(ListCycles),
         "exec" => {
             if *tokens.last().unwrap() == "&" {
                 Some(ExecuteAsync)
@@ -47,6 +67,24 @@ fn extract_cmd(tokens: &Vec<&str>) -> Option<Command> {
             }
         }
         "kill" => Some(Kill),
+        "dump" => Some(DumpRam),
+        "free" => Some(MemUsage),
+        "mem" => Some(MemMap),
+        "cache" => Some(CacheStats),
+        "vmstat" => Some(VmStats),
+        "vmsim" => Some(VmSim),
+        "verify" => Some(Verify),
+        "debug" => Some(Debug),
+        "gdbserver" => Some(GdbServer),
+        "trace" => Some(Trace),
+        "timeline" => Some(Timeline),
+        "gantt" => Some(Gantt),
+        "record" => Some(Record),
+        "replay" => Some(Replay),
+        "save" => Some(Save),
+        "restore" => Some(Restore),
+        "migrate" => Some(Migrate),
+        "accept" => Some(Accept),
         "exit" => Some(Exit),
         _ => None,
     }
@@ -0,0 +1,203 @@
+use std::convert::TryFrom;
+use byte_utils::dump_hex;
+use os::{Debugger, SectionKind, StopReason, System};
+use super::sh::parse_num;
+
+pub const DEBUG_PROMPT: &str = "(dbg) ";
+
+const HELP: &str = "break LOC        stop before the instruction at LOC (an address or a label)
+delete LOC       remove the breakpoint at LOC
+watch ADDR       stop when the word at data address ADDR (or a data label) changes
+unwatch ADDR     remove the watchpoint at ADDR
+step [N]         execute N instructions (1 by default)
+next             execute until the following instruction, finishing loops
+continue         execute until a breakpoint, a watchpoint or the end
+limit [N]        show or set how many instructions next and continue execute at most
+where            show the next instruction
+regs             show the registers
+set rN VAL       set register N
+stack            show the stack
+x ADDR [LEN]     show LEN (16) bytes of the data segment from ADDR
+poke ADDR BYTE.. write bytes to the data segment from ADDR
+info             list the breakpoints and watchpoints
+quit             kill the process and leave the debugger";
+
+/// The state of the shell's `debug` mode, which reads debugger commands instead of shell
+/// commands until the process exits or the user quits.
+pub struct DebugSession {
+    dbg: Debugger,
+}
+
+impl DebugSession {
+    pub fn new(dbg: Debugger) -> Self {
+        DebugSession { dbg }
+    }
+
+    /// Describes the paused process.
+    pub fn start(&self, system: &System) -> String {
+        format!("Debugging process {} (type `help` for commands)\n{}",
+                self.dbg.get_proc_id(),
+                self.where_(system).unwrap_or_else(|err| err))
+    }
+
+    /// Runs the debugger command `line`. Returns its output, and whether the session is over.
+    pub fn exec_cmd(&mut self, system: &mut System, line: &str) -> (String, bool) {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        if tokens.is_empty() {
+            return (String::new(), false);
+        }
+        let result = match (tokens[0], &tokens[1..]) {
+            ("help", _) => Ok(HELP.to_string()),
+            ("quit", _) | ("q", _) => {
+                if !self.dbg.has_exited() {
+                    let _ = system.kill(self.dbg.get_proc_id());
+                }
+                return ("Left the debugger.".to_string(), true);
+            }
+            ("break", &[loc]) | ("b", &[loc]) => {
+                self.dbg
+                    .resolve(loc, SectionKind::Code)
+                    .and_then(|addr| {
+                        self.dbg.add_breakpoint(addr)?;
+                        Ok(format!("Breakpoint at {}", self.dbg.describe(addr)))
+                    })
+            }
+            ("delete", &[loc]) | ("d", &[loc]) => {
+                self.dbg.resolve(loc, SectionKind::Code).and_then(|addr| {
+                    if self.dbg.remove_breakpoint(addr) {
+                        Ok(format!("Deleted the breakpoint at 0x{:04x}", addr))
+                    } else {
+                        Err(format!("No breakpoint at 0x{:04x}", addr))
+                    }
+                })
+            }
+            ("watch", &[loc]) | ("w", &[loc]) => {
+                self.dbg.resolve(loc, SectionKind::Data).and_then(|addr| {
+                    self.dbg.add_watchpoint(system, addr)?;
+                    Ok(format!("Watching the word at 0x{:04x}", addr))
+                })
+            }
+            ("unwatch", &[loc]) => {
+                self.dbg.resolve(loc, SectionKind::Data).and_then(|addr| {
+                    if self.dbg.remove_watchpoint(addr) {
+                        Ok(format!("Stopped watching 0x{:04x}", addr))
+                    } else {
+                        Err(format!("No watchpoint at 0x{:04x}", addr))
+                    }
+                })
+            }
+            ("step", args) | ("s", args) => {
+                let num_instrs = match args.first() {
+                    Some(arg) => parse_num(arg),
+                    None => Ok(1),
+                };
+                num_instrs.and_then(|num_instrs| self.dbg.step(system, num_instrs))
+                    .and_then(|reason| self.report(system, reason))
+            }
+            ("next", _) | ("n", _) => {
+                self.dbg.next(system).and_then(|reason| self.report(system, reason))
+            }
+            ("continue", _) | ("c", _) => {
+                self.dbg.cont(system).and_then(|reason| self.report(system, reason))
+            }
+            ("limit", &[]) => Ok(format!("{} instructions", self.dbg.get_max_steps())),
+            ("limit", &[max_steps]) => {
+                parse_num(max_steps).map(|max_steps| {
+                    self.dbg.set_max_steps(max_steps);
+                    String::new()
+                })
+            }
+            ("where", _) => self.where_(system),
+            ("regs", _) | ("r", _) => self.regs(system),
+            ("set", &[reg, val]) if reg.starts_with('r') => {
+                parse_in_range::<u8>(&reg[1..])
+                    .and_then(|reg| Ok((reg, parse_in_range::<u16>(val)?)))
+                    .and_then(|(reg, val)| {
+                        system.set_reg(self.dbg.get_proc_id(), reg, val)?;
+                        self.regs(system)
+                    })
+            }
+            ("stack", _) => {
+                system.get_proc_info(self.dbg.get_proc_id())
+                    .map(|info| dump_hex(0, &info.stack))
+            }
+            ("x", args) if !args.is_empty() && args.len() <= 2 => {
+                let len = match args.get(1) {
+                    Some(arg) => parse_num(arg),
+                    None => Ok(16),
+                };
+                self.dbg
+                    .resolve(args[0], SectionKind::Data)
+                    .and_then(|addr| Ok((addr, len?)))
+                    .and_then(|(addr, len)| {
+                        let bytes = system.read_mem(self.dbg.get_proc_id(), addr, len)?;
+                        Ok(dump_hex(addr as usize, &bytes))
+                    })
+            }
+            ("poke", args) if args.len() >= 2 => {
+                let bytes = args[1..]
+                    .iter()
+                    .map(|arg| parse_in_range::<u8>(arg))
+                    .collect::<Result<Vec<u8>, String>>();
+                self.dbg
+                    .resolve(args[0], SectionKind::Data)
+                    .and_then(|addr| Ok((addr, bytes?)))
+                    .and_then(|(addr, bytes)| {
+                        system.write_mem(self.dbg.get_proc_id(), addr, &bytes)?;
+                        Ok(dump_hex(addr as usize, &bytes))
+                    })
+            }
+            ("info", _) => Ok(self.info()),
+            (cmd, _) => Err(format!("{}: unknown command or wrong arguments (see `help`)", cmd)),
+        };
+        match result {
+            Ok(output) => (output.trim_end().to_string(), self.dbg.has_exited()),
+            Err(err) => (err, self.dbg.has_exited()),
+        }
+    }
+
+    fn report(&self, system: &System, reason: StopReason) -> Result<String, String> {
+        match reason {
+            StopReason::Exited(_) => Ok(format!("Process {} {}", self.dbg.get_proc_id(), reason)),
+            StopReason::Stepped => self.where_(system),
+            _ => Ok(format!("Stopped: {}\n{}", reason, self.where_(system)?)),
+        }
+    }
+
+    fn where_(&self, system: &System) -> Result<String, String> {
+        let instr_ptr = self.dbg.get_instr_ptr(system)?;
+        Ok(format!("=> {}", self.dbg.describe(instr_ptr)))
+    }
+
+    fn regs(&self, system: &System) -> Result<String, String> {
+        let info = system.get_proc_info(self.dbg.get_proc_id())?;
+        let regs = info.registers
+            .iter()
+            .enumerate()
+            .map(|(idx, reg)| format!("r{} = {} (0x{:04x})", idx, reg, reg))
+            .collect::<Vec<String>>();
+        Ok(format!("ip = 0x{:04x}\n{}", info.instr_ptr, regs.join("\n")))
+    }
+
+    fn info(&self) -> String {
+        let breakpoints = self.dbg
+            .get_breakpoints()
+            .iter()
+            .map(|addr| format!("breakpoint {}", self.dbg.describe(*addr)));
+        let watchpoints = self.dbg
+            .get_watchpoints()
+            .iter()
+            .map(|(addr, val)| format!("watchpoint 0x{:04x} = {} (0x{:04x})", addr, val, val));
+        let lines = breakpoints.chain(watchpoints).collect::<Vec<String>>();
+        if lines.is_empty() {
+            "No breakpoints or watchpoints.".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
+/// Parses `s` like `parse_num`, failing if the number does not fit in a `T`.
+fn parse_in_range<T: TryFrom<usize>>(s: &str) -> Result<T, String> {
+    parse_num(s).and_then(|num| T::try_from(num).map_err(|_| format!("{}: out of range", s)))
+}
@@ -1,4 +1,5 @@
 pub use self::sh::Shell;
 
 mod cmd;
+mod debug;
 mod sh;
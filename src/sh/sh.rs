@@ -1,41 +1,104 @@
-use std::io;
+use std::fs;
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
 use io_utils;
 use sh::cmd::{CommandWithArgs, Command};
-use os::System;
+use sh::debug::{DebugSession, DEBUG_PROMPT};
+use os::{Console, ExecResult, GdbStub, RunLog, System, Timeline, Tracer};
+
+/// The default number of columns of `gantt`.
+const GANTT_WIDTH: usize = 64;
 
 // #[derive(Debug)]
 pub struct Shell {
     system: System,
     // TODO: make it a `&'static str` or `&'a str`? or AsRef<String>
     prompt: String,
-    reader: io::Stdin,
-    writer: io::Stdout,
+    /// Where the output goes, and where commands come from unless there is a script.
+    /// Processes run on it too.
+    console: Console,
+    /// The commands to run instead, each of which is written after the prompt.
+    script: Option<Box<dyn Read + Send>>,
+    /// The process being debugged, whose commands are read instead of shell commands.
+    session: Option<DebugSession>,
 }
 
 impl Shell {
+    /// Creates a shell on the console of `system`.
     pub fn new(system: System, prompt: String) -> Self {
+        let console = system.get_console().clone();
+        Shell::with_console(system, prompt, console)
+    }
+
+    pub fn with_console(system: System, prompt: String, console: Console) -> Self {
         Shell {
-            system: system,
-            prompt: prompt,
-            reader: io::stdin(),
-            writer: io::stdout(),
+            system,
+            prompt,
+            console,
+            script: None,
+            session: None,
         }
     }
 
+    /// Creates a shell on the console of `system` that runs the commands read from `script`
+    /// instead, writing each of them after the prompt.
+    pub fn with_script(system: System, prompt: String, script: Box<dyn Read + Send>) -> Self {
+        let mut shell = Shell::new(system, prompt);
+        shell.script = Some(script);
+        shell
+    }
+
+    /// Runs commands until `exit` or the end of the input.
     pub fn start(&mut self) -> Result<(), String> {
         loop {
-            let cmd_args = self.get_user_cmd();
+            if let Some(mut session) = self.session.take() {
+                let line = match self.read_prompted_line(DEBUG_PROMPT) {
+                    Some(line) => line,
+                    None => return Ok(()),
+                };
+                let (output, is_done) = session.exec_cmd(&mut self.system, &line);
+                if !output.is_empty() {
+                    self.write_ln(&output);
+                }
+                if !is_done {
+                    self.session = Some(session);
+                }
+                continue;
+            }
+            let cmd_args = match self.get_user_cmd() {
+                Some(cmd_args) => cmd_args,
+                None => return Ok(()),
+            };
             let cmd = &cmd_args.cmd;
             match *cmd {
                 Command::Exit => return Ok(()),
                 Command::Execute => {
                     let file = &cmd_args.args[0];
-                    let handle = self.system.exec(file, true)?;
-                    handle.join();
+                    match self.exec(file, true) {
+                        Ok(handle) => {
+                            if handle.join().is_err() {
+                                self.write_ln("The thread of the process panicked.");
+                            }
+                        }
+                        Err(err) => self.write_ln(&err),
+                    }
                 }
                 Command::ExecuteAsync => {
                     let file = &cmd_args.args[0];
-                    self.system.exec(file, false)?;
+                    if let Err(err) = self.exec(file, false) {
+                        self.write_ln(&err);
+                    }
+                }
+                Command::Debug => {
+                    match self.debug(cmd_args.args.first()) {
+                        Ok(session) => {
+                            let output = session.start(&self.system);
+                            self.write_ln(&output);
+                            self.session = Some(session);
+                        }
+                        Err(err) => self.write_ln(&err),
+                    }
                 }
                 _ => {
                     let result = self.exec_cmd(&cmd_args);
@@ -55,21 +118,186 @@ impl Shell {
         match command.cmd {
             ListFiles => Ok(self.system.list_files()),
             ListProcesses => Ok(self.system.list_procs()),
+            ListCycles => Ok(self.system.list_cycles()),
+            MemUsage => Ok(self.system.mem_usage()),
+            MemMap => Ok(self.system.mem_map()),
+            VmStats => {
+                match command.args.first().map(|arg| arg.as_str()) {
+                    Some("reset") => {
+                        self.system.reset_vm_stats();
+                        Ok("Reset the paging statistics".to_string())
+                    }
+                    None => Ok(self.system.vm_stats()),
+                    Some(_) => Err("Usage: vmstat [reset]".to_string()),
+                }
+            }
+            CacheStats => {
+                match command.args.first().map(|arg| arg.as_str()) {
+                    Some("flush") => {
+                        self.system.flush_cache()?;
+                        Ok("Flushed the cache".to_string())
+                    }
+                    None => self.system.cache_stats(),
+                    Some(_) => Err("Usage: cache [flush]".to_string()),
+                }
+            }
+            VmSim => {
+                let num_frames = match command.args.first() {
+                    Some(arg) => Some(parse_num(arg)?),
+                    None => None,
+                };
+                self.system.simulate_replacers(num_frames)
+            }
+            Verify => {
+                match command.args.first() {
+                    Some(file_name) => self.system.verify(file_name),
+                    None => Err("Usage: verify FILE".to_string()),
+                }
+            }
+            GdbServer => {
+                if command.args.len() != 2 {
+                    return Err("Usage: gdbserver PORT FILE".to_string());
+                }
+                let port = command.args[0]
+                    .parse::<u16>()
+                    .map_err(|_| format!("{}: not a port", command.args[0]))?;
+                self.gdb_server(port, &command.args[1])
+            }
+            Trace => {
+                match command.args.first().map(|arg| arg.as_str()) {
+                    Some("off") => {
+                        self.system.set_tracer(None);
+                        Ok("Tracing off".to_string())
+                    }
+                    Some(path) if command.args.len() <= 2 => {
+                        let filter = command.args.get(1).map_or("", |arg| arg.as_str()).parse()?;
+                        let tracer = Tracer::create(path, filter)?;
+                        let msg = format!("Tracing {} to {}", tracer.get_filter(), path);
+                        self.system.set_tracer(Some(tracer));
+                        Ok(msg)
+                    }
+                    _ => Err("Usage: trace FILE [pid=N,addrs=START..END] | trace off".to_string()),
+                }
+            }
+            Timeline => {
+                match command.args.first().map(|arg| arg.as_str()) {
+                    Some("on") => {
+                        if self.system.get_timeline().is_none() {
+                            // `Timeline` is the command here.
+                            self.system.set_timeline(Some(Default::default()));
+                        }
+                        Ok("Timeline on".to_string())
+                    }
+                    Some("off") => {
+                        self.system.set_timeline(None);
+                        Ok("Timeline off".to_string())
+                    }
+                    Some("clear") => {
+                        self.get_timeline()?.clear();
+                        Ok("Cleared the timeline".to_string())
+                    }
+                    Some(path) => {
+                        let timeline = self.get_timeline()?;
+                        let num_events = timeline.get_events().len();
+                        fs::write(path, timeline.to_chrome_trace())
+                            .map_err(|err| format!("{}: {}", path, err))?;
+                        Ok(format!("Wrote {} events to {}", num_events, path))
+                    }
+                    None => {
+                        Err("Usage: timeline on | timeline off | timeline clear | timeline FILE"
+                            .to_string())
+                    }
+                }
+            }
+            Gantt => {
+                let width = match command.args.first() {
+                    Some(arg) => parse_num(arg)?,
+                    None => GANTT_WIDTH,
+                };
+                Ok(self.get_timeline()?.to_gantt_chart(width))
+            }
+            Record => {
+                if command.args.len() < 2 {
+                    return Err("Usage: record LOG FILE...".to_string());
+                }
+                let path = &command.args[0];
+                let log = self.system.record(&command.args[1..])?;
+                fs::write(path, log.to_string()).map_err(|err| format!("{}: {}", path, err))?;
+                Ok(format!("Recorded {} slice(s) to {}", log.slices.len(), path))
+            }
+            Replay => {
+                let path = command.args.first().ok_or("Usage: replay LOG".to_string())?;
+                let contents = fs::read_to_string(path)
+                    .map_err(|err| format!("{}: {}", path, err))?;
+                let log = RunLog::parse(&contents).map_err(|err| format!("{}: {}", path, err))?;
+                self.system.replay(&log)
+            }
+            Save => {
+                let file_name = command.args.first().ok_or("Usage: save FILE".to_string())?;
+                let num_procs = self.system.save(file_name)?;
+                Ok(format!("Saved {} process(es) to {}", num_procs, file_name))
+            }
+            Restore => {
+                let file_name = command.args.first().ok_or("Usage: restore FILE".to_string())?;
+                let proc_ids = self.system.restore(file_name)?;
+                // Like `exec FILE &`, but on the consoles they were saved with, as a process
+                // may have been saved waiting for input.
+                for proc_id in &proc_ids {
+                    self.system.start(*proc_id, true)?;
+                }
+                let pids = proc_ids.iter()
+                    .map(|proc_id| proc_id.to_string())
+                    .collect::<Vec<String>>();
+                Ok(format!("Restored {} process(es): {}", pids.len(), pids.join(", ")))
+            }
+            Migrate => {
+                if command.args.len() != 2 {
+                    return Err("Usage: migrate PID PORT".to_string());
+                }
+                let pid = command.args[0]
+                    .parse::<u16>()
+                    .map_err(|_| format!("{}: not a process ID", command.args[0]))?;
+                let port = command.args[1]
+                    .parse::<u16>()
+                    .map_err(|_| format!("{}: not a port", command.args[1]))?;
+                let mut stream = TcpStream::connect(("127.0.0.1", port))
+                    .map_err(|err| err.to_string())?;
+                stream.set_nodelay(true).map_err(|err| err.to_string())?;
+                let remote_pid = self.system.migrate(pid, &mut stream)?;
+                Ok(format!("Migrated process {} to port {}, where it is process {}",
+                           pid,
+                           port,
+                           remote_pid))
+            }
+            Accept => {
+                let port = command.args.first().ok_or("Usage: accept PORT".to_string())?;
+                let port = port.parse::<u16>().map_err(|_| format!("{}: not a port", port))?;
+                self.accept(port)
+            }
             Kill => {
                 let pid = &command.args[0];
                 let pid = pid.parse::<u16>().unwrap();
                 self.system.kill(pid)?;
                 Ok(format!("Killed process {}", pid))
             }
+            DumpRam => {
+                if command.args.len() != 2 {
+                    return Err("Usage: dump ADDR LEN".to_string());
+                }
+                let addr = parse_num(&command.args[0])?;
+                let len = parse_num(&command.args[1])?;
+                self.system.dump_ram(addr, len)
+            }
             _ => unreachable!(),
         }
     }
 
-    fn get_user_cmd(&mut self) -> CommandWithArgs {
+    /// Returns the next command, or `None` at the end of the input.
+    fn get_user_cmd(&mut self) -> Option<CommandWithArgs> {
         let mut optional_cmd = None;
         while optional_cmd.is_none() {
-            self.write_prompt();
-            let line = self.read_line();
+            let prompt = self.prompt.to_owned();
+            let line = self.read_prompted_line(&prompt)?;
             optional_cmd = if line.is_empty() {
                 None
             } else {
@@ -83,23 +311,97 @@ impl Shell {
                 }
             }
         }
-        optional_cmd.unwrap()
+        optional_cmd
     }
 
-    fn read_line(&mut self) -> String {
-        io_utils::read_line(&mut self.reader)
+    /// Runs `file_name` on the console of the shell.
+    fn exec(&mut self,
+            file_name: &str,
+            use_term: bool)
+            -> Result<thread::JoinHandle<ExecResult>, String> {
+        let proc_id = self.system.load_file(file_name)?;
+        self.system.attach_console(proc_id, self.console.clone())?;
+        self.system.start(proc_id, use_term)
     }
 
-    fn write_prompt(&mut self) {
-        let prompt = self.prompt.to_owned();
-        self.write(&prompt);
+    fn get_timeline(&self) -> Result<&Timeline, String> {
+        self.system
+            .get_timeline()
+            .ok_or("The timeline is off; turn it on with `timeline on`.".to_string())
+    }
+
+    /// Loads `file_name` paused under a debugger, on the console of the shell.
+    fn debug(&mut self, file_name: Option<&String>) -> Result<DebugSession, String> {
+        let file_name = file_name.ok_or("Usage: debug FILE".to_string())?;
+        let dbg = self.system.debug(file_name)?;
+        self.system.attach_console(dbg.get_proc_id(), self.console.clone())?;
+        Ok(DebugSession::new(dbg))
+    }
+
+    /// Loads `file_name` paused and serves it to one GDB connection on the local `port`.
+    fn gdb_server(&mut self, port: u16, file_name: &str) -> Result<String, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|err| err.to_string())?;
+        let dbg = self.system.debug(file_name)?;
+        let proc_id = dbg.get_proc_id();
+        self.system.attach_console(proc_id, self.console.clone())?;
+        let mut stub = GdbStub::new(&self.system, dbg)?;
+        self.write_ln(&format!("Process {} waiting for GDB on port {}", proc_id, port));
+        let (stream, _) = listener.accept().map_err(|err| err.to_string())?;
+        stream.set_nodelay(true).map_err(|err| err.to_string())?;
+        stub.serve(&mut self.system, stream)?;
+        Ok("GDB disconnected".to_string())
+    }
+
+    /// Waits for a process migrated to `port`, then runs it like `exec FILE &`, but reading the
+    /// console it came with, as it may have been waiting for input.
+    fn accept(&mut self, port: u16) -> Result<String, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|err| err.to_string())?;
+        self.write_ln(&format!("Waiting for a process on port {}", port));
+        let (mut stream, _) = listener.accept().map_err(|err| err.to_string())?;
+        stream.set_nodelay(true).map_err(|err| err.to_string())?;
+        let proc_id = self.system.receive(&mut stream)?;
+        self.system.start(proc_id, true)?;
+        Ok(format!("Received process {}", proc_id))
+    }
+
+    /// Writes `prompt` and reads a line, echoing it when it comes from the script. Returns
+    /// `None` at the end of the input.
+    fn read_prompted_line(&mut self, prompt: &str) -> Option<String> {
+        self.write(prompt);
+        let line = match self.read_line() {
+            Some(line) => line,
+            None => {
+                self.write_ln("");
+                return None;
+            }
+        };
+        if self.script.is_some() {
+            self.write_ln(&line);
+        }
+        Some(line)
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        match self.script {
+            Some(ref mut script) => io_utils::try_read_line(script),
+            None => self.console.read_line(),
+        }
     }
 
     fn write(&mut self, msg: &str) {
-        io_utils::write(&mut self.writer, msg)
+        self.console.write(msg)
     }
 
     fn write_ln(&mut self, msg: &str) {
-        io_utils::write_ln(&mut self.writer, msg)
+        self.console.write_ln(msg)
     }
 }
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+pub fn parse_num(s: &str) -> Result<usize, String> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse::<usize>(),
+    };
+    result.map_err(|_| format!("{}: not a number", s))
+}
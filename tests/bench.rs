@@ -0,0 +1,83 @@
+//! Measures how many instructions per second the executor runs. The benchmarks take a while in
+//! debug builds, so they are ignored by default; run them with
+//! `cargo test --release --test bench -- --ignored --nocapture`.
+
+extern crate snoss;
+
+mod common;
+
+use std::time::Instant;
+use snoss::{Config, CycleCosts, ExecResult};
+use common::TestFs;
+
+/// Counts `r0` up to 0x4000, 64 times over: arithmetic and jumps only.
+fn alu_loop() -> Vec<u8> {
+    [[0x12, 0x01, 0x00, 0x01], // r1 = 1
+     [0x12, 0x02, 0x00, 0x00], // r2 = 0
+     [0x12, 0x05, 0x40, 0x00], // r5 = 0x4000
+     [0x12, 0x03, 0x00, 0x40], // r3 = 64
+     [0x12, 0x00, 0x00, 0x00], // outer: r0 = 0
+     [0x21, 0x00, 0x01, 0x00], // inner: r0 = r0 + r1
+     [0x25, 0x00, 0x05, 0x04], // r4 = r0 == r5
+     [0x32, 0x00, 0x24, 0x04], // if r4 goto next
+     [0x31, 0x00, 0x14, 0x00], // goto inner
+     [0x22, 0x03, 0x01, 0x03], // next: r3 = r3 - r1
+     [0x25, 0x03, 0x02, 0x04], // r4 = r3 == r2
+     [0x32, 0x00, 0x34, 0x04], // if r4 goto end
+     [0x31, 0x00, 0x10, 0x00], // goto outer
+     [0xff, 0x00, 0x00, 0x00]] // end
+        .concat()
+}
+
+/// Like `alu_loop`, but goes through data address 0 at every step.
+fn mem_loop() -> Vec<u8> {
+    [[0x12, 0x01, 0x00, 0x01], // r1 = 1
+     [0x12, 0x02, 0x00, 0x00], // r2 = 0
+     [0x12, 0x05, 0x40, 0x00], // r5 = 0x4000
+     [0x12, 0x03, 0x00, 0x20], // r3 = 32
+     [0x12, 0x00, 0x00, 0x00], // outer: r0 = 0
+     [0x21, 0x00, 0x01, 0x00], // inner: r0 = r0 + r1
+     [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+     [0x11, 0x04, 0x00, 0x00], // r4 = [0x0000]
+     [0x25, 0x04, 0x05, 0x04], // r4 = r4 == r5
+     [0x32, 0x00, 0x2c, 0x04], // if r4 goto next
+     [0x31, 0x00, 0x14, 0x00], // goto inner
+     [0x22, 0x03, 0x01, 0x03], // next: r3 = r3 - r1
+     [0x25, 0x03, 0x02, 0x04], // r4 = r3 == r2
+     [0x32, 0x00, 0x3c, 0x04], // if r4 goto end
+     [0x31, 0x00, 0x10, 0x00], // goto outer
+     [0xff, 0x00, 0x00, 0x00]] // end
+        .concat()
+}
+
+/// Runs `program` to completion on the calling thread, as `exec` does without `&`, and prints
+/// how fast it went.
+fn bench(name: &str, program: Vec<u8>) {
+    let fs = TestFs::new(&format!("bench-{}", name));
+    // At 1 cycle per instruction, the cycles of the process are the instructions it ran.
+    let cycle_costs = "Load:1,Store:1,Goto:1,GotoIf:1".parse::<CycleCosts>().unwrap();
+    let mut sys = fs.boot(Config { cycle_costs, ..Config::default() });
+    let proc_id = sys.load(name, &program).unwrap();
+    let start = Instant::now();
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    let elapsed = start.elapsed();
+    let num_instrs = sys.get_exited_procs().last().unwrap().cycles;
+    let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+    println!("{}: {} instructions, {:.3} s, {:.0} instructions/s",
+             name,
+             num_instrs,
+             secs,
+             num_instrs as f64 / secs);
+}
+
+#[test]
+#[ignore]
+fn alu_instructions_per_second() {
+    bench("alu-loop", alu_loop());
+}
+
+#[test]
+#[ignore]
+fn mem_instructions_per_second() {
+    bench("mem-loop", mem_loop());
+}
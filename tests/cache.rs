@@ -0,0 +1,84 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{CacheStats, Config, Console, ExecResult, System};
+use common::{store_a_then, TestFs};
+
+/// Stores 'A' at data address 0 and loads it back, in one line of 16 bytes of code.
+fn program() -> Vec<u8> {
+    store_a_then(&[[0x11, 0x01, 0x00, 0x00]]) // r1 = [0x0000]
+}
+
+fn config(fs: &TestFs) -> Config {
+    Config {
+        cache_len: 256,
+        cache_line_len: 16,
+        cache_ways: 2,
+        ..fs.config()
+    }
+}
+
+#[test]
+fn lookups_are_counted_per_process_and_reported() {
+    let fs = TestFs::new("cache-counted");
+    let mut sys = fs.boot(config(&fs));
+    let (console, out) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    let proc_id = sys.load("prog", &program()).unwrap();
+    sys.step(proc_id).unwrap();
+    sys.step(proc_id).unwrap();
+    // The first fetch and the store miss.
+    assert_eq!(sys.get_proc_info(proc_id).unwrap().cache_stats,
+               CacheStats { hits: 1, misses: 2 });
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    let stats = CacheStats { hits: 4, misses: 2 };
+    assert_eq!(sys.get_exited_procs()[0].cache_stats, stats);
+    assert_eq!(sys.get_cache_stats(), Some(stats));
+    assert_eq!(out.get_string(),
+               "\nProc 0 (prog): cache: 4 hits, 2 misses (66.7% hits)\n");
+}
+
+#[test]
+fn flushing_empties_the_cache() {
+    let fs = TestFs::new("cache-flushed");
+    let mut sys = fs.boot(config(&fs));
+    let first = sys.load("prog", &program()).unwrap();
+    let second = sys.load("prog", &program()).unwrap();
+    sys.step(first).unwrap();
+    sys.step(second).unwrap();
+    assert_eq!(sys.get_proc_info(second).unwrap().cache_stats,
+               CacheStats { hits: 0, misses: 1 });
+    sys.flush_cache().unwrap();
+    sys.step(first).unwrap();
+    assert_eq!(sys.get_proc_info(first).unwrap().cache_stats,
+               CacheStats { hits: 0, misses: 3 });
+}
+
+#[test]
+fn cache_parameters_are_checked() {
+    let fs = TestFs::new("cache-checked");
+    let err = |config: Config| System::init(config).err().unwrap();
+    assert_eq!(err(Config { cache_len: 48, ..config(&fs) }),
+               "cache_len must be a power of two, not 48");
+    assert_eq!(err(Config { cache_len: 16, ..config(&fs) }),
+               "cache_len must hold a set of 2 lines of 16 bytes, not 16");
+    assert!(Config::default().set("cache_policy", "mru").is_err());
+    let mut sys = fs.boot(Config { cache_len: 0, ..config(&fs) });
+    assert_eq!(sys.get_cache_stats(), None);
+    assert!(sys.flush_cache().is_err());
+}
+
+#[test]
+fn the_cache_leaves_paging_unchanged() {
+    let paging = |name: &str, cache_len: usize| {
+        let fs = TestFs::new(name);
+        let mut sys = fs.boot(Config { cache_len, ..config(&fs) });
+        for _ in 0..2 {
+            let proc_id = sys.load("prog", &program()).unwrap();
+            assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+        }
+        (sys.vm_stats(), sys.simulate_replacers(None).unwrap())
+    };
+    assert_eq!(paging("cache-paging-on", 256), paging("cache-paging-off", 0));
+}
@@ -0,0 +1,78 @@
+//! Helpers shared by the integration tests. Every suite uses only some of them.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use snoss::{Config, Console, System};
+
+/// A host directory mounted as the file system of the machines of a test, removed along with
+/// its files when dropped.
+pub struct TestFs {
+    path: PathBuf,
+}
+
+impl TestFs {
+    /// Creates an empty directory for the test `name`, which no other test of any suite may use.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("snoss-{}", name));
+        // Left over by a run that was interrupted.
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestFs { path }
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the file `file_name` of the file system.
+    pub fn write(&self, file_name: &str, contents: &[u8]) {
+        fs::write(self.path.join(file_name), contents).unwrap();
+    }
+
+    /// Copies the program `file_name` of the repository's `fs/`.
+    pub fn copy(&self, file_name: &str) {
+        fs::copy(Path::new("fs").join(file_name), self.path.join(file_name)).unwrap();
+    }
+
+    /// Returns the default parameters, with this directory as the file system.
+    pub fn config(&self) -> Config {
+        Config { fs_path: self.path.clone(), ..Config::default() }
+    }
+
+    /// Boots a machine with the parameters of `config` on this file system, whose console
+    /// has no input and keeps its output in memory.
+    pub fn boot(&self, config: Config) -> System {
+        let mut sys = System::init(Config { fs_path: self.path.clone(), ..config }).unwrap();
+        sys.set_console(Console::in_memory(vec![]).0);
+        sys
+    }
+}
+
+/// Creates the file system of the test `name` and boots a machine on it, as `TestFs::boot`
+/// does.
+pub fn boot(name: &str, config: Config) -> (TestFs, System) {
+    let fs = TestFs::new(name);
+    let sys = fs.boot(config);
+    (fs, sys)
+}
+
+impl Drop for TestFs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Joins instructions into a program.
+pub fn assemble(instrs: &[[u8; 4]]) -> Vec<u8> {
+    instrs.concat()
+}
+
+/// Stores 'A' at data address 0, then runs `instrs` and exits.
+pub fn store_a_then(instrs: &[[u8; 4]]) -> Vec<u8> {
+    let store_a = [[0x12, 0x00, 0x00, 0x41], // r0 = 'A'
+                   [0x13, 0x00, 0x00, 0x00]]; // [0x0000] = r0
+    assemble(&[&store_a[..], instrs, &[[0xff, 0x00, 0x00, 0x00]]].concat())
+}
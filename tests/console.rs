@@ -0,0 +1,50 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Console, ExecResult, Shell};
+use common::TestFs;
+
+fn test_fs(name: &str) -> TestFs {
+    let fs = TestFs::new(&format!("console-{}", name));
+    fs.copy("hello-world.sno");
+    fs.copy("temp-convert.sno");
+    fs
+}
+
+#[test]
+fn shell_runs_commands_of_its_console() {
+    let fs = test_fs("shell");
+    let sys = fs.boot(Config::default());
+    let (console, output) = Console::in_memory(b"exec hello-world.sno\nexit\n".to_vec());
+    let mut shell = Shell::with_console(sys, "> ".to_string(), console);
+    shell.start().unwrap();
+    assert_eq!(output.get_string(), "> Hello, world!\n> ");
+}
+
+#[test]
+fn processes_use_the_console_attached_to_them() {
+    let fs = test_fs("attached");
+    let mut sys = fs.boot(Config::default());
+    let (default_console, default_output) = Console::in_memory(vec![]);
+    sys.set_console(default_console);
+    let hello_id = sys.load_file("hello-world.sno").unwrap();
+    let convert_id = sys.load_file("temp-convert.sno").unwrap();
+    let (console, output) = Console::in_memory(b"212\n".to_vec());
+    sys.attach_console(convert_id, console).unwrap();
+
+    assert_eq!(sys.run(convert_id), Ok(ExecResult::Exit));
+    assert_eq!(sys.run(hello_id), Ok(ExecResult::Exit));
+    assert_eq!(output.get_string(), "00100");
+    assert_eq!(default_output.get_string(), "Hello, world!\n");
+}
+
+#[test]
+fn processes_started_without_the_terminal_cannot_read() {
+    let fs = test_fs("detached");
+    let mut sys = fs.boot(Config::default());
+    sys.set_console(Console::in_memory(b"212\n".to_vec()).0);
+    let proc_id = sys.load_file("temp-convert.sno").unwrap();
+    let handle = sys.start(proc_id, false).unwrap();
+    assert_eq!(handle.join().unwrap(), ExecResult::AccessErr);
+}
@@ -0,0 +1,70 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, CycleCosts, ExecResult, InstructionType, System};
+use common::{assemble, boot, TestFs};
+
+/// Divides 6 by 2 twice, loads three more constants, then exits.
+fn program() -> Vec<u8> {
+    assemble(&[[0x12, 0x00, 0x00, 0x06], // r0 = 6
+               [0x12, 0x01, 0x00, 0x02], // r1 = 2
+               [0x24, 0x00, 0x01, 0x02], // r2 = r0 / r1
+               [0x24, 0x02, 0x01, 0x02], // r2 = r2 / r1
+               [0x12, 0x03, 0x00, 0x01], // r3 = 1
+               [0x12, 0x04, 0x00, 0x01], // r4 = 1
+               [0x12, 0x05, 0x00, 0x01], // r5 = 1
+               [0xff, 0x00, 0x00, 0x00]])
+}
+
+#[test]
+fn cycle_costs_are_parsed_over_the_defaults() {
+    let costs = "Divide:30, Multiply:7".parse::<CycleCosts>().unwrap();
+    assert_eq!(costs.get(InstructionType::Divide), 30);
+    assert_eq!(costs.get(InstructionType::Multiply), 7);
+    assert_eq!(costs.get(InstructionType::Add), 1);
+    assert_eq!(costs.to_string().parse::<CycleCosts>(), Ok(costs));
+    assert_eq!("Modulo:3".parse::<CycleCosts>(),
+               Err("Modulo: unknown instruction type".to_string()));
+    assert_eq!("Divide".parse::<CycleCosts>(),
+               Err("Divide: expected `TYPE:CYCLES`".to_string()));
+    assert_eq!("Goto:0".parse::<CycleCosts>(),
+               Err("Goto:0: an instruction takes at least 1 cycle".to_string()));
+}
+
+#[test]
+fn instructions_that_take_no_cycles_are_refused() {
+    let fs = TestFs::new("cycles-free");
+    let mut costs = CycleCosts::default();
+    costs.set(InstructionType::Goto, 0);
+    assert_eq!(System::init(Config { cycle_costs: costs, ..fs.config() }).err(),
+               Some("cycle_costs must be at least 1 for every instruction, not 0 for Goto"
+                   .to_string()));
+}
+
+#[test]
+fn processes_are_charged_the_cycles_of_their_instructions() {
+    let costs = "Divide:10,Exit:2".parse().unwrap();
+    let (fs, mut sys) = boot("cycles-charged", Config { cycle_costs: costs, ..Config::default() });
+    fs.write("prog", &program());
+    let proc_id = sys.load_file("prog").unwrap();
+    for _ in 0..3 {
+        sys.step(proc_id).unwrap();
+    }
+    assert_eq!(sys.get_proc_info(proc_id).unwrap().cycles, 12);
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    let exited = sys.get_exited_procs();
+    assert_eq!(exited.len(), 1);
+    assert_eq!((exited[0].id, exited[0].cycles), (proc_id, 27));
+    assert_eq!(sys.get_cpu_cycles(), 27);
+}
+
+#[test]
+fn slices_end_once_their_cycle_budget_is_spent() {
+    let (fs, mut sys) = boot("cycles-budget", Config { slice_cycles: 5, ..Config::default() });
+    fs.write("prog", &program());
+    let log = sys.record(&["prog".to_string()]).unwrap();
+    let num_instrs = log.slices.iter().map(|slice| slice.num_instrs).collect::<Vec<usize>>();
+    assert_eq!(num_instrs, vec![3, 1, 4]);
+    assert_eq!(log.slices.last().map(|slice| slice.result.as_str()), Some("exit"));
+}
@@ -0,0 +1,100 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Debugger, Executable, ExecResult, SectionKind, StopReason, Symbol, System};
+use common::{assemble, TestFs};
+
+/// Counts `r0` down from 3 to 0, storing it at data address 0 each time.
+fn countdown() -> Executable {
+    let code = assemble(&[[0x12, 0x00, 0x00, 0x03], // r0 = 3
+                          [0x12, 0x01, 0x00, 0x01], // r1 = 1
+                          [0x12, 0x02, 0x00, 0x00], // r2 = 0
+                          [0x22, 0x00, 0x01, 0x00], // loop: r0 = r0 - r1
+                          [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+                          [0x25, 0x00, 0x02, 0x03], // r3 = r0 == r2
+                          [0x32, 0x00, 0x20, 0x03], // if r3 goto end
+                          [0x31, 0x00, 0x0c, 0x00], // goto loop
+                          [0xff, 0x00, 0x00, 0x00]]); // end
+    let mut exe = Executable::new(code);
+    exe.data = vec![0x00, 0x03];
+    exe.symbols = vec![Symbol {
+                           name: "loop".to_string(),
+                           kind: SectionKind::Code,
+                           addr: 0x0c,
+                       },
+                       Symbol {
+                           name: "count".to_string(),
+                           kind: SectionKind::Data,
+                           addr: 0,
+                       }];
+    exe
+}
+
+fn debug(name: &str) -> (TestFs, System, Debugger) {
+    let fs = TestFs::new(&format!("debugger-{}", name));
+    fs.write("countdown", &countdown().to_bytes().unwrap());
+    let mut sys = fs.boot(Config::default());
+    let dbg = sys.debug("countdown").unwrap();
+    (fs, sys, dbg)
+}
+
+#[test]
+fn breakpoints_stop_before_their_instruction() {
+    let (_fs, mut sys, mut dbg) = debug("break");
+    assert_eq!(dbg.get_instr_ptr(&sys), Ok(0));
+    let loop_addr = dbg.resolve("loop", SectionKind::Code).unwrap();
+    dbg.add_breakpoint(loop_addr).unwrap();
+    assert!(dbg.add_breakpoint(0x0e).is_err());
+    for _ in 0..3 {
+        assert_eq!(dbg.cont(&mut sys), Ok(StopReason::Breakpoint(0x0c)));
+    }
+    assert_eq!(dbg.step(&mut sys, 2), Ok(StopReason::Stepped));
+    assert_eq!(dbg.get_instr_ptr(&sys), Ok(0x14));
+    assert!(dbg.remove_breakpoint(loop_addr));
+    assert_eq!(dbg.cont(&mut sys), Ok(StopReason::Exited(ExecResult::Exit)));
+    assert!(dbg.has_exited());
+    assert!(dbg.step(&mut sys, 1).is_err());
+}
+
+#[test]
+fn next_and_cont_stop_once_their_budget_is_spent() {
+    let (_fs, mut sys, mut dbg) = debug("budget");
+    dbg.set_max_steps(5);
+    assert_eq!(dbg.cont(&mut sys), Ok(StopReason::StepLimit(5)));
+    assert_eq!(dbg.get_instr_ptr(&sys), Ok(0x14));
+    dbg.step(&mut sys, 2).unwrap();
+    // The loop runs 9 more instructions before it falls through to the end.
+    assert_eq!(dbg.next(&mut sys), Ok(StopReason::StepLimit(5)));
+    dbg.set_max_steps(100);
+    assert_eq!(dbg.cont(&mut sys), Ok(StopReason::Exited(ExecResult::Exit)));
+}
+
+#[test]
+fn watchpoints_stop_after_the_word_changes() {
+    let (_fs, mut sys, mut dbg) = debug("watch");
+    let count = dbg.resolve("count", SectionKind::Data).unwrap();
+    dbg.add_watchpoint(&sys, count).unwrap();
+    let reason = StopReason::Watchpoint {
+        addr: 0,
+        old_val: 3,
+        new_val: 2,
+    };
+    assert_eq!(dbg.cont(&mut sys), Ok(reason));
+    assert_eq!(dbg.get_instr_ptr(&sys), Ok(0x14));
+}
+
+#[test]
+fn registers_and_memory_can_be_modified() {
+    let (_fs, mut sys, mut dbg) = debug("modify");
+    let pid = dbg.get_proc_id();
+    dbg.step(&mut sys, 3).unwrap();
+    // Counting down from 1 leaves the loop at once.
+    sys.set_reg(pid, 0, 1).unwrap();
+    assert!(sys.set_reg(pid, 6, 1).is_err());
+    assert_eq!(dbg.next(&mut sys), Ok(StopReason::Stepped));
+    assert_eq!(sys.get_proc_info(pid).unwrap().registers[0], 0);
+    sys.write_mem(pid, 1, &[0x2a]).unwrap();
+    assert_eq!(sys.read_mem(pid, 0, 2), Ok(vec![0x00, 0x2a]));
+    assert_eq!(dbg.step(&mut sys, 4), Ok(StopReason::Exited(ExecResult::Exit)));
+}
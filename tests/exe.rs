@@ -0,0 +1,130 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Console, DebugInfo, Executable, ExecResult, SectionKind, Symbol};
+use common::TestFs;
+
+/// `CharPrint` of the byte at data address `addr`.
+fn char_print(addr: u8) -> Vec<u8> {
+    vec![0x41, 0x00, addr, 0x00]
+}
+
+fn exit() -> Vec<u8> {
+    vec![0xff, 0x00, 0x00, 0x00]
+}
+
+#[test]
+fn executables_survive_a_round_trip() {
+    let mut exe = Executable::new([char_print(0), exit()].concat());
+    exe.entry = 4;
+    exe.data = b"data".to_vec();
+    exe.symbols = vec![Symbol {
+                           name: "main".to_string(),
+                           kind: SectionKind::Code,
+                           addr: 4,
+                       }];
+    exe.debug = Some(DebugInfo {
+        source_file_name: "main.s".to_string(),
+        lines: vec![(0, 1), (4, 2)],
+    });
+    let parsed = Executable::parse(&exe.to_bytes().unwrap()).unwrap();
+    assert_eq!(parsed, exe);
+    assert_eq!(parsed.get_symbol("main").map(|symbol| symbol.addr), Some(4));
+}
+
+#[test]
+fn legacy_files_are_bare_code() {
+    let code = [char_print(0), exit()].concat();
+    let exe = Executable::parse(&code).unwrap();
+    assert_eq!(exe.version, 0);
+    assert_eq!(exe.entry, 0);
+    assert_eq!(exe.code, code);
+    assert!(exe.data.is_empty());
+    assert!(Executable::parse(&[0x41, 0x00]).is_err());
+}
+
+#[test]
+fn bad_executables_are_rejected() {
+    let exe = Executable::new(exit());
+    let mut newer = exe.to_bytes().unwrap();
+    newer[5] = 2;
+    assert!(Executable::parse(&newer).is_err());
+    let bytes = exe.to_bytes().unwrap();
+    assert!(Executable::parse(&bytes[..bytes.len() - 1]).is_err());
+    let mut misaligned = exe.clone();
+    misaligned.entry = 2;
+    assert!(Executable::parse(&misaligned.to_bytes().unwrap()).is_err());
+}
+
+#[test]
+fn values_too_long_for_the_format_are_rejected() {
+    let mut exe = Executable::new(exit());
+    exe.symbols = vec![Symbol {
+                           name: "s".repeat(256),
+                           kind: SectionKind::Code,
+                           addr: 0,
+                       }];
+    assert!(exe.to_bytes().is_err());
+    exe.symbols.clear();
+    exe.debug = Some(DebugInfo {
+        source_file_name: "é".repeat(128),
+        lines: vec![],
+    });
+    assert!(exe.to_bytes().is_err());
+    exe.debug = None;
+    exe.data = vec![0; 0x10000];
+    assert_eq!(exe.to_bytes(),
+               Err("The Data section (65536 bytes at offset 32) does not fit in a 64 KiB \
+                    executable."
+                   .to_string()));
+}
+
+#[test]
+fn processes_start_at_the_entry_point_with_their_data_preloaded() {
+    let fs = TestFs::new("exe-entry");
+    let mut sys = fs.boot(Config::default());
+    let (console, output) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    // The first instruction would print "d".
+    let mut exe = Executable::new([char_print(0), char_print(1), exit()].concat());
+    exe.entry = 4;
+    exe.data = b"data".to_vec();
+    let proc_id = sys.load("entry", &exe.to_bytes().unwrap()).unwrap();
+    assert_eq!(sys.get_proc_info(proc_id).unwrap().instr_ptr, 4);
+    assert_eq!(sys.read_mem(proc_id, 0, 4), Ok(b"data".to_vec()));
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    assert_eq!(output.get_string(), "a");
+}
+
+#[test]
+fn programs_can_be_longer_than_256_instructions() {
+    let fs = TestFs::new("exe-long");
+    let mut sys = fs.boot(Config::default());
+    let (console, output) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    // Jump over 399 instructions that would print "x", past the old 1024-byte limit.
+    let mut code = vec![0x31, 0x06, 0x40, 0x00];
+    for _ in 1..400 {
+        code.extend(char_print(0));
+    }
+    code.extend(char_print(1));
+    code.extend(exit());
+    let mut exe = Executable::new(code);
+    exe.data = b"xy".to_vec();
+    let proc_id = sys.load("long", &exe.to_bytes().unwrap()).unwrap();
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    assert_eq!(output.get_string(), "y");
+}
+
+#[test]
+fn programs_past_the_address_space_are_rejected() {
+    let fs = TestFs::new("exe-too-long");
+    let mut sys = fs.boot(Config::default());
+    let too_long = vec![0xff; 0x10004];
+    assert!(sys.load("too-long", &too_long).is_err());
+    // Addressable, but leaves no room for the stack and the heap.
+    let no_room = vec![0xff; 0xff00];
+    assert!(sys.load("no-room", &no_room).is_err());
+    assert!(sys.get_proc_ids().is_empty());
+}
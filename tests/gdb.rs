@@ -0,0 +1,157 @@
+extern crate snoss;
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use snoss::{Config, GdbStub};
+use common::{assemble, TestFs};
+
+/// Adds 1 to `r0` and stores it at data address 0, twice, then exits.
+fn program() -> Vec<u8> {
+    assemble(&[[0x12, 0x01, 0x00, 0x01], // r1 = 1
+               [0x21, 0x00, 0x01, 0x00], // r0 = r0 + r1
+               [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+               [0x21, 0x00, 0x01, 0x00], // r0 = r0 + r1
+               [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+               [0xff, 0x00, 0x00, 0x00]])
+}
+
+/// Serves `program` to one connection, returning the address to connect to.
+fn serve(name: &str, program: Vec<u8>) -> (String, thread::JoinHandle<()>) {
+    let fs = TestFs::new(&format!("gdb-{}", name));
+    fs.write("prog", &program);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let mut sys = fs.boot(Config::default());
+        let dbg = sys.debug("prog").unwrap();
+        let mut stub = GdbStub::new(&sys, dbg).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        stub.serve(&mut sys, stream).unwrap();
+    });
+    (addr, handle)
+}
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn connect(addr: String) -> Self {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        Client { stream }
+    }
+
+    /// Sends the packet `data` and returns the data of the reply.
+    fn send(&mut self, data: &str) -> String {
+        self.send_packet(data);
+        self.read_reply()
+    }
+
+    fn send_packet(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.stream, "${}#{:02x}", data, sum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+    }
+
+    fn read_reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = vec![];
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => reply.push(byte),
+            }
+        }
+        let mut checksum = [0; 2];
+        self.stream.read_exact(&mut checksum).unwrap();
+        let sum = reply.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        assert_eq!(String::from_utf8_lossy(&checksum), format!("{:02x}", sum));
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+#[test]
+fn registers_memory_and_breakpoints_are_served() {
+    let (addr, handle) = serve("session", program());
+    let mut client = Client::connect(addr);
+    assert!(client.send("qSupported:xmlRegisters=i386").contains("qXfer:features:read+"));
+    let xml = client.send("qXfer:features:read:target.xml:0,1000");
+    assert!(xml.starts_with("l<?xml"));
+    assert!(xml.contains("<reg name=\"ip\" bitsize=\"16\" type=\"code_ptr\" regnum=\"6\"/>"));
+    assert_eq!(client.send("?"), "S05");
+    assert_eq!(client.send("g"), "0000".repeat(7));
+    assert_eq!(client.send("m0,4"), "12010001");
+    assert_eq!(client.send("m10000,2"), "0000");
+
+    assert_eq!(client.send("P0=0029"), "OK");
+    // Neither wraps around to register 0.
+    assert_eq!(client.send("P100=0001"), "E01");
+    assert_eq!(client.send("P0=10029"), "E01");
+    assert_eq!(client.send("Z0,c,4"), "OK");
+    assert_eq!(client.send("c"), "S05");
+    assert_eq!(client.send("p6"), "000c");
+    assert_eq!(client.send("m10000,2"), "002a");
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p0"), "002b");
+
+    assert_eq!(client.send("M10000,2:0100"), "OK");
+    assert_eq!(client.send("Z2,10000,2"), "OK");
+    assert_eq!(client.send("c"), "T05watch:10000;");
+    assert_eq!(client.send("m10000,2"), "002b");
+    assert_eq!(client.send("M0,4:00000000"), "E01");
+    assert_eq!(client.send("vMustReplyEmpty"), "");
+    assert_eq!(client.send("c"), "W00");
+    write!(client.stream, "$k#6b").unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn malformed_packets_are_refused() {
+    let (addr, handle) = serve("malformed", program());
+    let mut client = Client::connect(addr);
+    assert_eq!(client.send(""), "");
+    assert_eq!(client.send("\u{e9}"), "");
+    assert_eq!(client.send("M10000,2:a\u{e9}a"), "E01");
+    assert_eq!(client.send("M10000,2:012"), "E01");
+    assert_eq!(client.send("m10000,2"), "0000");
+    write!(client.stream, "$k#6b").unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn a_running_process_can_be_interrupted() {
+    let (addr, handle) = serve("interrupted", assemble(&[[0x31, 0x00, 0x00, 0x00]]));
+    let mut client = Client::connect(addr);
+    client.send_packet("c");
+    thread::sleep(Duration::from_millis(50));
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(client.read_reply(), "S02");
+    assert_eq!(client.send("p6"), "0000");
+    write!(client.stream, "$k#6b").unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn segmentation_faults_stop_with_a_signal() {
+    let program = assemble(&[[0x51, 0x00, 0x00, 0x01], // r1 = sbrk(r0)
+                             [0x41, 0x00, 0x80, 0x00], // print [0x0080]
+                             [0xff, 0x00, 0x00, 0x00]]);
+    let (addr, handle) = serve("segfault", program);
+    let mut client = Client::connect(addr);
+    assert_eq!(client.send("c"), "S0b");
+    write!(client.stream, "$k#6b").unwrap();
+    handle.join().unwrap();
+}
@@ -0,0 +1,112 @@
+extern crate snoss;
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use snoss::{Config, Console, ExecResult};
+use common::{boot, store_a_then};
+
+/// Stores 'A' at data address 0, prints it twice, then exits.
+fn program() -> Vec<u8> {
+    store_a_then(&[[0x41, 0x00, 0x01, 0x00], // print [0x0001]
+                   [0x41, 0x00, 0x01, 0x00]]) // print [0x0001]
+}
+
+/// The result of receiving a process and what it printed.
+type Received = (Result<u16, String>, String);
+
+/// Receives one process on a system booted with `config`, which already runs a process, then
+/// runs it. Returns the address to connect to.
+fn receiver(name: &'static str, config: Config) -> (String, thread::JoinHandle<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let (_fs, mut sys) = boot(name, config);
+        sys.load("other", &program()).unwrap();
+        let (console, out) = Console::in_memory(vec![]);
+        sys.set_console(console);
+        let (mut stream, _) = listener.accept().unwrap();
+        let result = sys.receive(&mut stream);
+        if let Ok(proc_id) = result {
+            assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+        }
+        (result, out.get_string())
+    });
+    (addr, handle)
+}
+
+#[test]
+fn migrated_processes_resume_on_the_other_system() {
+    let (addr, handle) = receiver("migration-to", Config::default());
+    let (_fs, mut sys) = boot("migration-from", Config::default());
+    let (console, out) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    let proc_id = sys.load("prog", &program()).unwrap();
+    let console = sys.get_console().with_buffered(b"unread".to_vec());
+    sys.attach_console(proc_id, console).unwrap();
+    for _ in 0..3 {
+        sys.step(proc_id).unwrap();
+    }
+    let snapshot = sys.checkpoint(proc_id).unwrap();
+    assert_eq!(snapshot.procs[0].instr_ptr, 0x0c);
+    assert_eq!(snapshot.procs[0].console_input, Some(b"unread".to_vec()));
+    let mut stream = TcpStream::connect(addr).unwrap();
+    assert_eq!(sys.migrate(proc_id, &mut stream), Ok(1));
+    assert_eq!(sys.get_proc_ids(), vec![]);
+    assert_eq!(handle.join().unwrap(), (Ok(1), "A".to_string()));
+    assert_eq!(out.get_string(), "A");
+}
+
+#[test]
+fn refused_processes_stay_where_they_were() {
+    let config = Config { num_registers: 4, ..Config::default() };
+    let (addr, handle) = receiver("migration-refused", config);
+    let (_fs, mut sys) = boot("migration-kept", Config::default());
+    let proc_id = sys.load("prog", &program()).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let err = sys.migrate(proc_id, &mut stream).unwrap_err();
+    assert!(err.starts_with("The receiver refused the process: Saved with 6 registers"),
+            "{}",
+            err);
+    assert!(handle.join().unwrap().0.is_err());
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+}
+
+#[test]
+fn processes_resume_when_the_receiver_hangs_up_without_answering() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut len = [0; 4];
+        stream.read_exact(&mut len).unwrap();
+    });
+    let (_fs, mut sys) = boot("migration-hung-up", Config::default());
+    let proc_id = sys.load("prog", &program()).unwrap();
+    let handle_proc = sys.start(proc_id, true).unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let err = sys.migrate(proc_id, &mut stream).unwrap_err();
+    assert!(err.starts_with("No answer from the receiver"), "{}", err);
+    handle.join().unwrap();
+    assert_eq!(handle_proc.join().unwrap(), ExecResult::Exit);
+}
+
+#[test]
+fn unconfirmed_processes_are_freed_by_the_receiver() {
+    let (addr, handle) = receiver("migration-unconfirmed", Config::default());
+    let (_fs, mut sys) = boot("migration-unconfirming", Config::default());
+    let proc_id = sys.load("prog", &program()).unwrap();
+    let bytes = sys.checkpoint(proc_id).unwrap().to_bytes().unwrap();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(&(bytes.len() as u32).to_be_bytes()).unwrap();
+    stream.write_all(&bytes).unwrap();
+    let mut reply = [0; 5];
+    stream.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"ok 1\n");
+    drop(stream);
+    let (result, out) = handle.join().unwrap();
+    assert!(result.unwrap_err().starts_with("The sender did not confirm the migration"));
+    assert_eq!(out, "");
+}
@@ -0,0 +1,90 @@
+//! Runs every program of `fs/` on a headless machine and compares what it prints with
+//! `tests/programs/NAME.out`, feeding it `tests/programs/NAME.in` if there is one.
+//!
+//! After changing a program on purpose, regenerate its expected output with
+//! `BLESS=1 cargo test --test programs`.
+
+extern crate snoss;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use snoss::{Config, Console, ExecResult};
+use common::TestFs;
+
+/// Programs still running after this many instructions are stopped.
+const MAX_STEPS: usize = 10_000;
+
+/// Runs `program` and returns what it printed, followed by how it ended.
+fn run(name: &str, program: &[u8], stdin: Vec<u8>) -> String {
+    // Every machine gets a mount of its own, so that tests do not share files.
+    let fs = TestFs::new(&format!("programs-{}", name));
+    let mut sys = fs.boot(Config::default());
+    let (console, output) = Console::in_memory(stdin);
+    sys.set_console(console);
+
+    let proc_id = match sys.load(name, program) {
+        Ok(proc_id) => proc_id,
+        Err(err) => return format!("--- not loaded: {}\n", err),
+    };
+    let mut result = ExecResult::Success;
+    let mut num_steps = 0;
+    while result == ExecResult::Success && num_steps < MAX_STEPS {
+        result = sys.step(proc_id).unwrap();
+        num_steps += 1;
+    }
+    let end = if result == ExecResult::Success {
+        sys.kill(proc_id).unwrap();
+        format!("still running after {} instructions", MAX_STEPS)
+    } else {
+        format!("{:?} (exit code {})", result, result.get_exit_code())
+    };
+
+    format!("{}\n--- {}\n", output.get_string(), end)
+}
+
+fn get_programs() -> Vec<PathBuf> {
+    let mut programs = fs::read_dir("fs")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sno"))
+        .collect::<Vec<PathBuf>>();
+    programs.sort();
+    programs
+}
+
+fn read_or_empty(path: &Path) -> Vec<u8> {
+    fs::read(path).unwrap_or(vec![])
+}
+
+#[test]
+fn programs_print_expected_output() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = vec![];
+    for program in get_programs() {
+        let name = program.file_stem().unwrap().to_str().unwrap().to_string();
+        let golden_path = Path::new("tests/programs").join(format!("{}.out", name));
+        let stdin = read_or_empty(&Path::new("tests/programs").join(format!("{}.in", name)));
+        let actual = run(&name, &fs::read(&program).unwrap(), stdin);
+        if bless {
+            fs::write(&golden_path, &actual).unwrap();
+            continue;
+        }
+        let expected = String::from_utf8(read_or_empty(&golden_path)).unwrap();
+        if actual != expected {
+            failures.push(format!("{}:\n--- expected ({})\n{}--- actual\n{}",
+                                  name,
+                                  golden_path.display(),
+                                  expected,
+                                  actual));
+        }
+    }
+    if !failures.is_empty() {
+        panic!("{} program(s) did not print the expected output (rerun with BLESS=1 to \
+                accept the new output):\n\n{}",
+               failures.len(),
+               failures.join("\n"));
+    }
+}
@@ -0,0 +1,125 @@
+extern crate snoss;
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+use snoss::{Config, Console, RunLog, System};
+use common::{assemble, TestFs};
+
+/// Reads a byte to data address 0, prints it, then counts `r0` up to 0x1000.
+fn counter() -> Vec<u8> {
+    assemble(&[[0x42, 0x00, 0x00, 0x00], // [0x0000] = a byte of input
+               [0x41, 0x00, 0x00, 0x00], // print [0x0000]
+               [0x12, 0x01, 0x00, 0x01], // r1 = 1
+               [0x12, 0x02, 0x10, 0x00], // r2 = 0x1000
+               [0x21, 0x00, 0x01, 0x00], // loop: r0 = r0 + r1
+               [0x25, 0x00, 0x02, 0x03], // r3 = r0 == r2
+               [0x32, 0x00, 0x20, 0x03], // if r3 goto end
+               [0x31, 0x00, 0x10, 0x00], // goto loop
+               [0xff, 0x00, 0x00, 0x00]]) // end
+}
+
+fn test_fs(name: &str) -> TestFs {
+    let fs = TestFs::new(&format!("replay-{}", name));
+    fs.write("counter", &counter());
+    fs
+}
+
+fn boot(fs: &TestFs, input: &[u8]) -> System {
+    let mut sys = fs.boot(Config::default());
+    sys.set_console(Console::in_memory(input.to_vec()).0);
+    sys
+}
+
+fn record(fs: &TestFs) -> RunLog {
+    let mut sys = boot(fs, b"ab");
+    let log = sys.record(&["counter".to_string(), "counter".to_string()]).unwrap();
+    assert_eq!(log.input, b"ab");
+    assert_eq!(RunLog::parse(&log.to_string()), Ok(log.clone()));
+    log
+}
+
+#[test]
+fn replays_run_the_recorded_slices_with_the_recorded_input() {
+    let fs = test_fs("same");
+    let log = record(&fs);
+    // No input this time: it comes from the log.
+    let mut sys = boot(&fs, b"");
+    let (console, out) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    assert_eq!(sys.replay(&log),
+               Ok(format!("Replayed {} slice(s) of 2 program(s)", log.slices.len())));
+    assert_eq!(out.get_string(), "ab");
+}
+
+#[test]
+fn the_first_divergence_is_reported() {
+    let fs = test_fs("diverged");
+    let mut log = record(&fs);
+    log.slices[1].registers[0] ^= 1;
+    let err = boot(&fs, b"").replay(&log).unwrap_err();
+    assert!(err.starts_with("Diverged at slice 1:\n  recorded: "), "{}", err);
+
+    let mut log = record(&fs);
+    log.slices.pop();
+    let err = boot(&fs, b"").replay(&log).unwrap_err();
+    assert_eq!(err, "Diverged at the end: 1 process(es) still running");
+
+    let log = record(&fs);
+    fs.write("counter", &[counter(), counter()].concat());
+    assert_eq!(boot(&fs, b"").replay(&log),
+               Err("counter: not the recorded program".to_string()));
+}
+
+/// Waits for the processes killed by a failed replay to be freed.
+fn wait_for_no_procs(sys: &System) {
+    for _ in 0..1000 {
+        if sys.get_proc_ids().is_empty() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("processes left: {:?}", sys.get_proc_ids());
+}
+
+#[test]
+fn failed_replays_free_their_processes() {
+    let fs = test_fs("freed");
+    let mut log = record(&fs);
+    log.slices[1].proc_idx = 5;
+    let mut sys = boot(&fs, b"");
+    assert_eq!(sys.replay(&log), Err("Slice 1: no program 5".to_string()));
+    wait_for_no_procs(&sys);
+
+    let mut log = record(&fs);
+    log.slices[0].registers[0] ^= 1;
+    assert!(sys.replay(&log).is_err());
+    wait_for_no_procs(&sys);
+}
+
+#[test]
+fn file_names_with_spaces_are_recorded() {
+    let fs = test_fs("spaces");
+    fs.write("two  words ", &counter());
+    let mut sys = boot(&fs, b"a");
+    let log = sys.record(&["two  words ".to_string()]).unwrap();
+    assert_eq!(log.programs[0].file_name, "two  words ");
+    assert_eq!(RunLog::parse(&log.to_string()), Ok(log.clone()));
+    assert!(boot(&fs, b"").replay(&log).is_ok());
+    assert!(sys.record(&["two\nlines".to_string()]).is_err());
+}
+
+#[test]
+fn bad_logs_are_rejected() {
+    assert!(RunLog::parse("snoss-run-log 3\n").is_err());
+    assert!(RunLog::parse("snoss-run-log 2\nslice 0 1 0x0000 exit 0000\n").is_err());
+    assert_eq!(RunLog::parse("snoss-run-log 2\ninput\n"), Ok(RunLog::default()));
+    assert!(RunLog::parse("snoss-run-log 2\ninput 616\n").is_err());
+    assert!(RunLog::parse("snoss-run-log 2\ninput 6é\n").is_err());
+    assert!(RunLog::parse("snoss-run-log 2\nprogram 00000000000000ff 4 two words\n").is_err());
+    let log = RunLog::parse("snoss-run-log 2\nprogram 00000000000000ff 9 two words\n").unwrap();
+    assert_eq!(log.programs[0].file_name, "two words");
+    let log = RunLog::parse("snoss-run-log 1\nprogram 00000000000000ff counter\n").unwrap();
+    assert_eq!(log.programs[0].file_name, "counter");
+}
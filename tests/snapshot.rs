@@ -0,0 +1,149 @@
+extern crate snoss;
+
+mod common;
+
+use std::fs;
+use snoss::{CacheStats, Config, Console, Executable, ExecResult, ExitedProc, ProcSnapshot,
+            ProcessStatus, SectionKind, Snapshot, Symbol};
+use common::{assemble, TestFs};
+
+/// Grows the heap, stores 'A' to it and to the stack, then prints both.
+fn program() -> Vec<u8> {
+    assemble(&[[0x12, 0x01, 0x00, 0x40], // r1 = 64
+               [0x51, 0x01, 0x00, 0x02], // r2 = sbrk(r1)
+               [0x12, 0x00, 0x00, 0x41], // r0 = 'A'
+               [0x13, 0x00, 0x40, 0x00], // [0x0040] = r0
+               [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+               [0x41, 0x00, 0x41, 0x00], // print [0x0041]
+               [0x41, 0x00, 0x01, 0x00], // print [0x0001]
+               [0xff, 0x00, 0x00, 0x00]])
+}
+
+#[test]
+fn restored_processes_resume_where_they_were_saved() {
+    let fs = TestFs::new("snapshot");
+    let mut sys = fs.boot(Config::default());
+    let first = sys.load("prog", &program()).unwrap();
+    let second = sys.load("prog", &program()).unwrap();
+    for _ in 0..5 {
+        sys.step(second).unwrap();
+    }
+    sys.run(first).unwrap();
+    assert_eq!(sys.save("snap"), Ok(1));
+    let info = sys.get_proc_info(second).unwrap();
+    let mem = sys.read_mem(second, 0, 0x80).unwrap();
+
+    let mut sys = fs.boot(Config::default());
+    let other = sys.load("prog", &program()).unwrap();
+    assert_eq!(sys.restore("snap"), Ok(vec![second]));
+    assert_eq!(sys.get_proc_info(second), Ok(info));
+    assert_eq!(sys.read_mem(second, 0, 0x80), Ok(mem));
+    assert_eq!(sys.restore("snap"),
+               Err(format!("Process ID {} is not free.", second)));
+    sys.kill(other).unwrap();
+
+    let (console, out) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    assert_eq!(sys.run(second), Ok(ExecResult::Exit));
+    assert_eq!(out.get_string(), "AA");
+}
+
+#[test]
+fn saving_leaves_paging_alone() {
+    let fs = TestFs::new("snapshot-paging");
+    let mut exe = Executable::new(program());
+    exe.data = b"hi".to_vec();
+    let mut sys = fs.boot(Config::default());
+    let proc_id = sys.load("prog", &exe.to_bytes().unwrap()).unwrap();
+    sys.step(proc_id).unwrap();
+    // The data page was never brought in.
+    let vm_stats = sys.vm_stats();
+    sys.save("snap").unwrap();
+    assert_eq!(sys.vm_stats(), vm_stats);
+
+    let mut sys = fs.boot(Config::default());
+    sys.restore("snap").unwrap();
+    assert_eq!(sys.read_mem(proc_id, 0, 2), Ok(b"hi".to_vec()));
+}
+
+/// Reads two bytes from the console, printing each one.
+fn echo_program() -> Vec<u8> {
+    assemble(&[[0x42, 0x00, 0x00, 0x00], // read [0x0000]
+               [0x41, 0x00, 0x00, 0x00], // print [0x0000]
+               [0x42, 0x00, 0x00, 0x00], // read [0x0000]
+               [0x41, 0x00, 0x00, 0x00], // print [0x0000]
+               [0xff, 0x00, 0x00, 0x00]])
+}
+
+#[test]
+fn consoles_programs_and_exited_processes_are_restored() {
+    let fs = TestFs::new("snapshot-consoles");
+    let mut exe = Executable::new(echo_program());
+    exe.symbols = vec![Symbol {
+                           name: "main".to_string(),
+                           kind: SectionKind::Code,
+                           addr: 0,
+                       }];
+    let mut sys = fs.boot(Config::default());
+    let exited = sys.load("exited", &program()).unwrap();
+    sys.run(exited).unwrap();
+    let proc_id = sys.load("echo", &exe.to_bytes().unwrap()).unwrap();
+    let (console, out) = Console::in_memory(b"ab".to_vec());
+    sys.attach_console(proc_id, console).unwrap();
+    sys.step(proc_id).unwrap();
+    sys.step(proc_id).unwrap();
+    assert_eq!(out.get_string(), "a");
+    sys.save("snap").unwrap();
+
+    let mut sys = fs.boot(Config::default());
+    let (console, out) = Console::in_memory(vec![]);
+    sys.set_console(console);
+    assert_eq!(sys.restore("snap"), Ok(vec![proc_id]));
+    assert_eq!(sys.get_exited_procs()[0].exe_file_name, "exited");
+    sys.save("again").unwrap();
+    let snapshot = Snapshot::parse(&fs::read(fs.get_path().join("again")).unwrap()).unwrap();
+    assert_eq!(snapshot.procs[0].exe.symbols, exe.symbols);
+    assert_eq!(snapshot.procs[0].console_input, Some(b"b".to_vec()));
+    // Started as the shell's `restore` does, reading its console.
+    let handle = sys.start(proc_id, true).unwrap();
+    assert_eq!(handle.join().unwrap(), ExecResult::Exit);
+    assert_eq!(out.get_string(), "b");
+}
+
+#[test]
+fn snapshots_round_trip_and_bad_ones_are_rejected() {
+    let mut exe = Executable::new(program());
+    exe.entry = 4;
+    let snapshot = Snapshot {
+        num_registers: 2,
+        stack_len: 4,
+        procs: vec![ProcSnapshot {
+                        id: 3,
+                        status: ProcessStatus::Blocked,
+                        cycles: 0x1_0000_0002,
+                        exe_file_name: "prog".to_string(),
+                        instr_ptr: 4,
+                        registers: vec![1, 2],
+                        exe,
+                        data_seg: vec![0, 1, 2, 3],
+                        heap_len: 0,
+                        console_input: Some(b"in".to_vec()),
+                    }],
+        exited: vec![ExitedProc {
+                         id: 1,
+                         exe_file_name: "done".to_string(),
+                         cycles: 7,
+                         cache_stats: CacheStats { hits: 2, misses: 1 },
+                     }],
+    };
+    let bytes = snapshot.to_bytes().unwrap();
+    assert_eq!(Snapshot::parse(&bytes), Ok(snapshot.clone()));
+    assert_eq!(ProcSnapshot::parse(&snapshot.procs[0].to_bytes().unwrap()),
+               Ok(snapshot.procs[0].clone()));
+    assert_eq!(Snapshot::parse(&bytes[..bytes.len() - 1]),
+               Err("Truncated snapshot.".to_string()));
+    assert_eq!(Snapshot::parse(&program()), Err("Not a snapshot.".to_string()));
+    let mut bytes = bytes;
+    bytes[5] = 4;
+    assert!(Snapshot::parse(&bytes).unwrap_err().starts_with("Unsupported snapshot version 4"));
+}
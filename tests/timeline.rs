@@ -0,0 +1,103 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Console, Event, EventKind, Timeline, MAX_TIMELINE_EVENTS};
+use common::{assemble, TestFs};
+
+#[test]
+fn executors_record_slices_and_waits_for_input() {
+    let fs = TestFs::new("timeline");
+    let mut sys = fs.boot(Config::default());
+    assert!(sys.get_timeline().is_none());
+    sys.set_timeline(Some(Timeline::new()));
+    sys.set_console(Console::in_memory(b"ab".to_vec()).0);
+    let read = [0x42, 0x00, 0x00, 0x00];
+    let exit = [0xff, 0x00, 0x00, 0x00];
+    let pid = sys.load("read", &assemble(&[read, exit])).unwrap();
+    sys.run(pid).unwrap();
+    let events = sys.get_timeline().unwrap().get_events();
+    let kinds = events.iter().map(|event| event.kind).collect::<Vec<EventKind>>();
+    // How many slices the program takes depends on the host.
+    let waits = kinds.iter()
+        .filter(|kind| **kind == EventKind::Block || **kind == EventKind::Wakeup)
+        .collect::<Vec<&EventKind>>();
+    assert_eq!(waits, vec![&EventKind::Block, &EventKind::Wakeup]);
+    assert_eq!(kinds.first(), Some(&EventKind::SliceStart));
+    assert!(kinds.ends_with(&[EventKind::SliceEnd, EventKind::Exit]));
+
+    // The PID is free again, but the second run gets a row of its own.
+    let other_pid = sys.load("read", &assemble(&[read, exit])).unwrap();
+    sys.run(other_pid).unwrap();
+    let events = sys.get_timeline().unwrap().get_events();
+    let first = events.first().map(|event| event.load_seq);
+    let last = events.last().map(|event| event.load_seq);
+    assert_ne!(first, last);
+}
+
+fn timeline() -> Timeline {
+    let timeline = Timeline::new();
+    timeline.record_at(0, 0, "a", EventKind::SliceStart, 0);
+    timeline.record_at(0, 0, "a", EventKind::SliceEnd, 40);
+    timeline.record_at(1, 1, "b", EventKind::SliceStart, 40);
+    timeline.record_at(1, 1, "b", EventKind::Block, 50);
+    timeline.record_at(1, 1, "b", EventKind::Wakeup, 70);
+    timeline.record_at(1, 1, "b", EventKind::SliceEnd, 80);
+    timeline.record_at(1, 1, "b", EventKind::Exit, 80);
+    timeline
+}
+
+#[test]
+fn timelines_are_drawn_as_gantt_charts() {
+    assert_eq!(timeline().to_gantt_chart(8),
+               "80 us, 10 us per column\n\
+                0 a |#####...|\n\
+                1 b |....#bbb|\n\
+                # running, b blocked on input, . waiting");
+    assert_eq!(Timeline::new().to_gantt_chart(8), "No events recorded.");
+}
+
+#[test]
+fn timelines_are_exported_as_chrome_traces() {
+    let trace = timeline().to_chrome_trace();
+    assert!(trace.starts_with("{\"traceEvents\":[\n"));
+    assert!(trace.contains("{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":1,\
+                            \"args\":{\"name\":\"1 b\"}}"));
+    assert!(trace.contains("{\"name\":\"blocked\",\"ph\":\"B\",\"pid\":1,\"tid\":1,\"ts\":50}"));
+    assert!(trace.contains("{\"name\":\"exit\",\"ph\":\"i\",\"pid\":1,\"tid\":1,\"ts\":80}"));
+}
+
+#[test]
+fn processes_that_reuse_a_pid_get_rows_of_their_own() {
+    let timeline = timeline();
+    timeline.record_at(0, 2, "c", EventKind::SliceStart, 80);
+    timeline.record_at(0, 2, "c", EventKind::SliceEnd, 80);
+    assert_eq!(timeline.to_gantt_chart(8),
+               "80 us, 10 us per column\n\
+                0 a |#####...|\n\
+                1 b |....#bbb|\n\
+                0 c |.......#|\n\
+                # running, b blocked on input, . waiting");
+    assert!(timeline.to_chrome_trace()
+        .contains("{\"name\":\"running\",\"ph\":\"B\",\"pid\":1,\"tid\":2,\"ts\":80}"));
+}
+
+#[test]
+fn ends_whose_beginning_was_dropped_are_not_exported() {
+    let timeline = Timeline::new();
+    timeline.record_at(0, 0, "a", EventKind::SliceStart, 0);
+    for idx in 0..MAX_TIMELINE_EVENTS / 2 {
+        timeline.record_at(1, 1, "b", EventKind::SliceStart, idx as i64);
+        timeline.record_at(1, 1, "b", EventKind::SliceEnd, idx as i64);
+    }
+    timeline.record_at(0, 0, "a", EventKind::SliceEnd, 0x10000);
+    let events = timeline.get_events();
+    assert_eq!(events.last(),
+               Some(&Event {
+                   proc_id: 0,
+                   load_seq: 0,
+                   kind: EventKind::SliceEnd,
+                   time_us: 0x10000,
+               }));
+    assert!(!timeline.to_chrome_trace().contains("\"tid\":0,\"ts\""));
+}
@@ -0,0 +1,74 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Console, OutputBuffer, TraceFilter, Tracer};
+use common::{assemble, TestFs};
+
+/// Stores `r0 + r1` at data address 0, reads a byte to address 2, then exits.
+fn program() -> Vec<u8> {
+    assemble(&[[0x12, 0x00, 0x00, 0x29], // r0 = 41
+               [0x12, 0x01, 0x00, 0x01], // r1 = 1
+               [0x21, 0x00, 0x01, 0x00], // r0 = r0 + r1
+               [0x13, 0x00, 0x00, 0x00], // [0x0000] = r0
+               [0x42, 0x00, 0x02, 0x00], // [0x0002] = a byte of input
+               [0xff, 0x00, 0x00, 0x00]])
+}
+
+/// Runs two processes of `program`, tracing with `filter`, and returns the trace.
+fn trace(name: &str, filter: &str) -> String {
+    let fs = TestFs::new(&format!("trace-{}", name));
+    let mut sys = fs.boot(Config::default());
+    sys.set_console(Console::in_memory(b"ab".to_vec()).0);
+    let out = OutputBuffer::default();
+    let filter = filter.parse::<TraceFilter>().unwrap();
+    sys.set_tracer(Some(Tracer::new(Box::new(out.clone()), filter)));
+    let pids = (0..2).map(|_| sys.load("prog", &program()).unwrap()).collect::<Vec<u16>>();
+    for pid in pids {
+        sys.run(pid).unwrap();
+    }
+    out.get_string()
+}
+
+#[test]
+fn every_instruction_is_recorded_with_its_writes() {
+    assert_eq!(trace("all", ""),
+               "0 0x0000  LoadConstant r0, 41  r0=0x0029\n\
+                0 0x0004  LoadConstant r1, 1  r1=0x0001\n\
+                0 0x0008  Add r0, r1, r0  r0=0x002a\n\
+                0 0x000c  Store [0x0000], r0  [0x0000]=0x002a\n\
+                0 0x0010  CharRead [0x0002]  [0x0002]=0x61\n\
+                0 0x0014  Exit  -> Exit\n\
+                1 0x0000  LoadConstant r0, 41  r0=0x0029\n\
+                1 0x0004  LoadConstant r1, 1  r1=0x0001\n\
+                1 0x0008  Add r0, r1, r0  r0=0x002a\n\
+                1 0x000c  Store [0x0000], r0  [0x0000]=0x002a\n\
+                1 0x0010  CharRead [0x0002]  [0x0002]=0x62\n\
+                1 0x0014  Exit  -> Exit\n");
+}
+
+#[test]
+fn filters_select_processes_and_addresses() {
+    assert_eq!(trace("filtered", "pid=1,addrs=0x08..0x10"),
+               "1 0x0008  Add r0, r1, r0  r0=0x002a\n\
+                1 0x000c  Store [0x0000], r0  [0x0000]=0x002a\n");
+    assert!("pid=x".parse::<TraceFilter>().is_err());
+    assert!("addrs=4".parse::<TraceFilter>().is_err());
+}
+
+#[test]
+fn tracing_leaves_paging_unchanged() {
+    let paging = |name: &str, is_traced: bool| {
+        let fs = TestFs::new(name);
+        let mut sys = fs.boot(Config::default());
+        sys.set_console(Console::in_memory(b"ab".to_vec()).0);
+        if is_traced {
+            let filter = "".parse::<TraceFilter>().unwrap();
+            sys.set_tracer(Some(Tracer::new(Box::new(OutputBuffer::default()), filter)));
+        }
+        let pid = sys.load("prog", &program()).unwrap();
+        sys.run(pid).unwrap();
+        sys.vm_stats()
+    };
+    assert_eq!(paging("trace-paging-on", true), paging("trace-paging-off", false));
+}
@@ -0,0 +1,42 @@
+extern crate snoss;
+
+mod common;
+
+use snoss::{Config, Executable};
+use common::{assemble, TestFs};
+
+#[test]
+fn bad_programs_are_not_loaded() {
+    let fs = TestFs::new("verify-bad");
+    let mut sys = fs.boot(Config::default());
+    let code = assemble(&[[0x00, 0x00, 0x00, 0x00], // invalid opcode
+                          [0x21, 0x00, 0x06, 0x01], // register 6 of 6
+                          [0x31, 0x00, 0x02, 0x00], // misaligned jump
+                          [0x32, 0x00, 0x20, 0x00], // jump past the end
+                          [0x41, 0x00, 0x42, 0x00], // past data (2) + stack (64)
+                          [0x13, 0x00, 0x41, 0x00], // a word straddling the end
+                          [0xff, 0x00, 0x00, 0x00]]);
+    let mut exe = Executable::new(code);
+    exe.data = vec![0, 0];
+    let err = sys.load("bad", &exe.to_bytes().unwrap()).unwrap_err();
+    assert_eq!(err,
+               "bad: 6 problem(s)\n\
+                bad: 0x0000: invalid opcode 0x00\n\
+                bad: 0x0004: register 6 does not exist (6 registers)\n\
+                bad: 0x0008: jump target 0x0002 is not an instruction\n\
+                bad: 0x000c: jump target 0x0020 is not an instruction\n\
+                bad: 0x0010: address 0x0042 is past the data segment (66 bytes)\n\
+                bad: 0x0014: address 0x0041 is past the data segment (66 bytes)");
+    assert!(sys.get_proc_ids().is_empty());
+}
+
+#[test]
+fn programs_calling_sbrk_may_address_the_heap() {
+    let fs = TestFs::new("verify-sbrk");
+    let mut sys = fs.boot(Config::default());
+    let print_heap = [0x41, 0x00, 0x80, 0x00];
+    let exit = [0xff, 0x00, 0x00, 0x00];
+    assert!(sys.load("no-sbrk", &assemble(&[print_heap, exit])).is_err());
+    let sbrk = [0x51, 0x00, 0x00, 0x01];
+    assert!(sys.load("sbrk", &assemble(&[sbrk, print_heap, exit])).is_ok());
+}
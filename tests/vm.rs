@@ -0,0 +1,67 @@
+extern crate snoss;
+
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use snoss::{ExecResult, System};
+use common::{store_a_then, TestFs};
+
+#[test]
+fn the_swap_file_is_hidden_in_the_file_system() {
+    let fs = TestFs::new("vm-swap");
+    fs.write("prog.sno", &[0xff, 0x00, 0x00, 0x00]);
+    let sys = fs.boot(fs.config());
+    let names = fs::read_dir(fs.get_path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<String>>();
+    assert_eq!(names.len(), 2);
+    assert!(names.iter().any(|name| name.starts_with(".swap-")));
+    assert_eq!(sys.list_files(), "prog.sno\t");
+    drop(sys);
+    // The memory goes once the thread freeing processes has stopped too.
+    let mut num_files = 2;
+    for _ in 0..100 {
+        num_files = fs::read_dir(fs.get_path()).unwrap().count();
+        if num_files == 1 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(num_files, 1);
+}
+
+/// Runs `program` to its end and returns the paging statistics.
+fn run(sys: &mut System, program: &[u8]) -> String {
+    let proc_id = sys.load("prog", program).unwrap();
+    assert_eq!(sys.run(proc_id), Ok(ExecResult::Exit));
+    sys.vm_stats()
+}
+
+#[test]
+fn resetting_the_statistics_measures_a_run_on_its_own() {
+    let fs = TestFs::new("vm-reset");
+    let mut sys = fs.boot(fs.config());
+    // Storing faults the data page in.
+    let program = store_a_then(&[]);
+    let first = run(&mut sys, &program);
+    let both = run(&mut sys, &program);
+    assert_ne!(first, both);
+    sys.reset_vm_stats();
+    assert!(sys.vm_stats().contains("0 page faults, 0 evictions"));
+    assert!(sys.vm_stats().ends_with("trace: 0 references"));
+    assert_eq!(run(&mut sys, &program), first);
+}
+
+#[test]
+fn every_entry_of_the_process_table_fits_in_ram() {
+    let fs = TestFs::new("vm-procs");
+    let mut sys = fs.boot(fs.config());
+    for _ in 0..16 {
+        sys.load("prog", &store_a_then(&[])).unwrap();
+    }
+    assert_eq!(sys.get_proc_ids().len(), 16);
+    assert!(sys.load("prog", &store_a_then(&[])).is_err());
+}